@@ -1,21 +1,47 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use loss72_platemaker_construct::{copy_dir_recursively, copy_files, copy_individual_file};
+use chrono::Datelike;
+use loss72_platemaker_construct::{
+    Change, ConstructFile, ConstructionError, ConstructionPlan, copy_changes, copy_dir_recursively,
+    copy_file_pairs, copy_files, copy_individual_file, copy_pairs_of_dir,
+};
 use loss72_platemaker_core::{
     fs::{Directory, File},
-    log, model::GenerationContext,
+    log,
+    model::{Article, GenerationContext},
+    util::content_hash,
 };
-use loss72_platemaker_markdown::{MarkdownProcessError, parse_markdown};
+use loss72_platemaker_markdown::{MarkdownProcessError, parse_markdown, syntax_theme_css};
 use loss72_platemaker_structure::{
-    ArticleFile, ArticleGroup, AssetFile, ContentDirectory,
-    template::{is_template_file, template_file_paths},
+    ArticleFile, ArticleGroup, AssetFile, ContentDirectory, ContentError,
+    template::{is_template_file, template_files, template_pages},
 };
 use loss72_platemaker_website::{
-    WebsiteGenerationError, generate_article_html, generate_index_html, get_webpage_construction,
-    load_templates,
+    ArticlePage, AssetManifest, BUILD_MANIFEST_FILE, BuildManifest, BuildTimings, FEED_FILE,
+    ROBOTS_FILE, SITEMAP_FILE, SiteData, WebPageHtmlTemplates, WebsiteGenerationError,
+    article_references, find_broken_links, generate_archive_pages, generate_build_manifest,
+    generate_each_article_page, generate_feed, generate_group_index, generate_index_html,
+    generate_not_found_page, generate_redirect_pages, generate_robots_txt, generate_series_pages,
+    generate_sitemap, generate_tag_pages, get_webpage_construction, load_data, load_templates,
+    minify_html, referenced_assets,
+};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    bundle::{bundle_inputs, write_bundles},
+    cache::BuildCache,
+    compress::precompress_outputs,
+    config::Configuration,
+    error::report_error,
+    fingerprint::{
+        apply_asset_manifest, asset_manifest_changed, collect_asset_manifest, source_build_id,
+    },
+    images::{generate_image_variants, localize_remote_images},
+    siblings::copy_sibling_assets,
 };
-
-use crate::{config::Configuration, error::report_error};
 
 #[derive(Debug, thiserror::Error)]
 pub enum TaskError {
@@ -27,97 +53,688 @@ pub enum TaskError {
 
     #[error(transparent)]
     FileCopy(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Content(#[from] ContentError),
+
+    #[error(transparent)]
+    Construction(#[from] ConstructionError),
+
+    #[error("{0} links in the pages are broken")]
+    BrokenLinks(usize),
 }
 
 pub type TaskResult<T> = Result<T, TaskError>;
 
-pub fn run_all_build_steps(config: &Configuration, ctx: &GenerationContext) -> TaskResult<()> {
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub built: usize,
+    // Articles which failed to parse or render, by the path relative to the article directory
+    pub failures: Vec<(PathBuf, TaskError)>,
+    // Only the full builds have the scan and the total
+    pub timings: BuildTimings,
+    // of the full builds, written with the timings at last
+    pub manifest: Option<BuildManifest>,
+}
+
+impl BuildReport {
+    pub fn log_summary(&self) {
+        log!(section: "Build summary");
+        log!(ok: "Built {} articles", self.built);
+
+        let BuildTimings {
+            scan,
+            parse,
+            render,
+            write,
+            assets,
+            total,
+        } = self.timings;
+        if !total.is_zero() {
+            log!(
+                step: "Took {}ms: scan {}ms, parse {}ms, render {}ms, write {}ms, assets {}ms",
+                total.as_millis(), scan.as_millis(), parse.as_millis(), render.as_millis(), write.as_millis(), assets.as_millis()
+            );
+        }
+
+        if self.failures.is_empty() {
+            return;
+        }
+
+        log!(warn: "{} articles failed:", self.failures.len());
+        for (path, error) in &self.failures {
+            log!(warn: "  ./{}\n{}", path.display(), error);
+        }
+    }
+}
+
+pub fn run_all_build_steps(
+    config: &Configuration,
+    ctx: &GenerationContext,
+) -> TaskResult<BuildReport> {
     log!(job_start: "Building all articles in {}", config.article_md_dir.path().display());
+    log!(step: "Jobs for the parallel steps: {}", rayon::current_num_threads());
 
-    let content_dir = ContentDirectory::new(&config.article_md_dir)?;
+    let started = Instant::now();
+    let content_dir = ContentDirectory::new(
+        &config.article_md_dir,
+        &config.article_filename_pattern,
+        &config.assets_dir,
+    )?;
 
     log!(ok: "Discovered {} articles", content_dir.markdown_files.len());
 
-    let result = Ok(())
-        .and_then(|_| build_files(config, &content_dir.markdown_files, true, &ctx))
-        .and_then(|_| copy_template_files(config))
-        .and_then(|_| copy_asset_files(config, &content_dir.article_group));
+    let result = collect_asset_manifest(config, ctx).and_then(|assets| {
+        let scan = started.elapsed();
+        let mut report = build_files(config, &content_dir.markdown_files, true, ctx, &assets)?;
+
+        let copying = Instant::now();
+        copy_template_files(config, ctx, &assets)?;
+        write_robots_txt(config)?;
+        copy_asset_files(config, &content_dir.article_group)?;
+        apply_asset_manifest(config, &assets)?;
+        report.timings.assets += copying.elapsed();
 
-    if result.is_ok() {
-        log!(job_end: "Successfully built all articles in {}", config.article_md_dir.path().display())
+        // Until the manifest, which is compressed as the other outputs
+        report.timings.scan = scan;
+        report.timings.total = started.elapsed();
+        write_build_manifest(config, &mut report)?;
+
+        check_links(config)?;
+        precompress_outputs(config, ctx)?;
+
+        Ok(report)
+    });
+
+    if let Ok(report) = &result {
+        report.log_summary();
+
+        if report.failures.is_empty() {
+            log!(job_end: "Successfully built all articles in {}", config.article_md_dir.path().display())
+        }
     }
 
     result
 }
 
+// For watching without `--build-first`: builds only the articles which are not in the cache as
+// they are now or of which the pages are missing, and copies the files changed since then.
+// Everything is built when there is no cache to compare with, or articles are removed.
+pub fn build_stale_files(
+    config: &Configuration,
+    ctx: &GenerationContext,
+) -> TaskResult<BuildReport> {
+    log!(job_start: "Checking the outputs in {}", config.destination.path().display());
+
+    let ContentDirectory {
+        markdown_files,
+        article_group,
+        ..
+    } = ContentDirectory::new(
+        &config.article_md_dir,
+        &config.article_filename_pattern,
+        &config.assets_dir,
+    )?;
+
+    let mut cache = if config.no_cache {
+        BuildCache::default()
+    } else {
+        BuildCache::load(&config.destination)
+    };
+    cache.validate(&config.html_template_dir, ctx)?;
+
+    if cache.is_empty() {
+        log!(warn: "No build cache to compare the outputs with! Rebuilding all articles.");
+        return run_all_build_steps(config, ctx);
+    }
+
+    let sources = markdown_files
+        .iter()
+        .map(|file| file.file().path().to_path_buf())
+        .collect::<Vec<_>>();
+    let removed = cache
+        .sources()
+        .filter(|path| !sources.iter().any(|source| source == path))
+        .map(Path::to_path_buf)
+        .collect::<Vec<_>>();
+    if remove_outputs_of_articles(config, &removed)? {
+        log!(warn: "Articles are removed since the last build! Rebuilding all articles.");
+        return run_all_build_steps(config, ctx);
+    }
+
+    let total = markdown_files.len();
+    let stale = markdown_files
+        .into_iter()
+        .filter(|file| {
+            let cached = std::fs::read(file.file().path()).is_ok_and(|content| {
+                cache
+                    .get(file.file().path(), &content_hash(content))
+                    .is_some()
+            });
+            !cached
+                || !config
+                    .destination
+                    .path()
+                    .join(config.permalink.path(&file.id))
+                    .is_file()
+        })
+        .collect::<Vec<_>>();
+    log!(ok: "{} of {} articles are stale", stale.len(), total);
+
+    let assets = collect_asset_manifest(config, ctx)?;
+    let report = build_files(config, &stale, false, ctx, &assets)?;
+    copy_template_files(config, ctx, &assets)?;
+    copy_asset_files(config, &article_group)?;
+    apply_asset_manifest(config, &assets)?;
+    precompress_outputs(config, ctx)?;
+
+    log!(job_end: "Updated {} stale articles", report.built);
+
+    Ok(report)
+}
+
 pub fn build_files(
     config: &Configuration,
     files: &[ArticleFile],
     full_build: bool,
     ctx: &GenerationContext,
-) -> TaskResult<()> {
-    let mut files = files.iter().peekable();
-
-    if files.peek().is_none() {
-        return Ok(());
+    assets: &AssetManifest,
+) -> TaskResult<BuildReport> {
+    if files.is_empty() {
+        return Ok(BuildReport::default());
     }
 
+    // The templates and the cache are counted in the render
+    let loading = Instant::now();
     log!(section: "Loading HTML from {}", config.html_template_dir.path().display());
-    let html_templates = load_templates(&config.html_template_dir)?;
+    let data = match &config.data_dir {
+        Some(data_dir) => {
+            log!(section: "Loading data from {}", data_dir.path().display());
+            load_data(data_dir)?
+        }
+        None => SiteData::default(),
+    };
+    let mut html_templates = load_templates(&config.html_template_dir, &data)?;
+    html_templates.syntax_style = syntax_theme_css(ctx.syntax_theme).unwrap_or_default();
+    html_templates.assets = assets.clone();
+    html_templates.permalink = config.permalink.clone();
+    html_templates.related_count = config.related_articles;
+    html_templates.assets_dir = config.assets_dir.clone();
+    html_templates.back_to_top_after = config.back_to_top_after;
+    html_templates.site = config.site.clone();
+    html_templates.base_url = config.base_url.clone();
+    html_templates.url_style = config.url_style;
+    html_templates.default_permalink = config.article_base.permalink();
+    html_templates.date_format = config.date_format.clone();
+    html_templates.month_date_format = config.month_date_format.clone();
+
+    let mut cache = if config.no_cache {
+        BuildCache::default()
+    } else {
+        BuildCache::load(&config.destination)
+    };
+    cache.validate(&config.html_template_dir, ctx)?;
+
+    if !full_build && cache.is_empty() {
+        log!(warn: "No build cache to list the other articles from! Rebuilding all articles.");
+        return run_all_build_steps(config, ctx);
+    }
 
-    let articles = files
-        .filter_map(|file| parse_markdown(file).inspect_err(report_error).ok())
+    // Once for each full build, which the pages of the incremental builds share with the others
+    let build_id = match cache.build_id() {
+        Some(build_id) if !full_build => build_id.to_string(),
+        _ => source_build_id(config)?,
+    };
+    cache.set_build_id(build_id.clone());
+    let ctx = &GenerationContext {
+        build_id: Some(build_id),
+        ..ctx.clone()
+    };
+
+    let source_paths = files
+        .iter()
+        .map(|file| file.file().path().to_path_buf())
+        .collect::<Vec<_>>();
+
+    let loaded = loading.elapsed();
+    let parsing = Instant::now();
+    let mut report = BuildReport::default();
+    let mut parsed = vec![];
+    let mut links_changed = false;
+    let results = files
+        .par_iter()
+        .map(|file| parse_markdown_cached(file, &cache, ctx))
+        .collect::<Vec<_>>();
+    for (file, result) in files.iter().zip(results) {
+        match result {
+            Ok((article, None)) => parsed.push(article),
+            Ok((article, Some(hash))) => {
+                let path = file.file().path();
+                let previous = cache
+                    .previous(path)
+                    .map(|previous| article_references(&previous.content));
+                links_changed |=
+                    previous.unwrap_or_default() != article_references(&article.content);
+                cache.insert(path.to_path_buf(), hash, article.clone());
+                parsed.push(article);
+            }
+            Err(error) => {
+                report_error(&error);
+                report
+                    .failures
+                    .push((file.relative_path.clone(), error.into()));
+            }
+        }
+    }
+
+    // The backlinks of the articles which are linked to or not any more are on their pages
+    if !full_build && links_changed {
+        log!(warn: "Links between the articles are changed! Rebuilding all articles.");
+        return run_all_build_steps(config, ctx);
+    }
+
+    let today = today(config);
+    let mut articles = parsed
+        .into_iter()
+        .filter(|article| {
+            let publishes = ctx.publishes(&article.metadata);
+            if !publishes {
+                log!(step: "Skipping draft '{}' in release build", article.id.slug);
+            }
+            publishes
+        })
+        .filter(|article| {
+            let publishes = ctx.publishes_on(&article.id, today);
+            if !publishes {
+                log!(step: "Skipping '{}' dated in the future in release build (compared with today in the local time zone)", article.id.slug);
+            }
+            publishes
+        })
         .collect::<Vec<_>>();
 
     log!(ok: "Built {} articles", articles.len());
+    report.timings.parse = parsing.elapsed();
+
+    let imaging = Instant::now();
+    localize_remote_images(config, &mut articles)?;
+    generate_image_variants(config, &mut articles, &mut cache)?;
+    copy_sibling_assets(config, files, &mut articles)?;
+    report.timings.assets += imaging.elapsed();
+
+    let rendering = Instant::now();
     log!(section: "Generating HTML contents for articles");
 
     if ctx.release {
         log!(step: "Using release build!");
     }
 
-    let htmls = articles
-        .iter()
-        .map(|article| generate_article_html(&html_templates, article, ctx))
-        .collect::<Result<Vec<_>, _>>();
+    warn_missing_template_assets(&html_templates, files, &articles);
 
-    let mut htmls = match htmls {
-        Ok(htmls) => {
-            log!(ok: "Generated all {} article pages", articles.len());
-            htmls
-        }
-        Err(error) => {
-            return Err(error.into());
-        }
+    // The parts of the series which are not built this time are in the cache
+    let others = if full_build {
+        vec![]
+    } else {
+        cache
+            .articles()
+            .filter(|article| {
+                ctx.publishes(&article.metadata) && ctx.publishes_on(&article.id, today)
+            })
+            .collect::<Vec<_>>()
     };
 
+    let mut htmls = vec![];
+    for (article, result) in articles.iter().zip(generate_each_article_page(
+        &html_templates,
+        &articles,
+        &others,
+        ctx,
+    )) {
+        match result {
+            Ok(page) => htmls.push(page),
+            Err(error) => {
+                report_error(&error);
+                let path = files.iter().find(|file| file.id == article.id).map_or_else(
+                    || PathBuf::from(&article.id.slug),
+                    |file| file.relative_path.clone(),
+                );
+                report.failures.push((path, error.into()));
+            }
+        }
+    }
+
+    report.built = htmls.len();
+    log!(ok: "Generated {} article pages", htmls.len());
+
     htmls.sort_by(|left, right| left.article.id.cmp(&right.article.id).reverse());
 
+    // Incremental builds list every article from the cache, which has the updated ones
+    // spliced in by now
     let index_page = if full_build {
-        Some(generate_index_html(&html_templates, htmls.as_slice(), ctx)?)
+        let mut listed = htmls.clone();
+        sort_index(config, &mut listed);
+
+        generate_index_html(&html_templates, &listed, ctx)?
+    } else {
+        let mut listed = cache
+            .articles()
+            .filter(|article| {
+                ctx.publishes(&article.metadata) && ctx.publishes_on(&article.id, today)
+            })
+            .map(|article| ArticlePage::listing(&html_templates, article))
+            .collect::<Vec<_>>();
+        sort_index(config, &mut listed);
+
+        generate_index_html(&html_templates, &listed, ctx)?
+    };
+
+    let feed = if full_build {
+        generate_feed(&html_templates, htmls.as_slice(), config.feed)?
+    } else {
+        None
+    };
+
+    let tag_pages = if full_build {
+        generate_tag_pages(&html_templates, htmls.as_slice(), ctx)?
+    } else {
+        vec![]
+    };
+
+    let archive_pages = if full_build {
+        generate_archive_pages(&html_templates, htmls.as_slice(), ctx)?
+    } else {
+        vec![]
+    };
+
+    let series_pages = if full_build {
+        generate_series_pages(&html_templates, htmls.as_slice(), ctx)?
+    } else {
+        vec![]
+    };
+
+    let group_pages = if full_build {
+        generate_group_index(&html_templates, htmls.as_slice(), ctx)?
+    } else {
+        vec![]
+    };
+
+    let not_found_page = if full_build {
+        generate_not_found_page(&html_templates, ctx)?
     } else {
         None
     };
 
     log!(section: "Writing pages to the file system");
 
-    let construction = get_webpage_construction(index_page.as_ref(), htmls.as_slice());
-    let plan = construction.plan(config.destination.path());
+    let mut root_files = vec![(&index_page).into()];
+    if let Some(feed) = &feed {
+        root_files.push(ConstructFile {
+            path: Path::new(FEED_FILE),
+            content: feed,
+        });
+    }
+    root_files.extend(tag_pages.iter().map(ConstructFile::from));
+    root_files.extend(archive_pages.iter().map(ConstructFile::from));
+    root_files.extend(group_pages.iter().map(ConstructFile::from));
+    root_files.extend(series_pages.iter().map(ConstructFile::from));
+    root_files.extend(not_found_page.as_ref().map(ConstructFile::from));
+
+    // The feed and the others are not generated in the incremental builds, but are still there
+    let reserved = root_files
+        .iter()
+        .map(|file| file.path)
+        .chain([FEED_FILE, SITEMAP_FILE, ROBOTS_FILE].map(Path::new))
+        .collect::<Vec<_>>();
+    let redirects = generate_redirect_pages(&html_templates, htmls.as_slice(), &reserved)?;
+
+    let construction = get_webpage_construction(root_files, htmls.as_slice(), &redirects);
+    let mut plan = construction.plan(config.destination.path());
+
+    let minified = if ctx.minifies() {
+        log!(step: "Minifying HTML");
+        plan.files
+            .iter()
+            .map(|(path, content)| {
+                path.extension()
+                    .is_some_and(|ext| ext == "html")
+                    .then(|| minify_html(content))
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    for ((_, content), minified) in plan.files.iter_mut().zip(&minified) {
+        if let Some(minified) = minified {
+            *content = minified;
+        }
+    }
+
+    let sitemap = match (full_build, &config.base_url) {
+        (true, Some(base_url)) => Some(generate_sitemap(
+            &plan,
+            config.destination.path(),
+            &template_pages(&config.html_template_dir)?,
+            htmls.as_slice(),
+            &redirects,
+            base_url,
+        )),
+        (true, None) => {
+            log!(warn: "`base_url` is not configured, skipping {}", SITEMAP_FILE);
+            None
+        }
+        (false, _) => None,
+    };
+    if let Some(sitemap) = &sitemap {
+        plan.files
+            .push((config.destination.path().join(SITEMAP_FILE), sitemap));
+    }
+
+    // Lists the pages above, which are all of them only in full builds
+    if full_build {
+        report.manifest = Some(generate_build_manifest(
+            &plan,
+            config.destination.path(),
+            htmls.as_slice(),
+        ));
+    }
+    report.timings.render = loaded + rendering.elapsed();
+
+    if config.dry_run {
+        log_changes(&plan.changes());
+        return Ok(report);
+    }
+
+    let writing = Instant::now();
     plan.execute()?;
 
+    if full_build {
+        cache.retain(&source_paths);
+    }
+    cache.save(&config.destination)?;
+    report.timings.write = writing.elapsed();
+
     log!(ok: "Wrote pages");
 
-    Ok(())
+    Ok(report)
+}
+
+fn write_build_manifest(config: &Configuration, report: &mut BuildReport) -> TaskResult<()> {
+    let Some(manifest) = &mut report.manifest else {
+        return Ok(());
+    };
+    manifest.timings = report.timings;
+
+    let content = serde_json::to_string_pretty(manifest).map_err(std::io::Error::from)?;
+    let plan = ConstructionPlan {
+        dirs: vec![],
+        files: vec![(
+            config.destination.path().join(BUILD_MANIFEST_FILE),
+            &content,
+        )],
+    };
+
+    if config.dry_run {
+        log_changes(&plan.changes());
+        return Ok(());
+    }
+
+    Ok(plan.execute()?)
+}
+
+// of `${asset:...}` in the templates, which are looked up in `assets_dir` next to the markdown
+fn warn_missing_template_assets(
+    html_templates: &WebPageHtmlTemplates,
+    files: &[ArticleFile],
+    articles: &[Article],
+) {
+    for article in articles {
+        let (Some(file), Ok(template)) = (
+            files.iter().find(|file| file.id == article.id),
+            html_templates.article_template(article),
+        ) else {
+            continue;
+        };
+        let assets_dir = file
+            .file()
+            .path()
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&html_templates.assets_dir);
+
+        for name in referenced_assets(template) {
+            if !assets_dir.join(&name).is_file() {
+                log!(warn: "${{asset:{}}} in the template is not in the assets of '{}'", name, article.id.slug);
+            }
+        }
+    }
+}
+
+// by `index_order`, with the pinned ones above the rest in the same order among them.
+// Only the index is, not the feed and the sitemap.
+fn sort_index(config: &Configuration, pages: &mut [ArticlePage]) {
+    pages.sort_by(|left, right| config.index_order.compare(left.article, right.article));
+    // Stable, so the order is kept among the pinned ones of the same `pin_order` and the others
+    pages.sort_by_key(|page| {
+        let metadata = &page.article.metadata;
+        (
+            !metadata.pinned,
+            metadata
+                .pinned
+                .then_some(metadata.pin_order.unwrap_or(u32::MAX)),
+        )
+    });
+}
+
+// in the configured time zone, which the dates of the articles are taken to be in
+fn today(config: &Configuration) -> (u32, u8, u8) {
+    let today = config.timezone.today();
+    (today.year_ce().1, today.month() as u8, today.day() as u8)
+}
+
+// Runs in parallel, so the newly parsed ones come with their hash to be cached
+fn parse_markdown_cached(
+    file: &ArticleFile,
+    cache: &BuildCache,
+    ctx: &GenerationContext,
+) -> Result<(Article, Option<String>), MarkdownProcessError> {
+    let hash = content_hash(std::fs::read(file.file().path())?);
+
+    if let Some(article) = cache.get(file.file().path(), &hash) {
+        log!(step: "Using cached ./{}", file.relative_path.display());
+        return Ok((article.clone(), None));
+    }
+
+    Ok((parse_markdown(file, ctx)?, Some(hash)))
+}
+
+// Every file of a dry run, which would be written
+pub(crate) fn log_changes(changes: &[(&Path, Change)]) {
+    for (path, change) in changes {
+        log!(step: "{:<9}  {}", change, path.display());
+    }
+
+    let count = |kind| changes.iter().filter(|(_, change)| *change == kind).count();
+    log!(ok: "{} new, {} changed, {} unchanged", count(Change::New), count(Change::Changed), count(Change::Unchanged));
 }
 
-pub fn copy_template_files(config: &Configuration) -> TaskResult<()> {
+// After everything is written, for the links to the assets and the template pages too
+pub fn check_links(config: &Configuration) -> TaskResult<()> {
+    if !config.check_links || config.dry_run {
+        return Ok(());
+    }
+
+    log!(section: "Checking links in the pages");
+
+    let broken = find_broken_links(&config.destination)?;
+    if broken.is_empty() {
+        log!(ok: "No broken links");
+        return Ok(());
+    }
+
+    for link in &broken {
+        log!(warn: "Broken link to {} in ./{}", link.href, link.page.display());
+    }
+
+    Err(TaskError::BrokenLinks(broken.len()))
+}
+
+pub fn copy_template_files(
+    config: &Configuration,
+    ctx: &GenerationContext,
+    assets: &AssetManifest,
+) -> TaskResult<()> {
     log!(section: "Copying files in template directory");
 
+    // The files in the bundles are only written in them
+    let mut excludes = template_files(&config.html_template_dir)?;
+    excludes.extend(bundle_inputs(config));
+
+    if config.dry_run {
+        let pairs = copy_pairs_of_dir(&config.html_template_dir, &config.destination, &excludes)?;
+        log_changes(&copy_changes(&pairs, config.force_assets));
+        return write_bundles(config, ctx);
+    }
+
     copy_dir_recursively(
         &config.html_template_dir,
         &config.destination,
-        &template_file_paths(),
+        &excludes,
+        config.force_assets,
     )?;
+    write_bundles(config, ctx)?;
+
+    if !assets.is_empty() {
+        for page in template_pages(&config.html_template_dir)? {
+            let dest = config.destination.path().join(&page);
+            let html = std::fs::read_to_string(&dest)?;
+            std::fs::write(
+                &dest,
+                assets.rewrite(&html, &format!("/{}", page.to_string_lossy())),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// Only on the full builds, unless the template directory has its own
+fn write_robots_txt(config: &Configuration) -> TaskResult<()> {
+    if config.html_template_dir.path().join(ROBOTS_FILE).is_file() {
+        log!(step: "Keeping {} in the template directory", ROBOTS_FILE);
+        return Ok(());
+    }
+
+    log!(section: "Generating {}", ROBOTS_FILE);
+    let robots = generate_robots_txt(config.base_url.as_deref(), &config.robots_disallow);
+    let plan = ConstructionPlan {
+        dirs: vec![],
+        files: vec![(config.destination.path().join(ROBOTS_FILE), &robots)],
+    };
+
+    if config.dry_run {
+        log_changes(&plan.changes());
+    } else {
+        plan.execute()?;
+    }
 
     Ok(())
 }
@@ -127,82 +744,206 @@ pub fn copy_asset_files(config: &Configuration, article_group: &[ArticleGroup])
 
     let directories = article_group
         .iter()
-        .flat_map(|group| {
-            let dir = Directory::new(config.article_md_dir.path().join(group.group_dir_path()));
-            let dir = match dir {
-                Ok(dir) => dir,
-                Err(e) => return Some(Err(e)),
-            };
-
-            dir.get_child("assets")
-                .map(|dir| dir.map(|dir| (dir, group)))
-        })
+        .map(|group| group.scan_asset_dirs(&config.article_md_dir, &config.assets_dir))
         .collect::<Result<Vec<_>, _>>()?;
 
-    for (dir, group) in &directories {
-        copy_dir_recursively(
-            dir,
-            &config.destination.get_or_mkdir_child(
-                Path::new(".")
-                    .join("articles")
-                    .join(group.group_dir_flat_path())
-                    .join("assets"),
-            )?,
-            &[],
-        )?;
+    let mut pairs = vec![];
+    for dir in directories.iter().flatten() {
+        let dest = if config.dry_run {
+            Directory::new_unchecked(
+                config
+                    .destination
+                    .path()
+                    .join(dir.destination_path(config.article_base.path())),
+            )
+        } else {
+            config
+                .destination
+                .get_or_mkdir_child(dir.destination_path(config.article_base.path()))?
+        };
+        pairs.extend(copy_pairs_of_dir(dir.directory(), &dest, &[])?);
     }
 
+    if config.dry_run {
+        log_changes(&copy_changes(&pairs, config.force_assets));
+        return Ok(());
+    }
+
+    let stats = copy_file_pairs(&pairs, config.force_assets, &|processed, total| {
+        // Every tenth of them, not to flood the log with large media folders
+        if processed == total || processed * 10 / total != (processed - 1) * 10 / total {
+            log!(step: "Processed {}/{} files", processed, total);
+        }
+    })?;
+
+    log!(ok: "Copied {} asset files, skipped {} unchanged", stats.copied, stats.skipped);
+
     Ok(())
 }
 
-pub fn copy_individual_template_files(config: &Configuration, files: &[File], ctx: &GenerationContext) -> TaskResult<()> {
+pub fn copy_individual_template_files(
+    config: &Configuration,
+    files: &[File],
+    ctx: &GenerationContext,
+) -> TaskResult<()> {
     if files.is_empty() {
         return Ok(());
     }
 
     log!(job_start: "Updating template files");
 
-    if files.iter().any(|file| {
+    if ctx.fingerprints() {
+        log!(warn: "Asset fingerprints may be changed! Rebuilding all articles.");
+        run_all_build_steps(config, ctx)?;
+        log!(job_end: "Updated template files");
+        return Ok(());
+    }
+
+    let (templates, files) = files.iter().cloned().partition::<Vec<_>, _>(|file| {
         is_template_file(
             file.path()
                 .strip_prefix(config.html_template_dir.path())
                 .unwrap_or(file.path()),
         )
-    }) {
+    });
+
+    // Including the partials, which are only expanded into the pages
+    if !templates.is_empty() {
         log!(warn: "Article page template file is updated! Rebuilding all articles.");
         run_all_build_steps(config, ctx)?;
     }
 
-    copy_files(&config.html_template_dir, &config.destination, files)?;
+    let inputs = bundle_inputs(config);
+    let (bundled, files) = files.into_iter().partition::<Vec<_>, _>(|file| {
+        file.path()
+            .strip_prefix(config.html_template_dir.path())
+            .is_ok_and(|path| inputs.iter().any(|input| input == path))
+    });
+    if !bundled.is_empty() {
+        write_bundles(config, ctx)?;
+    }
+
+    copy_files(
+        &config.html_template_dir,
+        &config.destination,
+        &files,
+        config.force_assets,
+    )?;
 
     log!(job_end: "Updated template files");
 
     Ok(())
 }
 
-pub fn copy_individual_assets_files(config: &Configuration, files: &[AssetFile]) -> TaskResult<()> {
+pub fn copy_individual_assets_files(
+    config: &Configuration,
+    files: &[AssetFile],
+    ctx: &GenerationContext,
+) -> TaskResult<()> {
     if files.is_empty() {
         return Ok(());
     }
 
     log!(job_start: "Updating asset files");
 
+    if ctx.fingerprints() {
+        log!(warn: "Asset fingerprints may be changed! Rebuilding all articles.");
+        run_all_build_steps(config, ctx)?;
+        log!(job_end: "Updated asset files");
+        return Ok(());
+    }
+
+    // The files which became the same as another, or not any more
+    if config.share_duplicate_assets
+        && asset_manifest_changed(config, &collect_asset_manifest(config, ctx)?)
+    {
+        log!(warn: "Shared asset files are changed! Rebuilding all articles.");
+        run_all_build_steps(config, ctx)?;
+        log!(job_end: "Updated asset files");
+        return Ok(());
+    }
+
+    // Copied from the group keeping the subdirectories, for the nested assets
     for file in files {
         let file_root = config
             .article_md_dir
-            .get_child(file.group.group_dir_path().join("assets"))
-            .expect("assets directory to be exist")?;
+            .get_child(file.group.group_dir_path())
+            .expect("group directory to be exist")?;
         let dest_dir = &config.destination.get_or_mkdir_child(
             Path::new(".")
-                .join("articles")
-                .join(file.group.group_dir_flat_path())
-                .join("assets"),
+                .join(config.article_base.path())
+                .join(file.group.group_dir_flat_path()),
         )?;
 
-        copy_individual_file(&file_root, dest_dir, file.file())?;
+        copy_individual_file(&file_root, dest_dir, file.file(), config.force_assets)?;
     }
 
     log!(job_end: "Updated asset files");
 
     Ok(())
 }
+
+// Returns whether any article is removed, which listings and tag pages still refer to
+pub fn remove_outputs_of_articles(config: &Configuration, removed: &[PathBuf]) -> TaskResult<bool> {
+    let mut removed_any = false;
+
+    for path in removed {
+        if let Some(id) = ArticleFile::identify(
+            path,
+            &config.article_md_dir,
+            &config.article_filename_pattern,
+            &config.assets_dir,
+        ) {
+            removed_any = true;
+            remove_output(config, &config.permalink.path(&id))?;
+        } else if let Some(asset) =
+            AssetFile::from_removed_path(path, &config.article_md_dir, &config.assets_dir)
+        {
+            remove_output(config, &asset.destination_path(config.article_base.path()))?;
+        }
+    }
+
+    Ok(removed_any)
+}
+
+// Returns whether any template file is removed, which needs a full rebuild
+pub fn remove_outputs_of_templates(
+    config: &Configuration,
+    removed: &[PathBuf],
+) -> TaskResult<bool> {
+    let mut removed_template = false;
+
+    for path in removed {
+        let Ok(relative_path) = path.strip_prefix(config.html_template_dir.path()) else {
+            continue;
+        };
+
+        if is_template_file(relative_path) {
+            removed_template = true;
+        } else {
+            remove_output(config, relative_path)?;
+        }
+    }
+
+    Ok(removed_template)
+}
+
+fn remove_output(config: &Configuration, relative_path: &Path) -> TaskResult<()> {
+    let path = config.destination.path().join(relative_path);
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    std::fs::remove_file(&path)?;
+    log!(step: "Removed {}", path.display());
+
+    // e.g. the directory of `/:slug/index.html` permalinks, which is left empty.
+    // Fails for the ones with other files, which are kept.
+    if let Some(parent) = path.parent()
+        && parent != config.destination.path()
+    {
+        std::fs::remove_dir(parent).ok();
+    }
+
+    Ok(())
+}
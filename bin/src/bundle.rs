@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use loss72_platemaker_construct::ConstructionPlan;
+use loss72_platemaker_core::{log, model::GenerationContext};
+use loss72_platemaker_website::{minify_css, minify_js};
+
+use crate::{
+    build_tasks::{TaskResult, log_changes},
+    config::Configuration,
+};
+
+// `[bundles]`, of which each is the files in the template directory concatenated in the order
+// into the file in the destination. The files in them are not copied by themselves.
+pub fn bundle_inputs(config: &Configuration) -> Vec<PathBuf> {
+    config.bundles.values().flatten().cloned().collect()
+}
+
+// Paths relative to the destination and the contents, minified for the release builds and
+// with the name of each file above it otherwise
+pub fn render_bundles(
+    config: &Configuration,
+    ctx: &GenerationContext,
+) -> TaskResult<Vec<(PathBuf, String)>> {
+    config
+        .bundles
+        .iter()
+        .map(|(output, inputs)| {
+            let script = !is_css(output);
+            let mut bundle = String::new();
+
+            for input in inputs {
+                let content = std::fs::read_to_string(config.html_template_dir.path().join(input))
+                    .map_err(|e| {
+                        std::io::Error::new(
+                            e.kind(),
+                            format!(
+                                "{} in the bundle {}: {}",
+                                input.display(),
+                                output.display(),
+                                e
+                            ),
+                        )
+                    })?;
+
+                if !ctx.minifies() {
+                    bundle.push_str(&format!("/* {} */\n", input.display()));
+                }
+                bundle.push_str(content.trim_end());
+                // Not to join the last statement without `;` to the next file
+                bundle.push_str(if script { ";\n" } else { "\n" });
+            }
+
+            if ctx.minifies() {
+                bundle = if script {
+                    minify_js(&bundle)
+                } else {
+                    minify_css(&bundle)
+                };
+            }
+
+            Ok((output.clone(), bundle))
+        })
+        .collect()
+}
+
+pub fn write_bundles(config: &Configuration, ctx: &GenerationContext) -> TaskResult<()> {
+    if config.bundles.is_empty() {
+        return Ok(());
+    }
+
+    log!(section: "Bundling template files");
+
+    let bundles = render_bundles(config, ctx)?;
+    let plan = ConstructionPlan {
+        dirs: vec![],
+        files: bundles
+            .iter()
+            .map(|(path, content)| (config.destination.path().join(path), content.as_str()))
+            .collect(),
+    };
+
+    if config.dry_run {
+        log_changes(&plan.changes());
+        return Ok(());
+    }
+
+    plan.execute()?;
+
+    log!(ok: "Wrote {} bundles", bundles.len());
+
+    Ok(())
+}
+
+fn is_css(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("css"))
+}
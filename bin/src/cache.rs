@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use loss72_platemaker_core::{
+    fs::Directory,
+    log,
+    model::{Article, GenerationContext},
+    util::content_hash,
+};
+use loss72_platemaker_structure::template::template_files;
+use serde::{Deserialize, Serialize};
+
+pub const CACHE_FILE: &str = ".platemaker-cache.json";
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct BuildCache {
+    context: String,
+    templates: String,
+    articles: HashMap<PathBuf, CachedArticle>,
+    // resized image -> hash of the source image
+    #[serde(default)]
+    variants: HashMap<PathBuf, String>,
+    // of the last full build, with which the pages were built since then
+    #[serde(default)]
+    build_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CachedArticle {
+    hash: String,
+    // Local images which the article depends on, e.g. for their sizes
+    #[serde(default)]
+    images: Vec<(PathBuf, String)>,
+    article: Article,
+}
+
+impl BuildCache {
+    pub fn load(destination: &Directory) -> Self {
+        let Ok(file) = destination.get_file(CACHE_FILE) else {
+            return Self::default();
+        };
+
+        file.read_to_string()
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                log!(warn: "Build cache could not be read, building from scratch: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, destination: &Directory) -> Result<(), std::io::Error> {
+        std::fs::write(
+            destination.path().join(CACHE_FILE),
+            serde_json::to_string(self)?,
+        )
+    }
+
+    // Drops every entry when the templates or the generation settings changed since the
+    // cache was written, matching the "rebuild all on template change" behavior of watch.
+    pub fn validate(
+        &mut self,
+        template_dir: &Directory,
+        ctx: &GenerationContext,
+    ) -> Result<(), std::io::Error> {
+        // Including the partials, which are expanded into the article pages
+        let templates = template_files(template_dir)?
+            .iter()
+            .map(|template| std::fs::read(template_dir.path().join(template)).map(content_hash))
+            .collect::<Result<String, _>>()?;
+        // The build id changes with any article, not only the ones which need rebuilding
+        let ctx = GenerationContext {
+            build_id: None,
+            ..ctx.clone()
+        };
+        let context = content_hash(format!("{ctx:?}"));
+
+        if self.templates != templates || self.context != context {
+            if !self.articles.is_empty() {
+                log!(step: "Templates or build options changed, invalidating build cache");
+            }
+
+            // Resized images don't depend on either of them
+            *self = Self {
+                context,
+                templates,
+                articles: HashMap::new(),
+                variants: std::mem::take(&mut self.variants),
+                build_id: None,
+            };
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, path: &Path, hash: &str) -> Option<&Article> {
+        self.articles
+            .get(path)
+            .filter(|cached| cached.hash == hash)
+            .filter(|cached| {
+                cached.images.iter().all(|(image, hash)| {
+                    std::fs::read(image).is_ok_and(|content| &content_hash(content) == hash)
+                })
+            })
+            .map(|cached| &cached.article)
+    }
+
+    // as it was last built, even if the article changed since then
+    pub fn previous(&self, path: &Path) -> Option<&Article> {
+        self.articles.get(path).map(|cached| &cached.article)
+    }
+
+    pub fn insert(&mut self, path: PathBuf, hash: String, article: Article) {
+        let images = article
+            .images
+            .iter()
+            .filter_map(|image| {
+                let content = std::fs::read(&image.source).ok()?;
+                Some((image.source.clone(), content_hash(content)))
+            })
+            .collect();
+
+        self.articles.insert(
+            path,
+            CachedArticle {
+                hash,
+                images,
+                article,
+            },
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.articles.is_empty()
+    }
+
+    // The last built version of every article, which is all of them since the last full build
+    pub fn articles(&self) -> impl Iterator<Item = &Article> {
+        self.articles.values().map(|cached| &cached.article)
+    }
+
+    // of the articles in the cache
+    pub fn sources(&self) -> impl Iterator<Item = &Path> {
+        self.articles.keys().map(PathBuf::as_path)
+    }
+
+    pub fn build_id(&self) -> Option<&str> {
+        self.build_id.as_deref()
+    }
+
+    pub fn set_build_id(&mut self, build_id: String) {
+        self.build_id = Some(build_id);
+    }
+
+    pub fn variant_hash(&self, path: &Path) -> Option<&str> {
+        self.variants.get(path).map(String::as_str)
+    }
+
+    pub fn insert_variant(&mut self, path: PathBuf, hash: String) {
+        self.variants.insert(path, hash);
+    }
+
+    pub fn retain(&mut self, paths: &[PathBuf]) {
+        self.articles.retain(|path, _| paths.contains(path));
+    }
+}
@@ -1,52 +1,193 @@
-use std::path::{Path, PathBuf};
+use std::{path::PathBuf, time::Duration};
 
 use clap::Parser;
-use loss72_platemaker_core::model::GenerationContext;
+use loss72_platemaker_core::{
+    log::{ColorChoice, LogLevel},
+    model::GenerationContext,
+};
 
-use crate::cmd::watch::WatchParam;
+use crate::cmd::{serve::ServeParam, watch::WatchParam};
 
 #[derive(Parser, Debug)]
 #[command()]
 pub enum Commands {
     Build(BuildArgs),
     Watch(WatchArgs),
+    Serve(ServeArgs),
+    Clean(CleanArgs),
+}
+
+/// The configuration, of which these flags take precedence over the file
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    /// Path to config, `./platemaker.toml` by default. It may be missing only if it is not given.
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Directory of the templates, instead of `html_template_dir` in the config.
+    #[arg(long)]
+    pub html_template_dir: Option<PathBuf>,
+
+    /// Directory of the Markdown files, instead of `article_md_dir` in the config.
+    #[arg(long)]
+    pub article_md_dir: Option<PathBuf>,
+
+    /// Directory to write the website to, instead of `destination` in the config.
+    #[arg(long)]
+    pub destination: Option<PathBuf>,
+
+    /// Profile in `[profiles.<name>]` of the config to apply over the rest, like `prod`.
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Number of the threads for the parallel steps, instead of `jobs` in the config. 1 runs them one by one.
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
 }
 
 /// Build the website from a set of Markdown file.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct BuildArgs {
-    /// Path to config,
-    #[arg(short, long, default_value = "./platemaker.toml")]
-    pub config: PathBuf,
+    #[command(flatten)]
+    pub config: ConfigArgs,
 
     /// Enable release build.
     #[arg(short, long)]
     pub release: bool,
+
+    /// Build draft articles even in release build.
+    #[arg(long)]
+    pub drafts: bool,
+
+    /// Build articles dated in the future even in release build.
+    #[arg(long)]
+    pub future: bool,
+
+    /// Ignore the incremental build cache and build every article from scratch.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Copy every asset file even if the copy in the destination is up to date.
+    #[arg(long)]
+    pub force_assets: bool,
+
+    /// Remove the generated contents in the destination before building.
+    #[arg(long)]
+    pub clean: bool,
+
+    /// List the files the build would write, and whether they are changed, without writing any.
+    #[arg(long, conflicts_with = "clean")]
+    pub dry_run: bool,
+
+    /// Check the links in the built pages, and fail if any of them is broken.
+    #[arg(long)]
+    pub check_links: bool,
+
+    /// Print only the warnings.
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print the details too, like every file copied.
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Color the output: always, auto or never.
+    #[arg(long, default_value = "auto")]
+    pub color: ColorChoice,
 }
 
 /// Watch for the file change, and update the website as necessary
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct WatchArgs {
-    /// Path to config,
-    #[arg(short, long, default_value = "./platemaker.toml")]
-    pub config: PathBuf,
+    #[command(flatten)]
+    pub config: ConfigArgs,
 
     /// Full build before watching. Failing to this build does not abort watching.
     #[arg(short, long, default_value_t = false)]
     pub build_first: bool,
 
+    /// Milliseconds to wait for the Markdown changes to settle, instead of `markdown_debounce_ms` in the config.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub markdown_debounce: Option<u64>,
+
+    /// Milliseconds to wait for the template and data changes to settle, instead of `template_debounce_ms` in the config.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub template_debounce: Option<u64>,
+
     /// Enable release build.
     #[arg(short, long)]
     pub release: bool,
+
+    /// Build draft articles even in release build.
+    #[arg(long)]
+    pub drafts: bool,
+
+    /// Build articles dated in the future even in release build.
+    #[arg(long)]
+    pub future: bool,
+
+    /// Ignore the incremental build cache and build every article from scratch.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Copy every asset file even if the copy in the destination is up to date.
+    #[arg(long)]
+    pub force_assets: bool,
+
+    /// Print only the warnings.
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print the details too, like every file copied.
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Color the output: always, auto or never.
+    #[arg(long, default_value = "auto")]
+    pub color: ColorChoice,
+}
+
+/// Serve the website locally, and update it as the files change
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub watch: WatchArgs,
+
+    /// Port to listen on.
+    #[arg(short, long, default_value_t = 8000)]
+    pub port: u16,
+}
+
+/// Remove the generated contents in the destination
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct CleanArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// Print only the warnings.
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print the details too, like every file copied.
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Color the output: always, auto or never.
+    #[arg(long, default_value = "auto")]
+    pub color: ColorChoice,
 }
 
 impl Commands {
-    pub fn config(&self) -> &Path {
+    pub fn config(&self) -> &ConfigArgs {
         match self {
             Commands::Build(build_args) => &build_args.config,
             Commands::Watch(watch_args) => &watch_args.config,
+            Commands::Serve(serve_args) => &serve_args.watch.config,
+            Commands::Clean(clean_args) => &clean_args.config,
         }
     }
 
@@ -54,6 +195,82 @@ impl Commands {
         match self {
             Commands::Build(build_args) => build_args.release,
             Commands::Watch(watch_args) => watch_args.release,
+            Commands::Serve(serve_args) => serve_args.watch.release,
+            Commands::Clean(_) => false,
+        }
+    }
+
+    pub fn no_cache(&self) -> bool {
+        match self {
+            Commands::Build(build_args) => build_args.no_cache,
+            Commands::Watch(watch_args) => watch_args.no_cache,
+            Commands::Serve(serve_args) => serve_args.watch.no_cache,
+            Commands::Clean(_) => false,
+        }
+    }
+
+    pub fn force_assets(&self) -> bool {
+        match self {
+            Commands::Build(build_args) => build_args.force_assets,
+            Commands::Watch(watch_args) => watch_args.force_assets,
+            Commands::Serve(serve_args) => serve_args.watch.force_assets,
+            Commands::Clean(_) => false,
+        }
+    }
+
+    pub fn check_links(&self) -> bool {
+        match self {
+            Commands::Build(build_args) => build_args.check_links,
+            Commands::Watch(_) | Commands::Serve(_) | Commands::Clean(_) => false,
+        }
+    }
+
+    pub fn dry_run(&self) -> bool {
+        match self {
+            Commands::Build(build_args) => build_args.dry_run,
+            Commands::Watch(_) | Commands::Serve(_) | Commands::Clean(_) => false,
+        }
+    }
+
+    pub fn log_level(&self) -> LogLevel {
+        let (quiet, verbose) = match self {
+            Commands::Build(build_args) => (build_args.quiet, build_args.verbose),
+            Commands::Watch(watch_args) => (watch_args.quiet, watch_args.verbose),
+            Commands::Serve(serve_args) => (serve_args.watch.quiet, serve_args.watch.verbose),
+            Commands::Clean(clean_args) => (clean_args.quiet, clean_args.verbose),
+        };
+
+        match (quiet, verbose) {
+            (true, _) => LogLevel::Quiet,
+            (_, true) => LogLevel::Verbose,
+            _ => LogLevel::Normal,
+        }
+    }
+
+    pub fn color(&self) -> ColorChoice {
+        match self {
+            Commands::Build(build_args) => build_args.color,
+            Commands::Watch(watch_args) => watch_args.color,
+            Commands::Serve(serve_args) => serve_args.watch.color,
+            Commands::Clean(clean_args) => clean_args.color,
+        }
+    }
+
+    pub fn drafts(&self) -> bool {
+        match self {
+            Commands::Build(build_args) => build_args.drafts,
+            Commands::Watch(watch_args) => watch_args.drafts,
+            Commands::Serve(serve_args) => serve_args.watch.drafts,
+            Commands::Clean(_) => false,
+        }
+    }
+
+    pub fn future(&self) -> bool {
+        match self {
+            Commands::Build(build_args) => build_args.future,
+            Commands::Watch(watch_args) => watch_args.future,
+            Commands::Serve(serve_args) => serve_args.watch.future,
+            Commands::Clean(_) => false,
         }
     }
 }
@@ -62,14 +279,28 @@ impl From<&WatchArgs> for WatchParam {
     fn from(value: &WatchArgs) -> Self {
         Self {
             build_first: value.build_first,
+            markdown_debounce: value.markdown_debounce.map(Duration::from_millis),
+            template_debounce: value.template_debounce.map(Duration::from_millis),
         }
     }
 }
 
-impl From<&Commands> for GenerationContext {
-    fn from(value: &Commands) -> Self {
+impl From<&ServeArgs> for ServeParam {
+    fn from(value: &ServeArgs) -> Self {
         Self {
-            release: value.release(),
+            port: value.port,
+            watch: (&value.watch).into(),
+        }
+    }
+}
+
+impl Commands {
+    pub fn generation_context(&self, base: &GenerationContext) -> GenerationContext {
+        GenerationContext {
+            release: base.release || self.release(),
+            drafts: base.drafts || self.drafts(),
+            future: base.future || self.future(),
+            ..base.clone()
         }
     }
 }
@@ -1,2 +1,4 @@
 pub mod build;
+pub mod clean;
+pub mod serve;
 pub mod watch;
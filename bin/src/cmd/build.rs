@@ -1,7 +1,10 @@
 use loss72_platemaker_core::model::GenerationContext;
 
-use crate::{build_tasks::TaskResult, config::Configuration};
+use crate::{
+    build_tasks::{BuildReport, TaskResult},
+    config::Configuration,
+};
 
-pub fn full_build(config: &Configuration, ctx: &GenerationContext) -> TaskResult<()> {
+pub fn full_build(config: &Configuration, ctx: &GenerationContext) -> TaskResult<BuildReport> {
     crate::build_tasks::run_all_build_steps(config, ctx)
 }
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use loss72_platemaker_core::{fs::FSNode, log};
+
+use crate::config::Configuration;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CleanError {
+    #[error(
+        "Refusing to clean {}, since it contains {}. Configure `destination` to another directory.",
+        .destination.display(),
+        .source_dir.display()
+    )]
+    ContainsSource {
+        destination: PathBuf,
+        source_dir: PathBuf,
+    },
+
+    #[error("Failed to clean the destination: {0}")]
+    IOError(#[from] std::io::Error),
+}
+
+pub fn clean(config: &Configuration) -> Result<(), CleanError> {
+    log!(section: "Cleaning {}", config.destination.path().display());
+
+    // The directories are canonicalized, so they are compared as they are
+    let destination = config.destination.path();
+    if let Some(source) = [&config.article_md_dir, &config.html_template_dir]
+        .into_iter()
+        .find(|dir| dir.path().starts_with(destination))
+    {
+        return Err(CleanError::ContainsSource {
+            destination: destination.to_path_buf(),
+            source_dir: source.path().to_path_buf(),
+        });
+    }
+
+    for node in config.destination.try_iter_content()? {
+        match node? {
+            FSNode::Directory(dir) => std::fs::remove_dir_all(dir.path())?,
+            node => std::fs::remove_file(node.path())?,
+        }
+    }
+
+    log!(ok: "Cleaned {}", destination.display());
+
+    Ok(())
+}
@@ -0,0 +1,243 @@
+mod live_reload;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+};
+
+use loss72_platemaker_core::{log, model::GenerationContext, util::percent_decode};
+
+use live_reload::{LIVE_RELOAD_PATH, LiveReload, inject_script};
+
+use crate::{
+    cmd::watch::{WatchParam, WatcherError, watch_for_change_then},
+    config::Configuration,
+    error::report_error,
+};
+
+#[derive(Debug)]
+pub struct ServeParam {
+    pub port: u16,
+    pub watch: WatchParam,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServeError {
+    #[error("Port {0} is already in use. Specify another one with --port.")]
+    AddrInUse(u16),
+
+    #[error("Server could not be started: {0}")]
+    Bind(std::io::Error),
+
+    #[error(transparent)]
+    Watcher(#[from] WatcherError),
+}
+
+pub fn serve(
+    config: &Configuration,
+    param: &ServeParam,
+    ctx: &GenerationContext,
+) -> Result<(), ServeError> {
+    let listener = TcpListener::bind(("127.0.0.1", param.port)).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AddrInUse {
+            ServeError::AddrInUse(param.port)
+        } else {
+            ServeError::Bind(e)
+        }
+    })?;
+
+    let live_reload = LiveReload::default();
+
+    let root = config.destination.path().to_path_buf();
+    let server_live_reload = live_reload.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let root = root.clone();
+            let live_reload = server_live_reload.clone();
+            std::thread::spawn(move || {
+                handle_connection(stream, &root, &live_reload)
+                    .inspect_err(report_error)
+                    .ok();
+            });
+        }
+    });
+
+    log!(ok: "Serving {} at http://127.0.0.1:{}/", config.destination.path().display(), param.port);
+
+    Ok(watch_for_change_then(config, &param.watch, ctx, || {
+        live_reload.reload()
+    })?)
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    live_reload: &LiveReload,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut websocket_key = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header == "\r\n" || header == "\n" {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("sec-websocket-key")
+        {
+            websocket_key = Some(value.trim().to_string());
+        }
+    }
+
+    let mut request = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (request.next(), request.next()) else {
+        return respond(&mut stream, "400 Bad Request", "text/plain", b"Bad Request");
+    };
+
+    if let (LIVE_RELOAD_PATH, Some(key)) = (target, &websocket_key) {
+        return live_reload.accept(stream, key);
+    }
+
+    if method != "GET" && method != "HEAD" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method Not Allowed",
+        );
+    }
+
+    let Some(path) = resolve_path(root, target) else {
+        log!(warn: "404 {}", target);
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not Found");
+    };
+
+    let mut body = std::fs::read(&path)?;
+    let content_type = mime_type(&path);
+    if content_type.starts_with("text/html") {
+        body = inject_script(&body);
+    }
+    log!(step: "200 {}", target);
+
+    respond(
+        &mut stream,
+        "200 OK",
+        content_type,
+        if method == "HEAD" { &[] } else { &body },
+    )
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn resolve_path(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(path)?;
+
+    let mut resolved = root.join(path.trim_start_matches('/'));
+    if resolved.is_dir() {
+        resolved.push("index.html");
+    }
+
+    // Refuse anything that escapes the destination, e.g. via `..`
+    let resolved = resolved.canonicalize().ok()?;
+    (resolved.starts_with(root) && resolved.is_file()).then_some(resolved)
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{mime_type, resolve_path};
+
+    #[test]
+    fn paths_out_of_the_root_are_not_resolved() {
+        let dir = std::env::temp_dir().join(format!("platemaker-serve-{}", std::process::id()));
+        let root = dir.join("out");
+        std::fs::create_dir_all(root.join("posts")).expect("temp dir to be writable");
+        for path in ["out/index.html", "out/posts/a b.htm", "secret.txt"] {
+            std::fs::write(dir.join(path), "").expect("temp dir to be writable");
+        }
+        let root = root.canonicalize().expect("temp dir to exist");
+        let resolve = |target: &str| {
+            resolve_path(&root, target).map(|path| {
+                path.strip_prefix(&root)
+                    .map(|path| path.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_default()
+            })
+        };
+
+        let resolved = [
+            "/",
+            "/posts/a%20b.htm?x=1",
+            "/posts/",
+            "/../secret.txt",
+            "/posts/../../secret.txt",
+            "/%2e%2e/secret.txt",
+        ]
+        .map(resolve);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(
+            resolved,
+            [
+                Some("index.html".to_string()),
+                Some("posts/a b.htm".to_string()),
+                None,
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn htm_pages_are_html() {
+        assert!(mime_type(Path::new("a.htm")).starts_with("text/html"));
+        assert!(mime_type(Path::new("A.HTML")).starts_with("text/html"));
+        assert!(!mime_type(Path::new("a.xml")).starts_with("text/html"));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    io::Write,
+    net::TcpStream,
+    sync::{Arc, Mutex},
+};
+
+use tungstenite::{Message, WebSocket, handshake::derive_accept_key, protocol::Role};
+
+pub const LIVE_RELOAD_PATH: &str = "/__platemaker/live-reload";
+
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+  new WebSocket(`ws://${location.host}/__platemaker/live-reload`)
+    .addEventListener("message", () => location.reload());
+</script>"#;
+
+#[derive(Clone, Default)]
+pub struct LiveReload {
+    sockets: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+}
+
+impl LiveReload {
+    pub fn accept(&self, mut stream: TcpStream, key: &str) -> std::io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        )?;
+        stream.flush()?;
+
+        self.sockets
+            .lock()
+            .expect("live reload sockets not to be poisoned")
+            .push(WebSocket::from_raw_socket(stream, Role::Server, None));
+
+        Ok(())
+    }
+
+    pub fn reload(&self) {
+        let mut sockets = self
+            .sockets
+            .lock()
+            .expect("live reload sockets not to be poisoned");
+
+        // Pages which are closed already fail here, and no longer need notifying
+        sockets.retain_mut(|socket| socket.send(Message::text("reload")).is_ok());
+    }
+}
+
+pub fn inject_script(html: &[u8]) -> Vec<u8> {
+    let Ok(html) = std::str::from_utf8(html) else {
+        return html.to_vec();
+    };
+
+    match html.to_ascii_lowercase().rfind("</body>") {
+        Some(index) => [&html[..index], LIVE_RELOAD_SCRIPT, &html[index..]]
+            .concat()
+            .into_bytes(),
+        None => html.as_bytes().to_vec(),
+    }
+}
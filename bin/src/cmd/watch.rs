@@ -1,23 +1,39 @@
 use std::{path::PathBuf, time::Duration};
 
-use crossbeam_channel::{RecvError, select, unbounded};
+use crossbeam_channel::{Receiver, RecvError, after, never, select, unbounded};
 
-use loss72_platemaker_core::{fs::File, log, model::GenerationContext};
-use loss72_platemaker_structure::{ArticleFile, AssetFile};
-use notify::{EventKind, RecursiveMode};
+use loss72_platemaker_core::{
+    fs::{Directory, FSNode, File},
+    log,
+    model::GenerationContext,
+};
+use loss72_platemaker_structure::{
+    ArticleFile, AssetFile, ignore::IgnorePatterns, template::is_template_file,
+};
+use notify::{
+    EventKind, RecursiveMode,
+    event::{ModifyKind, RenameMode},
+};
 use notify_debouncer_full::{DebounceEventResult, new_debouncer};
 
 use crate::{
     build_tasks::{
-        build_files, copy_individual_assets_files, copy_individual_template_files, run_all_build_steps,
+        build_files, build_stale_files, copy_individual_assets_files,
+        copy_individual_template_files, remove_outputs_of_articles, remove_outputs_of_templates,
+        run_all_build_steps,
     },
+    compress::precompress_outputs,
     config::Configuration,
     error::{report_error, report_if_fail},
+    fingerprint::collect_asset_manifest,
 };
 
 #[derive(Debug)]
 pub struct WatchParam {
     pub build_first: bool,
+    // over the ones in the configuration
+    pub markdown_debounce: Option<Duration>,
+    pub template_debounce: Option<Duration>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -26,29 +42,105 @@ pub enum WatcherError {
     NotifierError(#[from] notify::Error),
 }
 
+// Changes following a batch within it are taken together, like the ones of a branch switch
+// which the debouncers may give in several batches
+const COALESCE_WINDOW: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Default)]
+struct FileChanges {
+    updated: Vec<File>,
+    // not exist anymore
+    removed: Vec<PathBuf>,
+}
+
+impl FileChanges {
+    fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    // The later changes to the same path take precedence
+    fn merge(&mut self, other: FileChanges) {
+        for file in other.updated {
+            self.removed.retain(|path| path != file.path());
+            if !self
+                .updated
+                .iter()
+                .any(|updated| updated.path() == file.path())
+            {
+                self.updated.push(file);
+            }
+        }
+
+        for path in other.removed {
+            self.updated.retain(|file| file.path() != path);
+            if !self.removed.contains(&path) {
+                self.removed.push(path);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PendingChanges {
+    articles: FileChanges,
+    templates: FileChanges,
+    data: bool,
+}
+
+impl PendingChanges {
+    fn is_empty(&self) -> bool {
+        self.articles.is_empty() && self.templates.is_empty() && !self.data
+    }
+}
+
 #[derive(Debug)]
 pub enum Changed {
     Article(PathBuf),
     Template,
 }
 
-pub fn watch_for_change(config: &Configuration, param: &WatchParam, ctx: &GenerationContext) -> Result<(), WatcherError> {
+pub fn watch_for_change(
+    config: &Configuration,
+    param: &WatchParam,
+    ctx: &GenerationContext,
+) -> Result<(), WatcherError> {
+    watch_for_change_then(config, param, ctx, || {})
+}
+
+pub fn watch_for_change_then(
+    config: &Configuration,
+    param: &WatchParam,
+    ctx: &GenerationContext,
+    on_rebuilt: impl Fn(),
+) -> Result<(), WatcherError> {
     if param.build_first {
         log!(ok: "--build-first specified - full building first!");
         report_if_fail(|| run_all_build_steps(config, ctx)).ok();
         log!(ok: "Full building completed, now starting watch...");
+    } else {
+        report_if_fail(|| build_stale_files(config, ctx)).ok();
     }
 
     let (md_tx, md_rx) = unbounded();
     let (tpl_tx, tpl_rx) = unbounded();
+    let (data_tx, data_rx) = unbounded();
     let (ctrlc_tx, ctrlc_rx) = unbounded::<()>();
 
-    let mut markdown_watcher = new_debouncer(Duration::from_millis(500), None, md_tx)?;
+    let markdown_debounce = param.markdown_debounce.unwrap_or(config.markdown_debounce);
+    let template_debounce = param.template_debounce.unwrap_or(config.template_debounce);
+
+    let mut markdown_watcher = new_debouncer(markdown_debounce, None, md_tx)?;
     markdown_watcher.watch(config.article_md_dir.path(), RecursiveMode::Recursive)?;
 
-    let mut template_watcher = new_debouncer(Duration::from_millis(500), None, tpl_tx)?;
+    let mut template_watcher = new_debouncer(template_debounce, None, tpl_tx)?;
     template_watcher.watch(config.html_template_dir.path(), RecursiveMode::Recursive)?;
 
+    // Every page may use the data, so they are all rebuilt on its changes like the templates
+    let mut data_watcher = new_debouncer(template_debounce, None, data_tx)?;
+    if let Some(data_dir) = &config.data_dir {
+        data_watcher.watch(data_dir.path(), RecursiveMode::NonRecursive)?;
+    }
+
     if let Err(e) = ctrlc::set_handler(move || {
         ctrlc_tx.send(()).ok();
     }) {
@@ -61,83 +153,265 @@ pub fn watch_for_change(config: &Configuration, param: &WatchParam, ctx: &Genera
     log!(section: "Configurations");
     log!(step: "   Article folder: {}", config.article_md_dir.path().display());
     log!(step: "  Template folder: {}", config.html_template_dir.path().display());
+    if let Some(data_dir) = &config.data_dir {
+        log!(step: "      Data folder: {}", data_dir.path().display());
+    }
     log!(ok: "Changes to the files in directories above will be watched");
 
-    loop {
-        select! {
-            recv(md_rx) -> received => {
-                let Some(files) = handle_notify_event(received) else {
-                    continue;
-                };
+    while let Some(changes) = receive_changes(&md_rx, &tpl_rx, &data_rx, &ctrlc_rx) {
+        if apply_changes(config, ctx, changes) {
+            on_rebuilt();
+        }
+    }
 
-                let articles = files.iter()
-                    .filter_map(|file| ArticleFile::from_file(file, &config.article_md_dir))
-                    .collect::<Vec<_>>();
+    log::blank_line();
+    log!(job_end: "Receved Ctrl-C, Exiting!");
 
-                build_files(config, &articles, false, ctx)
-                    .inspect_err(report_error)
-                    .ok();
+    Ok(())
+}
 
-                let article_asset_file = files.iter()
-                    .filter_map(|file| AssetFile::from_file(file, &config.article_md_dir))
-                    .collect::<Vec<_>>();
+// Blocks for the first batch of the changes, and then takes the ones following within
+// `COALESCE_WINDOW` together. `None` on Ctrl-C, which is not held by the window.
+fn receive_changes(
+    md_rx: &Receiver<DebounceEventResult>,
+    tpl_rx: &Receiver<DebounceEventResult>,
+    data_rx: &Receiver<DebounceEventResult>,
+    ctrlc_rx: &Receiver<()>,
+) -> Option<PendingChanges> {
+    let mut pending = PendingChanges::default();
+    let mut batches = 0;
+
+    loop {
+        let window = if pending.is_empty() {
+            never()
+        } else {
+            after(COALESCE_WINDOW)
+        };
 
-                copy_individual_assets_files(config, &article_asset_file)
-                    .inspect_err(report_error)
-                    .ok();
+        select! {
+            recv(md_rx) -> received => {
+                if let Some(changes) = handle_notify_event(received).filter(|changes| !changes.is_empty()) {
+                    pending.articles.merge(changes);
+                    batches += 1;
+                }
             },
             recv(tpl_rx) -> received => {
-                let Some(files) = handle_notify_event(received) else {
-                    continue;
-                };
-
-                copy_individual_template_files(config, &files, ctx)
-                    .inspect_err(report_error)
-                    .ok();
+                if let Some(changes) = handle_notify_event(received).filter(|changes| !changes.is_empty()) {
+                    pending.templates.merge(changes);
+                    batches += 1;
+                }
             },
-            recv(ctrlc_rx) -> _ => {
-                println!();
-                log!(job_end: "Receved Ctrl-C, Exiting!");
-                break;
-            }
+            recv(data_rx) -> received => {
+                if handle_notify_event(received).is_some_and(|changes| !changes.is_empty()) {
+                    pending.data = true;
+                    batches += 1;
+                }
+            },
+            recv(ctrlc_rx) -> _ => return None,
+            recv(window) -> _ => break,
         }
     }
 
-    Ok(())
+    if batches > 1 {
+        log!(step: "Taking {} batches of the changes together", batches);
+    }
+
+    Some(pending)
 }
 
-fn handle_notify_event(received: Result<DebounceEventResult, RecvError>) -> Option<Vec<File>> {
+// Every page is rebuilt at most once for all the changes, and the rest is updated
+// incrementally otherwise. Returns whether all of it succeeded.
+fn apply_changes(config: &Configuration, ctx: &GenerationContext, changes: PendingChanges) -> bool {
+    let removed_articles = remove_outputs_of_articles(config, &changes.articles.removed)
+        .inspect_err(report_error)
+        .unwrap_or(false);
+    let removed_templates = remove_outputs_of_templates(config, &changes.templates.removed)
+        .inspect_err(report_error)
+        .unwrap_or(false);
+
+    let article_asset_files = changes
+        .articles
+        .updated
+        .iter()
+        .filter_map(|file| AssetFile::from_file(file, &config.article_md_dir, &config.assets_dir))
+        .collect::<Vec<_>>();
+    // Including the partials, which are only expanded into the pages
+    let updated_templates = changes.templates.updated.iter().any(|file| {
+        is_template_file(
+            file.path()
+                .strip_prefix(config.html_template_dir.path())
+                .unwrap_or(file.path()),
+        )
+    });
+    let copies_assets = !changes.templates.updated.is_empty() || !article_asset_files.is_empty();
+
+    let full_build_reason = if changes.data {
+        Some("Data files are changed!")
+    } else if removed_articles {
+        Some("Articles are removed or moved!")
+    } else if removed_templates {
+        Some("Article page template file is removed!")
+    } else if updated_templates {
+        Some("Article page template file is updated!")
+    } else if copies_assets && ctx.fingerprints() {
+        Some("Asset fingerprints may be changed!")
+    } else {
+        None
+    };
+    if let Some(reason) = full_build_reason {
+        log!(warn: "{} Rebuilding all articles.", reason);
+        return report_if_fail(|| run_all_build_steps(config, ctx)).is_ok();
+    }
+
+    // Loaded every time for the edits to the ignore file to take effect
+    let ignore = IgnorePatterns::load(&config.article_md_dir)
+        .inspect_err(report_error)
+        .unwrap_or_default();
+
+    let articles = changes
+        .articles
+        .updated
+        .iter()
+        .filter_map(|file| {
+            ArticleFile::from_file(
+                file,
+                &config.article_md_dir,
+                &config.article_filename_pattern,
+                &config.assets_dir,
+            )
+        })
+        .filter(|article| !ignore.is_ignored(&article.relative_path, false))
+        .collect::<Vec<_>>();
+
+    let built = collect_asset_manifest(config, ctx)
+        .and_then(|assets| build_files(config, &articles, false, ctx, &assets))
+        .inspect_err(report_error)
+        .is_ok();
+
+    let copied_templates = copy_individual_template_files(config, &changes.templates.updated, ctx)
+        .inspect_err(report_error)
+        .is_ok();
+
+    let copied_assets = copy_individual_assets_files(config, &article_asset_files, ctx)
+        .inspect_err(report_error)
+        .is_ok();
+
+    // for the pages and the files updated above
+    let compressed = precompress_outputs(config, ctx)
+        .inspect_err(report_error)
+        .is_ok();
+
+    built && copied_templates && copied_assets && compressed
+}
+
+fn handle_notify_event(received: Result<DebounceEventResult, RecvError>) -> Option<FileChanges> {
     let events = match received {
         Ok(Ok(events)) => events,
         Ok(Err(errors)) => {
-            println!("warning: filesystem seems to be changed but the detail could not be read");
+            log!(warn: "filesystem seems to be changed but the detail could not be read");
             errors.iter().for_each(|error| {
-                println!("         - {error}");
+                log!(warn: "  - {error}");
             });
             return None;
         }
         Err(error) => {
-            println!("warning: filesystem seems to be changed but the detail could not be read");
-            println!("         {error}");
+            log!(warn: "filesystem seems to be changed but the detail could not be read");
+            log!(warn: "  {error}");
             return None;
         }
     };
 
-    Some(
-        events
-            .iter()
-            .flat_map(|event| match event.kind {
-                EventKind::Create(_) | EventKind::Modify(_) => event.paths.clone(),
-                _ => vec![],
-            })
-            .filter(|path| path.exists())
-            .filter_map(|file| match File::new(file) {
-                Ok(file) => Some(file),
+    let mut changes = FileChanges::default();
+
+    for event in events.iter() {
+        // The old path of renames comes as `Name(From)`, or the first of `Name(Both)` if the
+        // debouncer could pair them. Editors renaming via create and remove are batched
+        // within the debounce window, and handled the same as the other renames.
+        let removal = matches!(
+            event.kind,
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+        );
+        if !removal && !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            continue;
+        }
+
+        if let (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) =
+            (event.kind, event.paths.as_slice())
+        {
+            log!(step: "Renamed {} to {}", from.display(), to.display());
+        }
+
+        for path in &event.paths {
+            // Editors may remove the file and write it again while saving, so the removal
+            // is decided by the file system rather than the kind of the last event
+            if !path.exists() {
+                if removal && !changes.removed.contains(path) {
+                    changes.removed.push(path.clone());
+                }
+                continue;
+            }
+
+            // Files in a renamed directory don't get their own events
+            if removal && path.is_dir() {
+                changes.updated.extend(
+                    Directory::new(path)
+                        .and_then(|dir| dir.try_iter_tree()?.collect::<Result<Vec<_>, _>>())
+                        .inspect_err(|error| {
+                            log!(warn: "There was an error during checking what changed: {}", error)
+                        })
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(FSNode::into_file),
+                );
+                continue;
+            }
+
+            match File::new(path) {
+                Ok(file) => changes.updated.push(file),
                 Err(error) => {
                     log!(warn: "There was an error during checking what changed: {}", error);
-                    None
                 }
-            })
-            .collect(),
-    )
+            }
+        }
+    }
+
+    Some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use loss72_platemaker_core::fs::File;
+
+    use super::FileChanges;
+
+    #[test]
+    fn later_changes_take_precedence() {
+        let changes = |updated: &[&str], removed: &[&str]| FileChanges {
+            updated: updated.iter().map(File::new_unchecked).collect(),
+            removed: removed.iter().map(PathBuf::from).collect(),
+        };
+        let paths = |changes: &FileChanges| {
+            (
+                changes
+                    .updated
+                    .iter()
+                    .map(|file| file.path().to_path_buf())
+                    .collect::<Vec<_>>(),
+                changes.removed.clone(),
+            )
+        };
+
+        let mut pending = changes(&["a.md", "b.md"], &["c.md"]);
+        pending.merge(changes(&["c.md", "a.md"], &["b.md", "d.md"]));
+
+        assert_eq!(
+            paths(&pending),
+            (
+                vec![PathBuf::from("a.md"), PathBuf::from("c.md")],
+                vec![PathBuf::from("b.md"), PathBuf::from("d.md")],
+            )
+        );
+    }
 }
@@ -0,0 +1,173 @@
+use std::{
+    ffi::OsString,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use flate2::{Compression, write::GzEncoder};
+use loss72_platemaker_core::{
+    fs::{FSNode, File},
+    log,
+    model::GenerationContext,
+};
+
+use crate::{build_tasks::TaskResult, config::Configuration};
+
+// Text outputs, as the images and the fonts are compressed in their formats
+const COMPRESSED_EXTENSIONS: [&str; 9] = [
+    "html", "css", "js", "mjs", "json", "xml", "svg", "txt", "map",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    // With the `brotli` command, which is skipped when it is not installed
+    Brotli,
+}
+
+impl Encoding {
+    fn extension(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gz",
+            Encoding::Brotli => "br",
+        }
+    }
+}
+
+// `.gz` and `.br` next to the text outputs for the hosts serving them as they are, only in release
+// builds with `precompress`. The ones newer than the outputs are kept, and the ones of which the
+// outputs are removed are removed too.
+pub fn precompress_outputs(config: &Configuration, ctx: &GenerationContext) -> TaskResult<()> {
+    if !config.precompress || !ctx.release || config.dry_run {
+        return Ok(());
+    }
+
+    log!(section: "Compressing text outputs");
+
+    let files = config
+        .destination
+        .try_iter_tree()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+        .collect::<Vec<_>>();
+
+    let mut encodings = vec![Encoding::Gzip, Encoding::Brotli];
+    let mut written = 0;
+    // by the encodings, of all the compressed files including the ones kept
+    let mut saved = [0_u64; 2];
+
+    for file in &files {
+        if let Some(original) = original_of(file.path()) {
+            if !original.exists() {
+                log!(step: "Removing {}", file.path().display());
+                std::fs::remove_file(file.path())?;
+            }
+            continue;
+        }
+
+        if !compressible(file.path()) || file.size()? < config.precompress_min_bytes {
+            continue;
+        }
+
+        let mut content = None;
+        for encoding in encodings.clone() {
+            let variant = variant_path(file.path(), encoding);
+            let up_to_date = std::fs::metadata(&variant)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| file.modified().is_ok_and(|original| modified >= original));
+
+            let size = if up_to_date {
+                std::fs::metadata(&variant)?.len()
+            } else {
+                let content = match &content {
+                    Some(content) => content,
+                    None => content.insert(std::fs::read(file.path())?),
+                };
+                let compressed = match compress(file, content, encoding) {
+                    Ok(compressed) => compressed,
+                    Err(e) if encoding == Encoding::Brotli && e.kind() == ErrorKind::NotFound => {
+                        log!(warn: "brotli command is not found, writing only the .gz files");
+                        encodings.retain(|encoding| *encoding != Encoding::Brotli);
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                // The hosts serve the original without the variant
+                if compressed.len() as u64 >= file.size()? {
+                    if variant.exists() {
+                        std::fs::remove_file(&variant)?;
+                    }
+                    continue;
+                }
+
+                log!(detail: "Compressing {} to .{}", file.path().display(), encoding.extension());
+                std::fs::write(&variant, &compressed)?;
+                written += 1;
+                compressed.len() as u64
+            };
+
+            saved[encoding as usize] += file.size()?.saturating_sub(size);
+        }
+    }
+
+    log!(ok: "Wrote {} compressed files, saving {} bytes with gzip and {} bytes with brotli", written, saved[0], saved[1]);
+
+    Ok(())
+}
+
+fn compress(file: &File, content: &[u8], encoding: Encoding) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(vec![], Compression::best());
+            encoder.write_all(content)?;
+            encoder.finish()
+        }
+        Encoding::Brotli => {
+            let output = Command::new("brotli")
+                .args(["--best", "--stdout", "--"])
+                .arg(file.path())
+                .output()?;
+            if !output.status.success() {
+                return Err(std::io::Error::other(
+                    String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                ));
+            }
+            Ok(output.stdout)
+        }
+    }
+}
+
+// Not the hidden ones like the build cache, which are not served
+fn compressible(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+    !hidden
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| COMPRESSED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// `index.html` -> `index.html.gz`
+fn variant_path(path: &Path, encoding: Encoding) -> PathBuf {
+    let mut variant = OsString::from(path.as_os_str());
+    variant.push(".");
+    variant.push(encoding.extension());
+    PathBuf::from(variant)
+}
+
+// `index.html.gz` -> `index.html`, only for the compressed variants of the text outputs
+fn original_of(path: &Path) -> Option<PathBuf> {
+    let ext = path.extension()?;
+    if ext != "gz" && ext != "br" {
+        return None;
+    }
+
+    let original = path.with_extension("");
+    compressible(&original).then_some(original)
+}
@@ -1,12 +1,148 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    num::NonZero,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use loss72_platemaker_core::fs::Directory;
+use chrono::{FixedOffset, Local, NaiveDate, Utc};
+
+use loss72_platemaker_core::{
+    fs::{Directory, SymlinkPolicy},
+    log::LogFormat,
+    model::{ArticleOrder, GenerationContext},
+    util::url_host,
+};
+use loss72_platemaker_structure::{DEFAULT_ASSETS_DIR, filename::FilenamePattern};
+
+use crate::cli::ConfigArgs;
+use loss72_platemaker_website::{
+    ArticleBase, DEFAULT_RELATED_ARTICLES, DateFormat, FeedKind, Permalink, SiteVariables, UrlStyle,
+};
+
+pub const CONFIG_FILE: &str = "./platemaker.toml";
+
+const DEFAULT_DATA_DIR: &str = "./data";
+
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+// Smaller ones are served as fast without the compression
+const DEFAULT_PRECOMPRESS_MIN_BYTES: u64 = 1024;
+
+// Release builds by default
+const PROD_PROFILE: &str = "prod";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error("No profile `{profile}` is in the configuration, of which the profiles are: {}", available.join(", "))]
+    Unknown {
+        profile: String,
+        available: Vec<String>,
+    },
+
+    #[error("`profiles.{0}` should be a table")]
+    NotTable(String),
+
+    #[error(transparent)]
+    Invalid(#[from] toml::de::Error),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timezone {
+    #[default]
+    Local,
+    // `UTC` or like `+09:00`
+    Fixed(FixedOffset),
+}
+
+impl FromStr for Timezone {
+    type Err = std::io::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "local" => Ok(Timezone::Local),
+            "UTC" | "utc" | "Z" => Ok(Timezone::Fixed(
+                FixedOffset::east_opt(0).expect("UTC to be in range"),
+            )),
+            offset => offset.parse().map(Timezone::Fixed).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "timezone '{offset}' is none of local, UTC and the offsets like +09:00"
+                    ),
+                )
+            }),
+        }
+    }
+}
+
+impl Timezone {
+    pub fn today(&self) -> NaiveDate {
+        match self {
+            Timezone::Local => Local::now().date_naive(),
+            Timezone::Fixed(offset) => Utc::now().with_timezone(offset).date_naive(),
+        }
+    }
+}
 
 #[derive(Debug, serde::Deserialize)]
 pub struct ConfigurationScheme {
-    pub html_template_dir: PathBuf,
-    pub article_md_dir: PathBuf,
-    pub destination: PathBuf,
+    // Required, but may be given with the command line instead
+    pub html_template_dir: Option<PathBuf>,
+    pub article_md_dir: Option<PathBuf>,
+    pub destination: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub feed: FeedKind,
+    #[serde(default)]
+    pub index_order: ArticleOrder,
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub no_cache: bool,
+    #[serde(default)]
+    pub force_assets: bool,
+    pub share_duplicate_assets: Option<bool>,
+    #[serde(default)]
+    pub bundles: BTreeMap<PathBuf, Vec<PathBuf>>,
+    #[serde(default)]
+    pub precompress: bool,
+    pub precompress_min_bytes: Option<u64>,
+    #[serde(default)]
+    pub check_links: bool,
+    #[serde(default)]
+    pub image_widths: Vec<u32>,
+    #[serde(default)]
+    pub download_remote_images: bool,
+    #[serde(default)]
+    pub sibling_assets: bool,
+    #[serde(default)]
+    pub article_base: ArticleBase,
+    pub assets_dir: Option<String>,
+    pub permalink: Option<Permalink>,
+    pub url_style: Option<UrlStyle>,
+    #[serde(default)]
+    pub article_filename_pattern: FilenamePattern,
+    pub markdown_extensions: Option<Vec<String>>,
+    pub related_articles: Option<usize>,
+    pub back_to_top_after: Option<usize>,
+    #[serde(default)]
+    pub date_format: DateFormat,
+    #[serde(default = "DateFormat::month")]
+    pub month_date_format: DateFormat,
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub robots_disallow: Vec<String>,
+    #[serde(default)]
+    pub site: SiteVariables,
+    pub log_format: Option<LogFormat>,
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    pub markdown_debounce_ms: Option<u64>,
+    pub template_debounce_ms: Option<u64>,
+    pub jobs: Option<usize>,
+    #[serde(default)]
+    pub generation: GenerationContext,
 }
 
 #[derive(Debug)]
@@ -14,16 +150,388 @@ pub struct Configuration {
     pub html_template_dir: Directory,
     pub article_md_dir: Directory,
     pub destination: Directory,
+    // `./data` if it exists without the configuration
+    pub data_dir: Option<Directory>,
+    pub feed: FeedKind,
+    // of the articles on the index only. The feed keeps the newest first and the sitemap the
+    // order of the pages, as the tag and the archive pages keep the newest first.
+    pub index_order: ArticleOrder,
+    pub base_url: Option<String>,
+    pub no_cache: bool,
+    pub force_assets: bool,
+    // The same article assets in the groups are written once to `assets/_shared`, which is off by
+    // default, as the pages refer to them by the other paths than the ones in the articles
+    pub share_duplicate_assets: bool,
+    // Path of each bundle in the destination -> the files in the template directory in the order,
+    // of which the bundle is `.css`, `.js` or `.mjs`
+    pub bundles: BTreeMap<PathBuf, Vec<PathBuf>>,
+    // `.gz` and `.br` of the text outputs of the release builds, of the sizes from the minimum
+    pub precompress: bool,
+    pub precompress_min_bytes: u64,
+    pub check_links: bool,
+    // Only from the command line
+    pub dry_run: bool,
+    pub image_widths: Vec<u32>,
+    // Remote images in the articles are downloaded and served from the site, off by default
+    pub download_remote_images: bool,
+    // The files next to the markdown which the articles refer to are copied next to their pages,
+    // off by default. The ones not referred to are never copied.
+    pub sibling_assets: bool,
+    // Directory of the articles and their assets in the destination
+    pub article_base: ArticleBase,
+    // Name of the directories of the assets next to the articles, `assets` by default, which
+    // they are copied to under `article_base` as they are named
+    pub assets_dir: String,
+    // under `article_base` unless configured, with `url_style` already applied
+    pub permalink: Permalink,
+    pub url_style: Option<UrlStyle>,
+    pub article_filename_pattern: FilenamePattern,
+    // Number of the related articles on each article page
+    pub related_articles: usize,
+    // Links back to the top after each `<h2>` section past this many, none without it
+    pub back_to_top_after: Option<usize>,
+    // of `${date}`, and the other for the articles without the day
+    pub date_format: DateFormat,
+    pub month_date_format: DateFormat,
+    // The articles are dated in it, which decides the future ones
+    pub timezone: Timezone,
+    // `Disallow:` rules in the generated robots.txt, like `/drafts/`
+    pub robots_disallow: Vec<String>,
+    // Variables in `[site]`, like `${site.title}` in the templates
+    pub site: SiteVariables,
+    // `PLATEMAKER_LOG_FORMAT` takes precedence over it
+    pub log_format: Option<LogFormat>,
+    // `skip` by default, or `follow` for the symlinked directories
+    pub symlinks: SymlinkPolicy,
+    // of the watchers, which may be longer for the templates rebuilding every page.
+    // The data files are the same as the templates.
+    pub markdown_debounce: Duration,
+    pub template_debounce: Duration,
+    // Threads of the parallel steps, the CPUs by default
+    pub jobs: usize,
+    pub generation: GenerationContext,
+}
+
+impl ConfigurationScheme {
+    // The top-level keys which are none of the configuration, like the misspelled ones,
+    // including the ones in the profiles
+    pub fn unknown_keys(content: &str) -> Vec<String> {
+        let Ok(table) = toml::from_str::<toml::Table>(content) else {
+            return vec![];
+        };
+        let unknown = |table: &toml::Table, prefix: &str| {
+            table
+                .keys()
+                // The profiles are taken out before the scheme, and are not nested in the profiles
+                .filter(|key| {
+                    if *key == "profiles" {
+                        !prefix.is_empty()
+                    } else {
+                        !known_keys().contains(&key.as_str())
+                    }
+                })
+                .map(|key| format!("{prefix}{key}"))
+                .collect::<Vec<_>>()
+        };
+
+        let mut keys = unknown(&table, "");
+        if let Some(toml::Value::Table(profiles)) = table.get("profiles") {
+            for (name, profile) in profiles {
+                if let toml::Value::Table(profile) = profile {
+                    keys.extend(unknown(profile, &format!("profiles.{name}.")));
+                }
+            }
+        }
+        keys
+    }
+
+    // `[profiles.<name>]` over the rest of the configuration, merging the tables key by key.
+    // `prod` is a release build unless `release` is set in its `generation`, or in the base.
+    pub fn with_profile(content: &str, profile: &str) -> Result<Self, ProfileError> {
+        let mut table = toml::from_str::<toml::Table>(content)?;
+        let profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            _ => toml::Table::new(),
+        };
+
+        let overrides = match profiles.get(profile) {
+            Some(toml::Value::Table(overrides)) => overrides.clone(),
+            Some(_) => return Err(ProfileError::NotTable(profile.to_string())),
+            None => {
+                return Err(ProfileError::Unknown {
+                    profile: profile.to_string(),
+                    available: profiles.keys().cloned().collect(),
+                });
+            }
+        };
+        merge_table(&mut table, overrides);
+
+        if profile == PROD_PROFILE
+            && let toml::Value::Table(generation) = table
+                .entry("generation")
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        {
+            generation
+                .entry("release")
+                .or_insert(toml::Value::Boolean(true));
+        }
+
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
+    pub fn override_with(self, args: &ConfigArgs) -> Self {
+        ConfigurationScheme {
+            html_template_dir: args.html_template_dir.clone().or(self.html_template_dir),
+            article_md_dir: args.article_md_dir.clone().or(self.article_md_dir),
+            destination: args.destination.clone().or(self.destination),
+            jobs: args.jobs.or(self.jobs),
+            ..self
+        }
+    }
+}
+
+// The keys of `ConfigurationScheme` as serde derives them, taken by a deserializer which only
+// records the fields of the struct asked for
+fn known_keys() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom(
+                "only the fields of the struct are taken",
+            ))
+        }
+
+        fn deserialize_struct<V: serde::de::Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom(
+                "only the fields of the struct are taken",
+            ))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = <ConfigurationScheme as serde::Deserialize>::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+fn merge_table(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge_table(base, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn debounce(millis: Option<u64>, key: &str) -> std::io::Result<Duration> {
+    match millis.unwrap_or(DEFAULT_DEBOUNCE_MS) {
+        0 => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("`{key}` should be more than 0"),
+        )),
+        millis => Ok(Duration::from_millis(millis)),
+    }
+}
+
+fn jobs(jobs: Option<usize>) -> std::io::Result<usize> {
+    match jobs {
+        Some(0) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "`jobs` should be more than 0",
+        )),
+        Some(jobs) => Ok(jobs),
+        None => Ok(std::thread::available_parallelism().map_or(1, NonZero::get)),
+    }
+}
+
+fn assets_dir(name: Option<String>) -> std::io::Result<String> {
+    let Some(name) = name else {
+        return Ok(DEFAULT_ASSETS_DIR.to_string());
+    };
+
+    // A name of the directory itself, matched to each component of the paths
+    let mut components = Path::new(&name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(name),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("`assets_dir` '{name}' should be a name of the directory, not a path"),
+        )),
+    }
+}
+
+fn bundles(
+    bundles: BTreeMap<PathBuf, Vec<PathBuf>>,
+) -> std::io::Result<BTreeMap<PathBuf, Vec<PathBuf>>> {
+    let unknown = bundles.keys().find(|output| {
+        !output
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["css", "js", "mjs"].contains(&ext.to_ascii_lowercase().as_str()))
+    });
+
+    if let Some(output) = unknown {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("bundle {} is none of .css, .js and .mjs", output.display()),
+        ));
+    }
+
+    // Without `./`, to be compared with the paths in the template directory
+    let relative = |path: &PathBuf| {
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect::<PathBuf>()
+    };
+    Ok(bundles
+        .iter()
+        .map(|(output, inputs)| (relative(output), inputs.iter().map(relative).collect()))
+        .collect())
+}
+
+fn required(value: Option<PathBuf>, key: &str) -> std::io::Result<PathBuf> {
+    value.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "`{key}` is required, but given neither with --{} nor in the configuration file. The command line takes precedence over the file, and the file over the defaults.",
+                key.replace('_', "-")
+            ),
+        )
+    })
 }
 
 impl TryFrom<ConfigurationScheme> for Configuration {
     type Error = std::io::Error;
 
     fn try_from(value: ConfigurationScheme) -> Result<Self, Self::Error> {
+        let site_host = value.base_url.as_deref().and_then(url_host);
+
         Ok(Configuration {
-            html_template_dir: Directory::new(value.html_template_dir)?,
-            article_md_dir: Directory::new(value.article_md_dir)?,
-            destination: Directory::new(value.destination)?,
+            html_template_dir: Directory::new(required(
+                value.html_template_dir,
+                "html_template_dir",
+            )?)?,
+            article_md_dir: Directory::new(required(value.article_md_dir, "article_md_dir")?)?,
+            destination: Directory::new(required(value.destination, "destination")?)?,
+            data_dir: match value.data_dir {
+                Some(data_dir) => Some(Directory::new(data_dir)?),
+                None => Directory::new(DEFAULT_DATA_DIR).ok(),
+            },
+            feed: value.feed,
+            index_order: value.index_order,
+            base_url: value.base_url,
+            no_cache: value.no_cache,
+            force_assets: value.force_assets,
+            share_duplicate_assets: value.share_duplicate_assets.unwrap_or_default(),
+            bundles: bundles(value.bundles)?,
+            precompress: value.precompress,
+            precompress_min_bytes: value
+                .precompress_min_bytes
+                .unwrap_or(DEFAULT_PRECOMPRESS_MIN_BYTES),
+            check_links: value.check_links,
+            dry_run: false,
+            image_widths: value.image_widths,
+            download_remote_images: value.download_remote_images,
+            sibling_assets: value.sibling_assets,
+            permalink: value
+                .permalink
+                .unwrap_or_else(|| value.article_base.permalink())
+                .with_style(value.url_style),
+            article_base: value.article_base,
+            assets_dir: assets_dir(value.assets_dir)?,
+            url_style: value.url_style,
+            article_filename_pattern: match &value.markdown_extensions {
+                Some(extensions) => value.article_filename_pattern.with_extensions(extensions),
+                None => value.article_filename_pattern,
+            },
+            related_articles: value.related_articles.unwrap_or(DEFAULT_RELATED_ARTICLES),
+            back_to_top_after: value.back_to_top_after,
+            date_format: value.date_format,
+            month_date_format: value.month_date_format,
+            timezone: value
+                .timezone
+                .as_deref()
+                .map(Timezone::from_str)
+                .transpose()?
+                .unwrap_or_default(),
+            robots_disallow: value.robots_disallow,
+            site: value.site,
+            log_format: value.log_format,
+            symlinks: value.symlinks,
+            markdown_debounce: debounce(value.markdown_debounce_ms, "markdown_debounce_ms")?,
+            template_debounce: debounce(value.template_debounce_ms, "template_debounce_ms")?,
+            jobs: jobs(value.jobs)?,
+            generation: GenerationContext {
+                site_host,
+                ..value.generation
+            },
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigurationScheme, jobs, merge_table};
+
+    #[test]
+    fn profiles_are_merged_into_the_nested_tables() {
+        let mut base = toml::from_str::<toml::Table>(
+            "destination = \"out\"\n[site]\ntitle = \"Blog\"\nauthor = { name = \"A\", url = \"/\" }",
+        )
+        .expect("toml to be parsed");
+        let overrides = toml::from_str::<toml::Table>(
+            "destination = \"dist\"\n[site]\nauthor = { name = \"B\" }\n[generation]\nrelease = true",
+        )
+        .expect("toml to be parsed");
+
+        merge_table(&mut base, overrides);
+
+        assert_eq!(
+            base,
+            toml::from_str::<toml::Table>(concat!(
+                "destination = \"dist\"\n",
+                "[site]\ntitle = \"Blog\"\nauthor = { name = \"B\", url = \"/\" }\n",
+                "[generation]\nrelease = true",
+            ))
+            .expect("toml to be parsed")
+        );
+    }
+
+    #[test]
+    fn jobs_are_more_than_zero() {
+        assert_eq!(jobs(Some(3)).expect("jobs to be valid"), 3);
+        assert!(jobs(None).expect("jobs to be valid") >= 1);
+        assert!(jobs(Some(0)).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_the_ones_out_of_the_scheme() {
+        assert_eq!(
+            ConfigurationScheme::unknown_keys(concat!(
+                "destination = \"out\"\njobs = 2\ndestinaton = \"typo\"\n",
+                "[profiles.dev]\ngeneration = { release = false }\nprofiles = {}\nfoo = 1",
+            )),
+            ["destinaton", "profiles.dev.foo", "profiles.dev.profiles"]
+        );
+    }
+}
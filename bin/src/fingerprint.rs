@@ -0,0 +1,348 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use loss72_platemaker_core::{
+    fs::{Directory, FSNode},
+    log,
+    model::GenerationContext,
+    util::{build_id, content_hash},
+};
+use loss72_platemaker_structure::{ArticleGroup, template::is_template_file};
+use loss72_platemaker_website::{ASSET_MANIFEST_FILE, AssetManifest};
+
+use crate::{
+    build_tasks::TaskResult,
+    bundle::{bundle_inputs, render_bundles},
+    config::Configuration,
+};
+
+// Pages keep their urls, and files like `robots.txt` or `favicon.ico` are looked up by name
+const FINGERPRINTED_EXTENSIONS: [&str; 12] = [
+    "css", "js", "mjs", "png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "woff", "woff2",
+];
+
+pub fn collect_asset_manifest(
+    config: &Configuration,
+    ctx: &GenerationContext,
+) -> TaskResult<AssetManifest> {
+    let mut manifest = AssetManifest::default();
+
+    if ctx.fingerprints() {
+        let inputs = bundle_inputs(config);
+        for (path, file) in assets_in(&config.html_template_dir)? {
+            if !is_template_file(&path) && !inputs.contains(&path) && is_fingerprinted(&path) {
+                manifest.insert_hashed(&path, &file_hash(&file)?);
+            }
+        }
+        for (path, bundle) in render_bundles(config, ctx)? {
+            manifest.insert(&path, bundle.as_bytes());
+        }
+    }
+
+    if !ctx.fingerprints() && !config.share_duplicate_assets {
+        return Ok(manifest);
+    }
+
+    let assets = article_assets(config)?;
+
+    if ctx.fingerprints() {
+        for (path, hash) in &assets {
+            if is_fingerprinted(path) {
+                manifest.insert_hashed(path, hash);
+            }
+        }
+    }
+
+    // The article assets of the same content in the different places, written once to be
+    // shared. They are all of the files, not only the fingerprinted ones, as the sharing is by
+    // the content.
+    if config.share_duplicate_assets {
+        let mut paths = HashMap::<&str, Vec<&Path>>::new();
+        for (path, hash) in &assets {
+            paths.entry(hash).or_default().push(path);
+        }
+
+        for (hash, paths) in paths {
+            if paths.len() > 1 {
+                for path in paths {
+                    manifest.insert_shared(path, hash);
+                }
+            }
+        }
+    }
+
+    Ok(manifest)
+}
+
+// The files next to the articles by their paths relative to the destination, with the hashes
+fn article_assets(config: &Configuration) -> TaskResult<Vec<(PathBuf, String)>> {
+    let mut assets = vec![];
+
+    for group in ArticleGroup::scan(&config.article_md_dir)? {
+        for dir in group.scan_asset_dirs(&config.article_md_dir, &config.assets_dir)? {
+            let dest = dir.destination_path(config.article_base.path());
+            for (path, file) in assets_in(dir.directory())? {
+                assets.push((dest.join(path), file_hash(&file)?));
+            }
+        }
+    }
+
+    Ok(assets)
+}
+
+// of the files by the paths, for watching not to read the same files again for each build
+static FILE_HASHES: LazyLock<Mutex<HashMap<PathBuf, FileHash>>> = LazyLock::new(Default::default);
+
+struct FileHash {
+    size: u64,
+    modified: SystemTime,
+    hash: String,
+}
+
+// `content_hash` of the file, which is read again only when the size or the modified time changed
+fn file_hash(path: &Path) -> std::io::Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    let (size, modified) = (metadata.len(), metadata.modified()?);
+
+    let mut hashes = FILE_HASHES.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(cached) = hashes.get(path)
+        && (cached.size, cached.modified) == (size, modified)
+    {
+        return Ok(cached.hash.clone());
+    }
+
+    let hash = content_hash(std::fs::read(path)?);
+    hashes.insert(
+        path.to_path_buf(),
+        FileHash {
+            size,
+            modified,
+            hash: hash.clone(),
+        },
+    );
+
+    Ok(hash)
+}
+
+// Renames the copied assets to the fingerprinted names, and records the manifest
+pub fn apply_asset_manifest(config: &Configuration, assets: &AssetManifest) -> TaskResult<()> {
+    // The copies are listed by the original names in dry runs
+    if config.dry_run {
+        return Ok(());
+    }
+
+    let destination = config.destination.path();
+    remove_stale_assets(destination, &written_asset_manifest(destination), assets)?;
+
+    // Not to leave the one of the previous build, which is compared in watching
+    if assets.is_empty() {
+        return match std::fs::remove_file(config.destination.path().join(ASSET_MANIFEST_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+
+    log!(section: "Fingerprinting asset files");
+
+    for (original, fingerprinted) in assets.renames() {
+        let original = config.destination.path().join(original);
+        if original.exists() {
+            log!(step: "Renaming {} to {}", original.display(), fingerprinted.display());
+            let fingerprinted = config.destination.path().join(fingerprinted);
+            // `assets/_shared` for the shared ones, which are renamed from each of the copies
+            if let Some(parent) = fingerprinted.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(&original, fingerprinted)?;
+        }
+    }
+
+    std::fs::write(
+        config.destination.path().join(ASSET_MANIFEST_FILE),
+        serde_json::to_string_pretty(assets).map_err(std::io::Error::from)?,
+    )?;
+
+    log!(ok: "Wrote {}", ASSET_MANIFEST_FILE);
+
+    Ok(())
+}
+
+fn written_asset_manifest(destination: &Path) -> AssetManifest {
+    std::fs::read_to_string(destination.join(ASSET_MANIFEST_FILE))
+        .ok()
+        .and_then(|written| serde_json::from_str(&written).ok())
+        .unwrap_or_default()
+}
+
+// The fingerprinted files of the previous build which are none of this one, like the ones of the
+// changed contents, as the names of them differ on every change
+fn remove_stale_assets(
+    destination: &Path,
+    previous: &AssetManifest,
+    assets: &AssetManifest,
+) -> std::io::Result<()> {
+    let current = assets
+        .renames()
+        .flat_map(|(original, fingerprinted)| [original, fingerprinted])
+        .collect::<HashSet<_>>();
+
+    for (_, fingerprinted) in previous.renames() {
+        if current.contains(&fingerprinted) {
+            continue;
+        }
+
+        let path = destination.join(&fingerprinted);
+        match std::fs::remove_file(&path) {
+            Ok(()) => log!(step: "Removed {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+// Whether the manifest is another one than the one written by the last build, in which the
+// assets are shared or fingerprinted differently
+pub fn asset_manifest_changed(config: &Configuration, assets: &AssetManifest) -> bool {
+    let written = std::fs::read_to_string(config.destination.path().join(ASSET_MANIFEST_FILE)).ok();
+    let current = (!assets.is_empty())
+        .then(|| serde_json::to_string_pretty(assets).ok())
+        .flatten();
+
+    written != current
+}
+
+// of the articles, the templates and the data, by their paths from each directory. The sizes and
+// the modification times tell the changes of the files without reading them, like the media.
+pub fn source_build_id(config: &Configuration) -> std::io::Result<String> {
+    let mut files = vec![];
+    for (name, dir) in [
+        ("md", Some(&config.article_md_dir)),
+        ("template", Some(&config.html_template_dir)),
+        ("data", config.data_dir.as_ref()),
+    ] {
+        let Some(dir) = dir else {
+            continue;
+        };
+
+        for file in dir
+            .try_iter_tree()?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter_map(FSNode::into_file)
+        {
+            let path = file.path().strip_prefix(dir.path()).unwrap_or(file.path());
+            let modified = file
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |modified| modified.as_nanos());
+            files.push((
+                format!("{name}/{}", path.to_string_lossy().replace('\\', "/")),
+                format!("{}:{modified}", file.size()?),
+            ));
+        }
+    }
+
+    Ok(build_id(
+        files
+            .iter()
+            .map(|(path, hash)| (path.as_str(), hash.as_str())),
+    ))
+}
+
+// of every file in `dir`, by the paths relative to it
+fn assets_in(dir: &Directory) -> std::io::Result<Vec<(PathBuf, PathBuf)>> {
+    Ok(dir
+        .try_iter_tree()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+        .filter_map(|file| {
+            let path = file.path().strip_prefix(dir.path()).ok()?.to_path_buf();
+            Some((path, file.path().to_path_buf()))
+        })
+        .collect())
+}
+
+fn is_fingerprinted(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| FINGERPRINTED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use loss72_platemaker_website::AssetManifest;
+
+    use super::{file_hash, is_fingerprinted, remove_stale_assets};
+
+    #[test]
+    fn fingerprinted_extensions_are_case_insensitive() {
+        assert!(is_fingerprinted(Path::new("styles/index.css")));
+        assert!(is_fingerprinted(Path::new("assets/PIC.PNG")));
+        assert!(!is_fingerprinted(Path::new("favicon.ico")));
+        assert!(!is_fingerprinted(Path::new("LICENSE")));
+    }
+
+    #[test]
+    fn file_hashes_follow_the_changes() {
+        let path = std::env::temp_dir().join(format!("platemaker-hash-{}", std::process::id()));
+        std::fs::write(&path, "a").expect("temp dir to be writable");
+        let first = file_hash(&path).expect("file to be hashed");
+        assert_eq!(file_hash(&path).expect("file to be hashed"), first);
+
+        std::fs::write(&path, "bb").expect("temp dir to be writable");
+        let second = file_hash(&path).expect("file to be hashed");
+        std::fs::remove_file(&path).ok();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn stale_fingerprinted_files_are_removed() {
+        let destination =
+            std::env::temp_dir().join(format!("platemaker-stale-{}", std::process::id()));
+        std::fs::create_dir_all(destination.join("styles")).expect("temp dir to be writable");
+
+        let manifest = |contents: &[(&str, &str)]| {
+            let mut manifest = AssetManifest::default();
+            for (path, content) in contents {
+                manifest.insert(Path::new(path), content.as_bytes());
+            }
+            manifest
+        };
+        let previous = manifest(&[("styles/index.css", "old"), ("styles/kept.css", "kept")]);
+        let current = manifest(&[("styles/index.css", "new"), ("styles/kept.css", "kept")]);
+
+        let written = |manifest: &AssetManifest| {
+            manifest
+                .renames()
+                .map(|(_, fingerprinted)| destination.join(fingerprinted))
+                .collect::<Vec<_>>()
+        };
+        for path in written(&previous).into_iter().chain(written(&current)) {
+            std::fs::write(path, "").expect("temp dir to be writable");
+        }
+
+        let result = remove_stale_assets(&destination, &previous, &current);
+        let exists = |manifest: &AssetManifest| {
+            written(manifest)
+                .iter()
+                .map(|path| path.exists())
+                .collect::<Vec<_>>()
+        };
+        let (previous, current) = (exists(&previous), exists(&current));
+        std::fs::remove_dir_all(&destination).ok();
+
+        result.expect("stale files to be removed");
+        assert_eq!(previous, [false, true]);
+        assert_eq!(current, [true, true]);
+    }
+}
@@ -0,0 +1,369 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use image::{DynamicImage, imageops::FilterType};
+use loss72_platemaker_construct::Change;
+use loss72_platemaker_core::{
+    log,
+    model::{Article, ArticleImage},
+    util::content_hash,
+    util::{escape_attribute, unescape_attribute},
+};
+
+use crate::{build_tasks::TaskResult, cache::BuildCache, config::Configuration};
+
+// in `assets_dir` next to the pages of the articles, like the assets copied there
+const REMOTE_IMAGE_DIR: &str = "remote";
+
+// The downloaded ones out of the destination, which are kept over `clean`
+const REMOTE_IMAGE_CACHE_DIR: &str = "./.platemaker-remote";
+
+#[derive(Debug, thiserror::Error)]
+enum DownloadError {
+    #[error("curl is not found")]
+    NoCurl,
+
+    #[error(transparent)]
+    Failed(#[from] std::io::Error),
+}
+
+pub fn generate_image_variants(
+    config: &Configuration,
+    articles: &mut [Article],
+    cache: &mut BuildCache,
+) -> TaskResult<()> {
+    if config.image_widths.is_empty() {
+        return Ok(());
+    }
+
+    log!(section: "Generating resized images");
+
+    for article in articles {
+        let dest_dir = article_dir(config, article);
+
+        for image in article.images.clone() {
+            let Some(srcset) = resize(config, &image, &dest_dir, cache)? else {
+                continue;
+            };
+
+            article.content = article.content.replace(
+                &format!(r#"<img src="{}""#, escape_attribute(&image.src)),
+                &format!(
+                    r#"<img src="{}" srcset="{}" sizes="(max-width: {width}px) 100vw, {width}px""#,
+                    escape_attribute(&image.src),
+                    escape_attribute(&srcset),
+                    width = image.width,
+                ),
+            );
+        }
+    }
+
+    log!(ok: "Generated resized images");
+
+    Ok(())
+}
+
+// Remote `<img src>` in the articles to the copies downloaded next to them, which are named by
+// the hash of the url, so the ones downloaded before are used as they are, even after `clean`.
+// Only with `download_remote_images`, and the ones which fail to download are left.
+pub fn localize_remote_images(config: &Configuration, articles: &mut [Article]) -> TaskResult<()> {
+    if !config.download_remote_images {
+        return Ok(());
+    }
+
+    log!(section: "Downloading remote images");
+
+    let cache_dir = Path::new(REMOTE_IMAGE_CACHE_DIR);
+    let mut downloaded = 0;
+    let mut no_curl = false;
+    for article in articles {
+        let dest_dir = article_dir(config, article);
+
+        for src in remote_srcs(&article.content) {
+            let url = unescape_attribute(&src);
+            let file_name = remote_file_name(&url);
+            let local = format!("./{}/{REMOTE_IMAGE_DIR}/{file_name}", config.assets_dir);
+            let dest = dest_dir
+                .join(&config.assets_dir)
+                .join(REMOTE_IMAGE_DIR)
+                .join(&file_name);
+
+            if !dest.exists() {
+                if config.dry_run {
+                    log!(step: "{:<9}  {}", Change::New, dest.display());
+                    continue;
+                }
+
+                let cached = cache_dir.join(&file_name);
+                if !cached.exists() {
+                    if no_curl {
+                        continue;
+                    }
+
+                    log!(step: "Downloading {}", url);
+                    match download(&url, &cached) {
+                        Ok(()) => downloaded += 1,
+                        Err(DownloadError::NoCurl) => {
+                            log!(warn: "curl is not found, leaving the urls of the remote images as they are");
+                            no_curl = true;
+                            continue;
+                        }
+                        Err(e) => {
+                            log!(warn: "Image {} could not be downloaded, leaving the url as it is: {}", url, e);
+                            continue;
+                        }
+                    }
+                }
+
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(&cached, &dest)?;
+            }
+
+            article.content = article.content.replace(
+                &format!(r#"<img src="{src}""#),
+                &format!(r#"<img src="{}""#, escape_attribute(&local)),
+            );
+        }
+    }
+
+    log!(ok: "Downloaded {} remote images", downloaded);
+
+    Ok(())
+}
+
+// Next to the article, which may be in a subdirectory of the group
+pub(crate) fn article_dir(config: &Configuration, article: &Article) -> PathBuf {
+    config
+        .destination
+        .path()
+        .join(config.article_base.path())
+        .join(&article.id.group)
+        .join(
+            Path::new(&article.id.slug)
+                .parent()
+                .unwrap_or(Path::new("")),
+        )
+}
+
+// As they are in the attributes, without the duplicates
+fn remote_srcs(content: &str) -> Vec<String> {
+    let mut srcs = content
+        .split(r#"<img src=""#)
+        .skip(1)
+        .filter_map(|rest| rest.split_once('"').map(|(src, _)| src))
+        .filter(|src| src.starts_with("http://") || src.starts_with("https://"))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    srcs.sort();
+    srcs.dedup();
+    srcs
+}
+
+// `https://example.com/pic.png?w=1` -> `1f2e3d4c5b6a7980.png`
+fn remote_file_name(url: &str) -> String {
+    let hash = &content_hash(url)[..16];
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|file_name| file_name.rsplit_once('.'))
+        .map(|(_, extension)| extension)
+        .filter(|extension| {
+            (1..=5).contains(&extension.len())
+                && extension.chars().all(|char| char.is_ascii_alphanumeric())
+        });
+
+    match extension {
+        Some(extension) => format!("{hash}.{}", extension.to_ascii_lowercase()),
+        None => hash.to_string(),
+    }
+}
+
+// With `curl`, which is there on most of the systems with TLS
+fn download(url: &str, dest: &Path) -> Result<(), DownloadError> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Not to leave a part of the image at the name which is taken as downloaded
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let output = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--max-time",
+            "30",
+            "--output",
+        ])
+        .arg(&partial)
+        .arg(url)
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => DownloadError::NoCurl,
+            _ => e.into(),
+        })?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial);
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )
+        .into());
+    }
+
+    Ok(std::fs::rename(partial, dest)?)
+}
+
+fn resize(
+    config: &Configuration,
+    image: &ArticleImage,
+    dest_dir: &Path,
+    cache: &mut BuildCache,
+) -> TaskResult<Option<String>> {
+    // Upscaled variants would only be larger without any more detail
+    let mut widths = config
+        .image_widths
+        .iter()
+        .copied()
+        .filter(|width| *width < image.width)
+        .collect::<Vec<_>>();
+    widths.sort();
+    widths.dedup();
+
+    if widths.is_empty() {
+        return Ok(None);
+    }
+
+    let hash = content_hash(std::fs::read(&image.source)?);
+    let mut decoded = None::<DynamicImage>;
+    let mut srcset = vec![];
+
+    for width in widths {
+        let src = variant_src(&image.src, width);
+        let Some(dest) = relative_path(&src).map(|path| dest_dir.join(path)) else {
+            log!(warn: "Image {} is outside of the article directory, skipping resizing", image.src);
+            return Ok(None);
+        };
+
+        let up_to_date = dest.exists() && cache.variant_hash(&dest) == Some(&hash);
+        if !up_to_date && config.dry_run {
+            let change = if dest.exists() {
+                Change::Changed
+            } else {
+                Change::New
+            };
+            log!(step: "{:<9}  {}", change, dest.display());
+        } else if !up_to_date {
+            let source = match &decoded {
+                Some(source) => source,
+                None => match image::open(&image.source) {
+                    Ok(source) => decoded.insert(source),
+                    Err(e) => {
+                        log!(warn: "Image {} could not be resized: {}", image.source.display(), e);
+                        return Ok(None);
+                    }
+                },
+            };
+
+            log!(step: "Resizing {} to {}px", image.source.display(), width);
+
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if let Err(e) = source
+                .resize(width, u32::MAX, FilterType::Lanczos3)
+                .save(&dest)
+            {
+                log!(warn: "Resized image {} could not be written: {}", dest.display(), e);
+                return Ok(None);
+            }
+
+            cache.insert_variant(dest, hash.clone());
+        }
+
+        srcset.push(format!("{src} {width}w"));
+    }
+
+    srcset.push(format!("{} {}w", image.src, image.width));
+
+    Ok(Some(srcset.join(", ")))
+}
+
+// `./assets/pic.png` -> `./assets/pic-640w.png`
+fn variant_src(src: &str, width: u32) -> String {
+    let (dir, file_name) = src
+        .rsplit_once('/')
+        .map_or(("", src), |(dir, file)| (dir, file));
+    let file_name = match file_name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{stem}-{width}w.{ext}"),
+        None => format!("{file_name}-{width}w"),
+    };
+
+    if dir.is_empty() {
+        file_name
+    } else {
+        format!("{dir}/{file_name}")
+    }
+}
+
+fn relative_path(src: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+
+    for component in Path::new(src).components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(component) => path.push(component),
+            _ => return None,
+        }
+    }
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{remote_file_name, remote_srcs};
+
+    #[test]
+    fn remote_files_are_named_by_the_urls() {
+        let name = remote_file_name("https://example.com/pics/Cat.PNG?w=1#top");
+        assert_eq!(name.len(), 16 + ".png".len());
+        assert!(name.ends_with(".png"));
+
+        assert_ne!(
+            remote_file_name("https://example.com/pic.png?w=1"),
+            remote_file_name("https://example.com/pic.png?w=2")
+        );
+        assert_eq!(remote_file_name("https://example.com/image").len(), 16);
+        assert_eq!(remote_file_name("https://example.com/v1.2/pic").len(), 16);
+        assert_eq!(
+            remote_file_name("https://example.com/a.toolongext").len(),
+            16
+        );
+    }
+
+    #[test]
+    fn remote_srcs_are_the_http_ones_without_the_duplicates() {
+        let content = concat!(
+            r#"<p><img src="https://example.com/b.png" alt=""><img src="./assets/a.png"></p>"#,
+            r#"<img src="http://example.com/a.png?x=1&amp;y=2"><img src="https://example.com/b.png">"#,
+            r#"<a href="https://example.com/c.png">c</a><img src="data:image/png;base64,AA">"#,
+        );
+
+        assert_eq!(
+            remote_srcs(content),
+            [
+                "http://example.com/a.png?x=1&amp;y=2",
+                "https://example.com/b.png"
+            ]
+        );
+    }
+}
@@ -1,62 +1,146 @@
 #![deny(clippy::unwrap_used)]
 
 pub mod build_tasks;
+mod bundle;
+mod cache;
 mod cli;
 pub mod cmd;
+mod compress;
 mod config;
 pub mod error;
+mod fingerprint;
+mod images;
+mod siblings;
 
 use std::path::Path;
 
 use anyhow::Context;
 use clap::Parser;
-use cli::Commands;
+use cli::{Commands, ConfigArgs};
 use cmd::{
     build::full_build,
+    clean::clean,
+    serve::{ServeParam, serve},
     watch::{WatchParam, watch_for_change},
 };
-use config::{Configuration, ConfigurationScheme};
+use config::{CONFIG_FILE, Configuration, ConfigurationScheme};
 use error::report_anyway_if_fail;
-use loss72_platemaker_core::{fs::File, log, model::GenerationContext};
+use loss72_platemaker_core::{
+    fs::{File, set_symlink_policy},
+    log,
+    log::{LOG_FORMAT_ENV, set_color, set_format, set_level},
+    model::GenerationContext,
+};
 
 fn main() -> Result<(), &'static str> {
     report_anyway_if_fail(|| {
         let args = Commands::parse();
+        set_level(args.log_level());
+        set_color(args.color());
 
-        let config = read_config(args.config())?;
+        let mut config = read_config(args.config())?;
+        config.no_cache |= args.no_cache();
+        config.force_assets |= args.force_assets();
+        config.check_links |= args.check_links();
+        config.dry_run = args.dry_run();
+        set_symlink_policy(config.symlinks);
+        // Once for all the builds, including the ones of the watchers
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(config.jobs)
+            .build_global()
+            .context("Failed to start the threads")?;
+        if let Some(format) = config.log_format
+            && std::env::var_os(LOG_FORMAT_ENV).is_none()
+        {
+            set_format(format);
+        }
+        let ctx = args.generation_context(&config.generation);
 
-        println!();
+        log::blank_line();
         match args {
-            Commands::Build(_) => build(&config, &(&args).into()),
-            Commands::Watch(ref watch_args) => watch(&config, &watch_args.into(), &(&args).into()),
+            Commands::Build(ref build_args) => build(&config, &ctx, build_args.clean),
+            Commands::Watch(ref watch_args) => watch(&config, &watch_args.into(), &ctx),
+            Commands::Serve(ref serve_args) => start_server(&config, &serve_args.into(), &ctx),
+            Commands::Clean(_) => Ok(clean(&config)?),
         }
     })
     .map_err(|_| "Failed due to the error above")
 }
 
-fn build(config: &Configuration, ctx: &GenerationContext) -> Result<(), anyhow::Error> {
-    Ok(full_build(config, ctx)?)
+fn build(
+    config: &Configuration,
+    ctx: &GenerationContext,
+    clean_first: bool,
+) -> Result<(), anyhow::Error> {
+    if clean_first {
+        clean(config)?;
+    }
+
+    let report = full_build(config, ctx)?;
+
+    // Exits with the failure for CI, even though the others are written
+    if !report.failures.is_empty() {
+        anyhow::bail!(
+            "{} of {} articles failed to build",
+            report.failures.len(),
+            report.failures.len() + report.built
+        );
+    }
+
+    Ok(())
 }
 
-fn watch(config: &Configuration, param: &WatchParam, ctx: &GenerationContext) -> Result<(), anyhow::Error> {
+fn watch(
+    config: &Configuration,
+    param: &WatchParam,
+    ctx: &GenerationContext,
+) -> Result<(), anyhow::Error> {
     Ok(watch_for_change(config, param, ctx)?)
 }
 
-fn read_config(path: &Path) -> Result<Configuration, anyhow::Error> {
+fn start_server(
+    config: &Configuration,
+    param: &ServeParam,
+    ctx: &GenerationContext,
+) -> Result<(), anyhow::Error> {
+    Ok(serve(config, param, ctx)?)
+}
+
+fn read_config(args: &ConfigArgs) -> Result<Configuration, anyhow::Error> {
+    let path = args.config.as_deref().unwrap_or(Path::new(CONFIG_FILE));
     log!(section: "Reading configuration {}", path.display());
 
-    File::new(path)
-        .context("Configuration file is not present or not available for reading")
-        .and_then(|file| {
-            file.read_to_string()
-                .context("Failed to read configuration")
-        })
-        .and_then(|content| {
-            toml::from_str::<ConfigurationScheme>(&content)
-                .context("Configuration file is not valid")
-        })
-        .and_then(|parsed_file| {
-            Configuration::try_from(parsed_file)
-                .context("Configuration contains invalid configuration")
-        })
+    let content = match File::new(path) {
+        Ok(file) => file
+            .read_to_string()
+            .context("Failed to read configuration")?,
+        // Only the command line and the defaults, if the file at the default path is missing
+        Err(_) if args.config.is_none() => {
+            log!(warn: "{} is not present, so only the command line and the defaults are used", path.display());
+            String::new()
+        }
+        Err(err) => {
+            return Err(err)
+                .context("Configuration file is not present or not available for reading");
+        }
+    };
+
+    for key in ConfigurationScheme::unknown_keys(&content) {
+        log!(warn: "Unknown configuration key `{}` in {}", key, path.display());
+    }
+
+    let parsed = match &args.profile {
+        Some(profile) => {
+            log!(step: "Applying the profile {}", profile);
+            ConfigurationScheme::with_profile(&content, profile)
+                .context("Configuration profile is not valid")
+        }
+        None => toml::from_str::<ConfigurationScheme>(&content)
+            .context("Configuration file is not valid"),
+    };
+
+    parsed.and_then(|parsed_file| {
+        Configuration::try_from(parsed_file.override_with(args))
+            .context("Configuration contains invalid configuration")
+    })
 }
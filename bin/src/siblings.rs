@@ -0,0 +1,120 @@
+use std::path::{Component, Path, PathBuf};
+
+use loss72_platemaker_construct::{copy_changes, copy_file_pairs};
+use loss72_platemaker_core::{
+    log,
+    model::Article,
+    util::{percent_decode, unescape_attribute},
+};
+use loss72_platemaker_structure::ArticleFile;
+
+use crate::{
+    build_tasks::{TaskResult, log_changes},
+    config::Configuration,
+    images::article_dir,
+};
+
+// Files right next to the markdown which the articles refer to, like `pic.png` beside `01_post.md`,
+// copied next to their pages with `sibling_assets`. The ones not referred to are left, as they may
+// be only for the sources. The references are rewritten to `./pic.png`, which they are copied to.
+pub fn copy_sibling_assets(
+    config: &Configuration,
+    files: &[ArticleFile],
+    articles: &mut [Article],
+) -> TaskResult<()> {
+    if !config.sibling_assets {
+        return Ok(());
+    }
+
+    log!(section: "Copying files next to the articles");
+
+    let mut pairs = vec![];
+    for article in articles {
+        let Some(file) = files.iter().find(|file| file.id == article.id) else {
+            continue;
+        };
+        let source_dir = file.file().path().parent().unwrap_or(Path::new(""));
+        let dest_dir = article_dir(config, article);
+
+        for url in relative_urls(&article.content) {
+            let Some((source, name, suffix)) = sibling_of(config, source_dir, &url) else {
+                continue;
+            };
+
+            pairs.push((
+                source.clone(),
+                dest_dir.join(source.file_name().unwrap_or_default()),
+            ));
+
+            let rewritten = format!("./{name}{suffix}");
+            if rewritten != url {
+                for attribute in ["src", "href"] {
+                    article.content = article.content.replace(
+                        &format!(r#" {attribute}="{url}""#),
+                        &format!(r#" {attribute}="{rewritten}""#),
+                    );
+                }
+            }
+        }
+    }
+
+    // Once for the files referred to more than once
+    pairs.sort();
+    pairs.dedup();
+
+    if config.dry_run {
+        log_changes(&copy_changes(&pairs, config.force_assets));
+        return Ok(());
+    }
+
+    let stats = copy_file_pairs(&pairs, config.force_assets, &|_, _| {})?;
+
+    log!(ok: "Copied {} files next to the articles, skipped {} unchanged", stats.copied, stats.skipped);
+
+    Ok(())
+}
+
+// of `src` and `href` as they are in the attributes, without the ones from the root or of the
+// other sites
+fn relative_urls(content: &str) -> Vec<String> {
+    let mut urls = [r#" src=""#, r#" href=""#]
+        .iter()
+        .flat_map(|attribute| content.split(attribute).skip(1))
+        .filter_map(|rest| rest.split_once('"').map(|(url, _)| url))
+        .filter(|url| !url.is_empty() && !url.starts_with(['/', '#', '?']) && !url.contains(':'))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+// The file in `dir` which `url` refers to, with its name as it is in the url and the query or the
+// fragment after it. The markdown files, and the ones in the subdirectories like the assets, are
+// not the siblings.
+fn sibling_of(config: &Configuration, dir: &Path, url: &str) -> Option<(PathBuf, String, String)> {
+    let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    let name = path.rsplit('/').next()?;
+
+    let mut source = dir.to_path_buf();
+    for component in Path::new(&percent_decode(&unescape_attribute(path))?).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                source.pop();
+            }
+            Component::Normal(component) => source.push(component),
+            _ => return None,
+        }
+    }
+
+    let file_name = source.file_name()?.to_str()?;
+    if source.parent() != Some(dir)
+        || config.article_filename_pattern.is_markdown(file_name)
+        || !source.is_file()
+    {
+        return None;
+    }
+
+    Some((source, name.to_string(), suffix.to_string()))
+}
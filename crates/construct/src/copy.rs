@@ -1,59 +1,227 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
 
 use loss72_platemaker_core::{
     fs::{Directory, FSNode, File},
     log,
 };
 
+use crate::Change;
+
+const MAX_COPY_THREADS: usize = 8;
+
+// Source and destination paths of a file to copy
+pub type CopyPair = (PathBuf, PathBuf);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CopyStats {
+    pub copied: usize,
+    // as the destination has the same size and modified time
+    pub skipped: usize,
+}
+
+impl CopyStats {
+    pub fn log(&self) {
+        log!(ok: "Copied {} files, skipped {} unchanged", self.copied, self.skipped);
+    }
+}
+
+// Files are copied even if unchanged when `force` is set, same for the functions below
 pub fn copy_dir_recursively(
     dir: &Directory,
     dest: &Directory,
     excludes: &[PathBuf],
+    force: bool,
 ) -> Result<(), std::io::Error> {
-    copy_files(
-        dir,
-        dest,
-        &dir.try_iter_tree()?
-            .filter_map(|node| match node {
-                Ok(FSNode::File(file)) => Some(Ok(file)),
-                Ok(_) => None,
-                Err(err) => Some(Err(err)),
-            })
-            .filter(|file| {
-                let Ok(file) = file else {
-                    return true;
-                };
-                !excluded(dir.path(), file.path(), excludes)
-            })
-            .collect::<Result<Vec<_>, _>>()?,
-    )
+    let pairs = copy_pairs_of_dir(dir, dest, excludes)?;
+    copy_file_pairs(&pairs, force, &|_, _| {})?.log();
+
+    Ok(())
 }
 
-pub fn copy_files(dir: &Directory, dest: &Directory, files: &[File]) -> Result<(), std::io::Error> {
-    for file in files {
-        copy_individual_file(dir, dest, file)?;
-    }
+// Files in `dir` to be copied into `dest` keeping the directory structure, which can be
+// gathered from several directories to copy them at once with `copy_file_pairs`
+pub fn copy_pairs_of_dir(
+    dir: &Directory,
+    dest: &Directory,
+    excludes: &[PathBuf],
+) -> Result<Vec<CopyPair>, std::io::Error> {
+    Ok(dir
+        .try_iter_tree()?
+        .filter_map(|node| match node {
+            Ok(FSNode::File(file)) => Some(Ok(file)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .filter(|file| {
+            let Ok(file) = file else {
+                return true;
+            };
+            !excluded(dir.path(), file.path(), excludes)
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .map(|file| copy_pair(dir, dest, file))
+        .collect())
+}
 
-    log!(ok: "Copied");
+pub fn copy_files(
+    dir: &Directory,
+    dest: &Directory,
+    files: &[File],
+    force: bool,
+) -> Result<(), std::io::Error> {
+    let pairs = files
+        .iter()
+        .map(|file| copy_pair(dir, dest, file))
+        .collect::<Vec<_>>();
+    copy_file_pairs(&pairs, force, &|_, _| {})?.log();
 
     Ok(())
 }
 
+// Copies with a bounded number of threads, stopping at the first error.
+// `progress` receives the number of processed files and the total, from any of the threads.
+pub fn copy_file_pairs(
+    pairs: &[CopyPair],
+    force: bool,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Result<CopyStats, std::io::Error> {
+    // Directories are made up front in order, so threads never race on creating them
+    let mut dirs = pairs
+        .iter()
+        .filter_map(|(_, dest)| dest.parent())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // Within `jobs` of the global pool, which the other parallel steps run in
+    let threads = rayon::current_num_threads()
+        .min(MAX_COPY_THREADS)
+        .min(pairs.len());
+
+    let next = AtomicUsize::new(0);
+    let processed = AtomicUsize::new(0);
+    let skipped = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let first_error = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let Some((source, dest)) = pairs.get(next.fetch_add(1, Ordering::Relaxed))
+                    else {
+                        break;
+                    };
+
+                    if !force && is_unchanged(source, dest) {
+                        skipped.fetch_add(1, Ordering::Relaxed);
+                    } else if let Err(error) = copy_with_modified_time(source, dest) {
+                        failed.store(true, Ordering::Relaxed);
+                        first_error
+                            .lock()
+                            .expect("no thread to panic while holding the error")
+                            .get_or_insert(error);
+                        break;
+                    } else {
+                        log!(detail: "Copied {} to {}", source.display(), dest.display());
+                    }
+
+                    progress(processed.fetch_add(1, Ordering::Relaxed) + 1, pairs.len());
+                }
+            });
+        }
+    });
+
+    match first_error
+        .into_inner()
+        .expect("no thread to panic while holding the error")
+    {
+        Some(error) => Err(error),
+        None => {
+            let skipped = skipped.into_inner();
+            Ok(CopyStats {
+                copied: pairs.len() - skipped,
+                skipped,
+            })
+        }
+    }
+}
+
+// What `copy_file_pairs` would do to each destination, for dry runs
+pub fn copy_changes(pairs: &[CopyPair], force: bool) -> Vec<(&Path, Change)> {
+    pairs
+        .iter()
+        .map(|(source, dest)| {
+            let change = if !dest.exists() {
+                Change::New
+            } else if !force && is_unchanged(source, dest) {
+                Change::Unchanged
+            } else {
+                Change::Changed
+            };
+
+            (dest.as_path(), change)
+        })
+        .collect()
+}
+
 pub fn copy_individual_file(
     dir: &Directory,
     dest: &Directory,
     file: &File,
+    force: bool,
 ) -> Result<(), std::io::Error> {
-    log!(step: "Copying file: {}", file.path().display());
+    let (source, dest) = copy_pair(dir, dest, file);
 
-    let subpath_in_dest = file.path().strip_prefix(dir.path()).unwrap_or(file.path());
-    let dest = dest.path().join(subpath_in_dest);
+    if !force && is_unchanged(&source, &dest) {
+        log!(step: "Skipping unchanged file: {}", file.path().display());
+        return Ok(());
+    }
+
+    log!(step: "Copying file: {}", file.path().display());
 
     if let Some(parent) = dest.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    std::fs::copy(file.path(), dest).map(|_| ())
+    copy_with_modified_time(&source, &dest)
+}
+
+fn is_unchanged(source: &Path, dest: &Path) -> bool {
+    let (Ok(source), Ok(dest)) = (source.metadata(), dest.metadata()) else {
+        return false;
+    };
+
+    source.len() == dest.len()
+        && source
+            .modified()
+            .is_ok_and(|modified| dest.modified().is_ok_and(|dest| dest == modified))
+}
+
+// The modified time is carried over to tell if the copy is up to date next time
+fn copy_with_modified_time(source: &Path, dest: &Path) -> Result<(), std::io::Error> {
+    std::fs::copy(source, dest)?;
+
+    std::fs::File::options()
+        .write(true)
+        .open(dest)?
+        .set_modified(source.metadata()?.modified()?)
+}
+
+fn copy_pair(dir: &Directory, dest: &Directory, file: &File) -> CopyPair {
+    let subpath_in_dest = file.path().strip_prefix(dir.path()).unwrap_or(file.path());
+
+    (file.path().to_path_buf(), dest.path().join(subpath_in_dest))
 }
 
 fn excluded(root: &Path, path: &Path, excludes: &[PathBuf]) -> bool {
@@ -67,3 +235,100 @@ fn excluded(root: &Path, path: &Path, excludes: &[PathBuf]) -> bool {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{CopyStats, copy_file_pairs};
+
+    #[test]
+    fn copies_files_in_parallel() {
+        let root = std::env::temp_dir().join(format!("platemaker-copy-{}", std::process::id()));
+        let source = root.join("source");
+        std::fs::create_dir_all(&source).expect("temp dir to be writable");
+
+        let pairs = (0..20)
+            .map(|index| {
+                let path = source.join(format!("{index}.txt"));
+                std::fs::write(&path, index.to_string()).expect("temp dir to be writable");
+                (
+                    path,
+                    root.join("dest")
+                        .join((index % 3).to_string())
+                        .join(format!("{index}.txt")),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let reported = AtomicUsize::new(0);
+        let stats = copy_file_pairs(&pairs, false, &|_, total| {
+            assert_eq!(total, 20);
+            reported.fetch_add(1, Ordering::Relaxed);
+        })
+        .expect("files to be copied");
+
+        assert_eq!(reported.into_inner(), 20);
+        assert_eq!(stats.copied, 20);
+        for (index, (_, dest)) in pairs.iter().enumerate() {
+            assert_eq!(std::fs::read_to_string(dest).ok(), Some(index.to_string()));
+        }
+
+        let missing = [(source.join("missing.txt"), root.join("dest/missing.txt"))];
+        assert!(copy_file_pairs(&missing, false, &|_, _| {}).is_err());
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn skips_unchanged_files() {
+        let root = std::env::temp_dir().join(format!("platemaker-skip-{}", std::process::id()));
+        std::fs::create_dir_all(&root).expect("temp dir to be writable");
+
+        let pairs = ["a", "b"].map(|name| {
+            let path = root.join(format!("{name}.txt"));
+            std::fs::write(&path, name).expect("temp dir to be writable");
+            (path, root.join("dest").join(format!("{name}.txt")))
+        });
+
+        let copy = |force| copy_file_pairs(&pairs, force, &|_, _| {}).expect("files to be copied");
+
+        assert_eq!(
+            copy(false),
+            CopyStats {
+                copied: 2,
+                skipped: 0
+            }
+        );
+        assert_eq!(
+            copy(false),
+            CopyStats {
+                copied: 0,
+                skipped: 2
+            }
+        );
+
+        std::fs::write(&pairs[0].0, "changed").expect("temp dir to be writable");
+        assert_eq!(
+            copy(false),
+            CopyStats {
+                copied: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(&pairs[0].1).ok().as_deref(),
+            Some("changed")
+        );
+
+        assert_eq!(
+            copy(true),
+            CopyStats {
+                copied: 2,
+                skipped: 0
+            }
+        );
+
+        std::fs::remove_dir_all(root).ok();
+    }
+}
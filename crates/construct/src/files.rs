@@ -1,10 +1,29 @@
 use std::{
     any::type_name,
+    collections::HashSet,
+    io::Write,
     path::{Path, PathBuf},
 };
 
 use loss72_platemaker_core::{log, util::get_slice_by_char};
 
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConstructionError {
+    #[error("Failed to create the directory {}: {source}", path.display())]
+    CreateDir {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to write {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
 #[derive(Debug)]
 pub struct Construction<'c> {
     pub dir: &'c Path,
@@ -58,7 +77,7 @@ impl ConstructionPlan<'_> {
 }
 
 impl Construction<'_> {
-    pub fn plan(&self, root: &Path) -> ConstructionPlan {
+    pub fn plan(&self, root: &Path) -> ConstructionPlan<'_> {
         let mut plan = self._plan(root);
 
         plan.dirs.sort_by_key(|x| x.as_os_str().len());
@@ -66,7 +85,7 @@ impl Construction<'_> {
         plan
     }
 
-    fn _plan(&self, parent: &Path) -> ConstructionPlan {
+    fn _plan(&self, parent: &Path) -> ConstructionPlan<'_> {
         let root = parent.join(self.dir);
 
         let mut plan = ConstructionPlan {
@@ -101,27 +120,158 @@ impl Construction<'_> {
     }
 }
 
+// Of an output file compared with the one in the destination
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    New,
+    Changed,
+    Unchanged,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Change::New => "new",
+            Change::Changed => "changed",
+            Change::Unchanged => "unchanged",
+        })
+    }
+}
+
 impl ConstructionPlan<'_> {
-    pub fn execute(&self) -> Result<(), std::io::Error> {
-        for dir in self.dirs.iter().rev() {
-            log!(step: "Creating dir {}", dir.display());
-            std::fs::create_dir_all(dir)?;
+    // What `execute` would do to each file, for dry runs
+    pub fn changes(&self) -> Vec<(&Path, Change)> {
+        let mut buffer = Vec::new();
+
+        self.files
+            .iter()
+            .map(|(path, content)| {
+                normalize_output(content, &mut buffer);
+                let change = match std::fs::read(path) {
+                    Ok(existing) if existing == buffer => Change::Unchanged,
+                    Ok(_) => Change::Changed,
+                    Err(_) if path.exists() => Change::Changed,
+                    Err(_) => Change::New,
+                };
+
+                (path.as_path(), change)
+            })
+            .collect()
+    }
+
+    // Directories are made as the files need them, and the ones left empty at last
+    pub fn execute(&self) -> Result<(), ConstructionError> {
+        let mut created = HashSet::new();
+        // The output of each file is put in it from the start, so it is kept for the next one
+        let mut buffer = Vec::with_capacity(WRITE_BUFFER_SIZE);
+
+        for (path, content) in self.files.iter() {
+            if let Some(parent) = path.parent() {
+                create_dir(parent, &mut created)?;
+            }
+
+            log!(step: "Writing file {}", path.display());
+            write_atomically(&mut buffer, path, content, replace).map_err(|source| {
+                ConstructionError::Write {
+                    path: path.clone(),
+                    source,
+                }
+            })?;
         }
 
-        for file in self.files.iter() {
-            log!(step: "Writing file {}", file.0.display());
-            std::fs::write(&file.0, file.1)?;
+        for dir in self.dirs.iter() {
+            create_dir(dir, &mut created)?;
         }
 
         Ok(())
     }
 }
 
+// UTF-8 without the BOM and with `\n` line endings, whichever the templates were written in.
+// The BOMs are removed wherever they are, as the ones of the partials end up in the pages.
+// `buffer` is cleared first, so nothing of the previous file is left in it.
+fn normalize_output(content: &str, buffer: &mut Vec<u8>) {
+    buffer.clear();
+
+    let mut rest = content;
+    while let Some(index) = rest.find(['\r', '\u{feff}']) {
+        buffer.extend_from_slice(&rest.as_bytes()[..index]);
+        rest = match rest[index..].strip_prefix('\r') {
+            Some(after) => {
+                buffer.push(b'\n');
+                after.strip_prefix('\n').unwrap_or(after)
+            }
+            None => &rest[index + '\u{feff}'.len_utf8()..],
+        };
+    }
+    buffer.extend_from_slice(rest.as_bytes());
+}
+
+// Into a temporary file next to `path` renamed over it, so that the readers including
+// the dev server never see a half-written page even if the build is killed
+fn write_atomically(
+    buffer: &mut Vec<u8>,
+    path: &Path,
+    content: &str,
+    rename: impl FnOnce(&Path, &Path) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let temp = temp_path(path);
+
+    // The whole output is written at once from the buffer, and the file is closed before
+    // renaming, which Windows doesn't allow for the open files
+    normalize_output(content, buffer);
+    let written = std::fs::File::create(&temp).and_then(|mut file| file.write_all(buffer));
+
+    let result = written.and_then(|()| rename(&temp, path));
+    if result.is_err() {
+        std::fs::remove_file(&temp).ok();
+    }
+
+    result
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
+
+fn replace(temp: &Path, path: &Path) -> std::io::Result<()> {
+    match std::fs::rename(temp, path) {
+        // `rename` replaces the existing file on Windows too, but not the read-only ones
+        #[cfg(windows)]
+        Err(error) if error.kind() == std::io::ErrorKind::PermissionDenied && path.is_file() => {
+            let mut permissions = path.metadata()?.permissions();
+            permissions.set_readonly(false);
+            std::fs::set_permissions(path, permissions)?;
+
+            std::fs::rename(temp, path)
+        }
+        result => result,
+    }
+}
+
+fn create_dir<'p>(dir: &'p Path, created: &mut HashSet<&'p Path>) -> Result<(), ConstructionError> {
+    if created.contains(dir) {
+        return Ok(());
+    }
+
+    log!(step: "Creating dir {}", dir.display());
+    std::fs::create_dir_all(dir).map_err(|source| ConstructionError::CreateDir {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    created.insert(dir);
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
 
-    use crate::{ConstructFile, Construction};
+    use super::{replace, temp_path, write_atomically};
+    use crate::{Change, ConstructFile, Construction, ConstructionError, ConstructionPlan};
 
     #[cfg(test)]
     #[test]
@@ -162,6 +312,146 @@ mod tests {
             }],
         };
 
-        panic!("{:#?}", construction.plan(Path::new("/root")));
+        let plan = construction.plan(Path::new("/root"));
+
+        assert_eq!(
+            plan.files
+                .iter()
+                .map(|(path, content)| (path.to_string_lossy().to_string(), *content))
+                .collect::<Vec<_>>(),
+            [
+                ("/root/pages/1.html", "AAA"),
+                ("/root/pages/2.html", "AAA"),
+                ("/root/pages/3.html", "AAA"),
+                ("/root/pages/sub-1/1.html", "A"),
+                ("/root/pages/sub-1/2.html", "B"),
+                ("/root/pages/sub-1/3.html", "C"),
+            ]
+            .map(|(path, content)| (path.to_string(), content))
+        );
+        assert!(plan.dirs.contains(&Path::new("/root/pages").to_path_buf()));
+        assert!(
+            plan.dirs
+                .contains(&Path::new("/root/pages/sub-1").to_path_buf())
+        );
+    }
+
+    #[test]
+    fn failed_write_keeps_the_written_files() {
+        let root = std::env::temp_dir().join(format!("platemaker-plan-{}", std::process::id()));
+        let content = "<p>page</p>".repeat(20_000);
+        let plan = ConstructionPlan {
+            dirs: vec![root.clone(), root.join("empty")],
+            files: vec![
+                (root.join("pages/1.html"), content.as_str()),
+                // Directory in the way
+                (root.join("pages"), "B"),
+                (root.join("pages/3.html"), "C"),
+            ],
+        };
+
+        let error = plan.execute().expect_err("directory not to be overwritten");
+
+        assert!(
+            matches!(&error, ConstructionError::Write { path, .. } if path == &root.join("pages"))
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("pages/1.html")).ok(),
+            Some(content.clone())
+        );
+        assert!(!root.join("pages/3.html").exists());
+
+        let plan = ConstructionPlan {
+            files: vec![(root.join("pages/3.html"), "C")],
+            ..plan
+        };
+        assert_eq!(
+            plan.changes(),
+            [(root.join("pages/3.html").as_path(), Change::New)]
+        );
+        plan.execute().expect("files to be written");
+        assert!(root.join("empty").is_dir());
+
+        let plan = ConstructionPlan {
+            files: vec![
+                (root.join("pages/3.html"), "C"),
+                (root.join("pages/1.html"), "A"),
+            ],
+            ..plan
+        };
+        assert_eq!(
+            plan.changes(),
+            [
+                (root.join("pages/3.html").as_path(), Change::Unchanged),
+                (root.join("pages/1.html").as_path(), Change::Changed),
+            ]
+        );
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn outputs_have_no_bom_nor_crlf() {
+        let root = std::env::temp_dir().join(format!("platemaker-newlines-{}", std::process::id()));
+        let plan = ConstructionPlan {
+            dirs: vec![],
+            files: vec![(
+                root.join("index.html"),
+                "\u{feff}<p>\r\n{{partial}}\u{feff}\r\n</p>\rend\n",
+            )],
+        };
+
+        plan.execute().expect("files to be written");
+
+        let written = std::fs::read(root.join("index.html")).expect("file to be written");
+        assert_eq!(written, b"<p>\n{{partial}}\n</p>\nend\n");
+        assert!(!written.windows(2).any(|bytes| bytes == b"\r\n"));
+        assert_eq!(
+            plan.changes(),
+            [(root.join("index.html").as_path(), Change::Unchanged)]
+        );
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn failure_before_rename_keeps_the_old_file() {
+        let root = std::env::temp_dir().join(format!("platemaker-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&root).expect("temp dir to be writable");
+        let path = root.join("index.html");
+        std::fs::write(&path, "old").expect("temp dir to be writable");
+
+        let mut buffer = vec![];
+        let result = write_atomically(&mut buffer, &path, "new", |_, _| {
+            Err(std::io::Error::other("killed before rename"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).ok().as_deref(), Some("old"));
+        assert!(!temp_path(&path).exists());
+
+        write_atomically(&mut buffer, &path, "new", replace).expect("file to be replaced");
+        assert_eq!(std::fs::read_to_string(&path).ok().as_deref(), Some("new"));
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn failed_writes_leave_nothing_to_the_next_files() {
+        let root = std::env::temp_dir().join(format!("platemaker-buffer-{}", std::process::id()));
+        std::fs::create_dir_all(&root).expect("temp dir to be writable");
+        let (failed, next) = (root.join("failed.html"), root.join("next.html"));
+
+        let mut buffer = vec![];
+        let result = write_atomically(&mut buffer, &failed, "\u{feff}long\r\ncontent", |_, _| {
+            Err(std::io::Error::other("failed to rename"))
+        });
+        assert!(result.is_err());
+
+        write_atomically(&mut buffer, &next, "ok\r", replace).expect("file to be written");
+        let contents = (std::fs::read(&failed).ok(), std::fs::read(&next).ok());
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(contents, (None, Some(b"ok\n".to_vec())));
     }
 }
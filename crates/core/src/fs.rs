@@ -1,4 +1,12 @@
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Cow,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
+
+use serde::Deserialize;
 
 mod dir;
 mod file;
@@ -6,6 +14,34 @@ mod file;
 pub use dir::Directory;
 pub use file::File;
 
+// Whether the traversals go into the symlinks, or yield them as `FSNode::Unknown`.
+// The followed ones keep their paths in the tree rather than the ones they link to.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    #[default]
+    Skip,
+    // The directories linking back to the ones above them are skipped, not to loop forever
+    Follow,
+}
+
+static FOLLOWS_SYMLINKS: AtomicBool = AtomicBool::new(false);
+
+// For `try_iter_content` and `try_iter_tree`, which are without the policy
+pub fn set_symlink_policy(policy: SymlinkPolicy) {
+    FOLLOWS_SYMLINKS.store(policy == SymlinkPolicy::Follow, Ordering::Relaxed);
+}
+
+impl SymlinkPolicy {
+    fn current() -> Self {
+        if FOLLOWS_SYMLINKS.load(Ordering::Relaxed) {
+            SymlinkPolicy::Follow
+        } else {
+            SymlinkPolicy::Skip
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum FSNode {
     File(File),
@@ -22,6 +58,24 @@ impl FSNode {
         }
     }
 
+    // Cached in the files. The unknown ones are not followed, as they may be skipped symlinks.
+    pub fn metadata(&self) -> std::io::Result<Cow<'_, Metadata>> {
+        match self {
+            FSNode::File(file) => file.metadata().map(Cow::Borrowed),
+            FSNode::Directory(directory) => directory.metadata().map(Cow::Owned),
+            FSNode::Unknown(path) => std::fs::symlink_metadata(path).map(Cow::Owned),
+        }
+    }
+
+    // in bytes
+    pub fn size(&self) -> std::io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    pub fn modified(&self) -> std::io::Result<SystemTime> {
+        self.metadata()?.modified()
+    }
+
     pub fn file(&self) -> Option<&File> {
         if let Self::File(file) = self {
             Some(file)
@@ -1,9 +1,10 @@
 use std::{
-    iter::Peekable,
+    fs::DirEntry,
     path::{Path, PathBuf},
 };
 
-use super::{FSNode, File};
+use super::{FSNode, File, SymlinkPolicy};
+use crate::log;
 
 #[derive(Clone, Debug, serde::Deserialize)]
 pub struct Directory(PathBuf);
@@ -21,10 +22,15 @@ impl Directory {
         Ok(Directory(path.to_path_buf()))
     }
 
+    // Tolerates the others making the same directory at the same time, like the copies in
+    // parallel, so every caller gets the directory whoever made it
     pub fn new_with_mkdir(path: impl AsRef<Path>) -> std::io::Result<Self> {
         let path = path.as_ref();
-        if !path.exists() {
-            std::fs::create_dir_all(path)?;
+        match std::fs::create_dir_all(path) {
+            Err(err) if !(err.kind() == std::io::ErrorKind::AlreadyExists && path.is_dir()) => {
+                return Err(err);
+            }
+            _ => {}
         }
 
         Directory::new(path)
@@ -38,6 +44,10 @@ impl Directory {
         &self.0
     }
 
+    pub fn metadata(&self) -> std::io::Result<std::fs::Metadata> {
+        std::fs::metadata(self.path())
+    }
+
     pub fn get_child(&self, path: impl AsRef<Path>) -> Option<std::io::Result<Directory>> {
         let child_path = self.path().join(path);
         if child_path.exists() {
@@ -77,38 +87,88 @@ impl Directory {
     pub fn try_iter_content(
         &self,
     ) -> Result<impl Iterator<Item = std::io::Result<FSNode>>, std::io::Error> {
-        Ok(self.path().read_dir()?.flat_map(|entry| {
-            entry.map(|entry| {
-                let path = entry.path();
-
-                if path.is_file() {
-                    File::new(&path).map(FSNode::File)
-                } else if path.is_dir() {
-                    Directory::new(&path).map(FSNode::Directory)
-                } else {
-                    Ok(FSNode::Unknown(path.to_path_buf()))
-                }
-            })
-        }))
+        self.try_iter_content_with(SymlinkPolicy::current())
+    }
+
+    pub fn try_iter_content_with(
+        &self,
+        policy: SymlinkPolicy,
+    ) -> Result<impl Iterator<Item = std::io::Result<FSNode>>, std::io::Error> {
+        Ok(self
+            .path()
+            .read_dir()?
+            .map(move |entry| entry_node(entry?, policy)))
     }
 
     pub fn try_iter_tree(&self) -> Result<RecursiveIterator, std::io::Error> {
-        RecursiveIterator::new(self.path())
+        self.try_iter_tree_with(SymlinkPolicy::current())
+    }
+
+    pub fn try_iter_tree_with(
+        &self,
+        policy: SymlinkPolicy,
+    ) -> Result<RecursiveIterator, std::io::Error> {
+        RecursiveIterator::new(self.path(), policy, vec![])
     }
 }
 
+// The entries are under the canonical directory, so their paths are kept as they are,
+// including the ones in the followed symlinks
+fn entry_node(entry: DirEntry, policy: SymlinkPolicy) -> std::io::Result<FSNode> {
+    let path = entry.path();
+    let file_type = entry.file_type()?;
+
+    let (is_file, is_dir) = match (file_type.is_symlink(), policy) {
+        (false, _) => (file_type.is_file(), file_type.is_dir()),
+        (true, SymlinkPolicy::Skip) => {
+            log!(detail: "Skipping the symlink {}", path.display());
+            (false, false)
+        }
+        // The broken links are unknown
+        (true, SymlinkPolicy::Follow) => std::fs::metadata(&path)
+            .map_or((false, false), |metadata| {
+                (metadata.is_file(), metadata.is_dir())
+            }),
+    };
+
+    Ok(if is_file {
+        FSNode::File(File::new_unchecked(path))
+    } else if is_dir {
+        FSNode::Directory(Directory::new_unchecked(path))
+    } else {
+        FSNode::Unknown(path)
+    })
+}
+
 pub struct RecursiveIterator {
-    current: Peekable<std::fs::ReadDir>,
+    current: std::fs::ReadDir,
     child: Option<Box<RecursiveIterator>>,
+    policy: SymlinkPolicy,
+    // The canonical paths of the directory and the ones above it, only to follow the symlinks
+    ancestors: Vec<PathBuf>,
 }
 
 impl RecursiveIterator {
-    fn new(dir: &Path) -> Result<Self, std::io::Error> {
+    fn new(
+        dir: &Path,
+        policy: SymlinkPolicy,
+        mut ancestors: Vec<PathBuf>,
+    ) -> Result<Self, std::io::Error> {
+        if policy == SymlinkPolicy::Follow {
+            ancestors.push(dir.canonicalize()?);
+        }
+
         Ok(Self {
-            current: dir.read_dir()?.peekable(),
+            current: dir.read_dir()?,
             child: None,
+            policy,
+            ancestors,
         })
     }
+
+    fn loops_back(&self, dir: &Path) -> std::io::Result<bool> {
+        Ok(self.policy == SymlinkPolicy::Follow && self.ancestors.contains(&dir.canonicalize()?))
+    }
 }
 
 impl Iterator for RecursiveIterator {
@@ -119,27 +179,36 @@ impl Iterator for RecursiveIterator {
             return Some(child_next);
         }
 
-        let node = match self.current.next()? {
+        let node = match self
+            .current
+            .next()?
+            .and_then(|entry| entry_node(entry, self.policy))
+        {
             Ok(node) => node,
             Err(err) => return Some(Err(err)),
         };
 
-        let path = node.path();
+        let FSNode::Directory(dir) = node else {
+            return Some(Ok(node));
+        };
 
-        if path.is_file() {
-            let file = File::new(&path).expect("entry to be exist and file");
-            Some(Ok(FSNode::File(file)))
-        } else if path.is_dir() {
-            self.child = Some(Box::new(match RecursiveIterator::new(&path) {
+        match self.loops_back(dir.path()) {
+            Ok(true) => {
+                log!(warn: "Skipping {}, which links to a directory above it", dir.path().display());
+                return Some(Ok(FSNode::Unknown(dir.path().to_path_buf())));
+            }
+            Ok(false) => {}
+            Err(err) => return Some(Err(err)),
+        }
+
+        self.child = Some(Box::new(
+            match RecursiveIterator::new(dir.path(), self.policy, self.ancestors.clone()) {
                 Ok(iter) => iter,
                 Err(err) => return Some(Err(err)),
-            }));
+            },
+        ));
 
-            let dir = Directory::new(&path).expect("entry to be exist and file");
-            Some(Ok(FSNode::Directory(dir)))
-        } else {
-            Some(Ok(FSNode::Unknown(path.to_path_buf())))
-        }
+        Some(Ok(FSNode::Directory(dir)))
     }
 }
 
@@ -148,3 +217,90 @@ impl From<Directory> for FSNode {
         FSNode::Directory(dir)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Directory;
+    use crate::fs::{FSNode, SymlinkPolicy};
+
+    #[cfg(unix)]
+    fn tree(dir: &Directory, policy: SymlinkPolicy) -> Vec<(PathBuf, &'static str)> {
+        let mut nodes = dir
+            .try_iter_tree_with(policy)
+            .expect("tree to be read")
+            .map(|node| {
+                let node = node.expect("node to be read");
+                let kind = match node {
+                    FSNode::File(_) => "file",
+                    FSNode::Directory(_) => "dir",
+                    FSNode::Unknown(_) => "unknown",
+                };
+                let path = node.path().strip_prefix(dir.path()).map(PathBuf::from);
+                (path.expect("nodes to be in the tree"), kind)
+            })
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes
+    }
+
+    #[test]
+    fn children_are_made_once_for_the_threads() {
+        let root = std::env::temp_dir().join(format!("platemaker-mkdir-{}", std::process::id()));
+        let parent = Directory::new_with_mkdir(&root).expect("temp dir to be writable");
+
+        let children = std::thread::scope(|scope| {
+            (0..8)
+                .map(|_| scope.spawn(|| parent.get_or_mkdir_child("a/b/c")))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|thread| thread.join().expect("thread not to panic"))
+                .collect::<Vec<_>>()
+        });
+        std::fs::remove_dir_all(&root).ok();
+
+        for child in children {
+            assert_eq!(
+                child.expect("child to be made").path(),
+                parent.path().join("a/b/c")
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_followed_without_looping() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("platemaker-symlinks-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("site/posts")).expect("temp dir to be writable");
+        std::fs::create_dir_all(root.join("shared")).expect("temp dir to be writable");
+        std::fs::write(root.join("shared/a.png"), "").expect("temp dir to be writable");
+        symlink(root.join("shared"), root.join("site/posts/assets")).expect("link to be made");
+        symlink(root.join("site"), root.join("site/posts/loop")).expect("link to be made");
+
+        let dir = Directory::new(root.join("site")).expect("temp dir to exist");
+        let skipped = tree(&dir, SymlinkPolicy::Skip);
+        let followed = tree(&dir, SymlinkPolicy::Follow);
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(
+            skipped,
+            [
+                (PathBuf::from("posts"), "dir"),
+                (PathBuf::from("posts/assets"), "unknown"),
+                (PathBuf::from("posts/loop"), "unknown"),
+            ]
+        );
+        assert_eq!(
+            followed,
+            [
+                (PathBuf::from("posts"), "dir"),
+                (PathBuf::from("posts/assets"), "dir"),
+                (PathBuf::from("posts/assets/a.png"), "file"),
+                (PathBuf::from("posts/loop"), "unknown"),
+            ]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use std::{
+    fs::Metadata,
     io::ErrorKind,
     path::{Path, PathBuf},
+    sync::OnceLock,
+    time::SystemTime,
 };
 
 use super::{Directory, FSNode};
@@ -8,6 +11,8 @@ use super::{Directory, FSNode};
 #[derive(Clone, Debug)]
 pub struct File {
     path: PathBuf,
+    // as of the first stat, which the clones share if it is done before
+    metadata: OnceLock<Metadata>,
 }
 
 impl File {
@@ -26,13 +31,39 @@ impl File {
 
         Ok(File {
             path: path.to_path_buf(),
+            metadata: OnceLock::new(),
         })
     }
 
+    pub fn new_unchecked(path: impl AsRef<Path>) -> Self {
+        File {
+            path: path.as_ref().to_path_buf(),
+            metadata: OnceLock::new(),
+        }
+    }
+
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    pub fn metadata(&self) -> std::io::Result<&Metadata> {
+        if let Some(metadata) = self.metadata.get() {
+            return Ok(metadata);
+        }
+
+        let metadata = std::fs::metadata(&self.path)?;
+        Ok(self.metadata.get_or_init(|| metadata))
+    }
+
+    // in bytes
+    pub fn size(&self) -> std::io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    pub fn modified(&self) -> std::io::Result<SystemTime> {
+        self.metadata()?.modified()
+    }
+
     pub fn containing_dir(&self) -> Option<std::io::Result<Directory>> {
         self.path.parent().map(Directory::new)
     }
@@ -47,3 +78,29 @@ impl From<File> for FSNode {
         FSNode::File(file)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::File;
+    use crate::fs::FSNode;
+
+    #[test]
+    fn metadata_is_kept_after_the_first_stat() {
+        let path = std::env::temp_dir().join(format!("platemaker-metadata-{}", std::process::id()));
+        std::fs::write(&path, "hello").expect("temp dir to be writable");
+
+        let file = File::new(&path).expect("file to exist");
+        let size = file.size();
+        let modified = file.modified();
+        std::fs::write(&path, "hello, world").expect("temp dir to be writable");
+        let cached = file.size();
+        let node = FSNode::from(File::new(&path).expect("file to exist"));
+        let fresh = node.size();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(size.expect("file to be stat"), 5);
+        assert!(modified.is_ok());
+        assert_eq!(cached.expect("metadata to be cached"), 5);
+        assert_eq!(fresh.expect("file to be stat"), 12);
+    }
+}
@@ -1,62 +1,304 @@
+use std::{
+    fmt::Arguments,
+    io::IsTerminal,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use serde::Deserialize;
+
+use crate::util::escape_json;
+
+pub const LOG_FORMAT_ENV: &str = "PLATEMAKER_LOG_FORMAT";
+
+// https://no-color.org/
+const NO_COLOR_ENV: &str = "NO_COLOR";
+
 #[macro_export]
 macro_rules! log {
     (job_start: $format:literal $(, $($value:expr),* $(,)? )?) => {
-        log!(_job => "\n", "3", $format $(, $($value),*)?);
+        log!(_emit => JobStart, $format $(, $($value),*)?);
     };
 
     (job_end: $format:literal $(, $($value:expr),* $(,)? )?) => {
-        log!(_job => "", "2", $format $(, $($value),*)?);
+        log!(_emit => JobEnd, $format $(, $($value),*)?);
     };
 
     (section: $format:literal $(, $($value:expr),* $(,)? )?) => {
-        log!(_status => "5", "*", "", $format $(, $($value),*)?);
+        log!(_emit => Section, $format $(, $($value),*)?);
     };
 
     (step: $format:literal $(, $($value:expr),* $(,)? )?) => {
-        log!(_status => "4", "┃", "\x1b[38;5;14m", $format $(, $($value),*)?);
+        log!(_emit => Step, $format $(, $($value),*)?);
     };
 
     (ok: $format:literal $(, $($value:expr),* $(,)? )?) => {
-        log!(_status => "2", "✓", "\x1b[1m", $format $(, $($value),*)?);
+        log!(_emit => Ok, $format $(, $($value),*)?);
     };
 
     (warn: $format:literal $(, $($value:expr),* $(,)? )?) => {
-        log!(_notice => "3", "warning", $format $(, $($value),*)?);
-    };
-
-    (_job => $prefix: literal, $escape:literal, $format:literal $(, $($value:expr),* $(,)?)?) => {
-        println!(
-            "{}",
-            format!(
-                concat!($prefix, "  \x1b[48;5;", $escape, ";38;5;0;1m ", "  ", $format, "   \x1b[m")
-                $(,
-                    $($value),*
-                )?
-            )
-        );
+        log!(_emit => Warn, $format $(, $($value),*)?);
     };
 
-    (_status => $escape:literal, $tag:literal, $reset:literal, $format:literal $(, $($value:expr),* $(,)?)?) => {
-        println!(
-            "{}",
-            format!(
-                concat!("\x1b[38;5;", $escape, "m  ", $tag, $reset, "  ", $format, "\x1b[m")
-                $(,
-                    $($value),*
-                )?
-            )
-        );
+    (detail: $format:literal $(, $($value:expr),* $(,)? )?) => {
+        log!(_emit => Detail, $format $(, $($value),*)?);
     };
 
-    (_notice => $escape:literal, $step:literal, $format:literal $(, $($value:expr),* $(,)?)?) => {
-        println!(
-            "{}",
-            format!(
-                concat!("\x1b[38;5;", $escape, "m{}", ": ", $format, "\x1b[m"),
-                $step $(,
-                    $($value),*
-                )?
-            )
-        );
+    (_emit => $kind:ident, $format:literal $(, $($value:expr),* $(,)?)?) => {
+        $crate::log::emit(
+            $crate::log::LogKind::$kind,
+            format_args!($format $(, $($value),*)?),
+        )
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogKind {
+    JobStart,
+    JobEnd,
+    Section,
+    Step,
+    Ok,
+    Warn,
+    // Only with `--verbose`, like every file copied
+    Detail,
+}
+
+impl LogKind {
+    fn name(&self) -> &'static str {
+        match self {
+            LogKind::JobStart => "job_start",
+            LogKind::JobEnd => "job_end",
+            LogKind::Section => "section",
+            LogKind::Step => "step",
+            LogKind::Ok => "ok",
+            LogKind::Warn => "warn",
+            LogKind::Detail => "detail",
+        }
+    }
+
+    fn level(&self) -> &'static str {
+        match self {
+            LogKind::Warn => "warn",
+            LogKind::Detail => "debug",
+            _ => "info",
+        }
+    }
+
+    fn shown_at(&self, level: LogLevel) -> bool {
+        match self {
+            LogKind::Warn => true,
+            LogKind::Detail => level == LogLevel::Verbose,
+            _ => level != LogLevel::Quiet,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    // Warnings only
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(1);
+
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+fn level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Quiet,
+        2 => LogLevel::Verbose,
+        _ => LogLevel::Normal,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    // for the humans in the terminal
+    #[default]
+    Pretty,
+    // One object per line, for CI and the other tools
+    Json,
+}
+
+// 0 is for not decided yet, which is read from the environment variable at the first log
+static FORMAT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_format(format: LogFormat) {
+    FORMAT.store(
+        match format {
+            LogFormat::Pretty => 1,
+            LogFormat::Json => 2,
+        },
+        Ordering::Relaxed,
+    );
+}
+
+fn format() -> LogFormat {
+    match FORMAT.load(Ordering::Relaxed) {
+        1 => LogFormat::Pretty,
+        2 => LogFormat::Json,
+        _ => {
+            let format = match std::env::var(LOG_FORMAT_ENV) {
+                Ok(value) if value.eq_ignore_ascii_case("json") => LogFormat::Json,
+                _ => LogFormat::Pretty,
+            };
+            set_format(format);
+            format
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    Always,
+    // When the output is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Never,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("'{0}' is none of always, auto and never")]
+pub struct UnknownColorChoice(String);
+
+impl FromStr for ColorChoice {
+    type Err = UnknownColorChoice;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "always" => Ok(ColorChoice::Always),
+            "auto" => Ok(ColorChoice::Auto),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(UnknownColorChoice(value.to_string())),
+        }
+    }
+}
+
+// 0 is for not decided yet, which is `auto` at the first log
+static COLOR: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_color(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Auto => {
+            std::io::stdout().is_terminal()
+                && std::env::var_os(NO_COLOR_ENV).is_none_or(|value| value.is_empty())
+        }
+        ColorChoice::Never => false,
     };
+
+    COLOR.store(if enabled { 1 } else { 2 }, Ordering::Relaxed);
+}
+
+fn color() -> bool {
+    match COLOR.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => {
+            set_color(ColorChoice::Auto);
+            color()
+        }
+    }
+}
+
+pub fn emit(kind: LogKind, message: Arguments) {
+    if !kind.shown_at(level()) {
+        return;
+    }
+
+    match format() {
+        LogFormat::Pretty => println!("{}", pretty(kind, message, color())),
+        LogFormat::Json => println!("{}", json(kind, &message.to_string())),
+    }
+}
+
+// Between the blocks of the pretty output, which other formats don't need
+pub fn blank_line() {
+    if format() == LogFormat::Pretty && level() != LogLevel::Quiet {
+        println!();
+    }
+}
+
+fn pretty(kind: LogKind, message: Arguments, color: bool) -> String {
+    // SGR parameters, all of which are dropped without the color
+    let sgr = |parameters: &str| {
+        if color {
+            format!("\x1b[{parameters}m")
+        } else {
+            String::new()
+        }
+    };
+    let reset = sgr("");
+
+    match kind {
+        LogKind::JobStart => format!("\n  {}   {message}   {reset}", sgr("48;5;3;38;5;0;1")),
+        LogKind::JobEnd => format!("  {}   {message}   {reset}", sgr("48;5;2;38;5;0;1")),
+        LogKind::Section => format!("{}  *  {message}{reset}", sgr("38;5;5")),
+        LogKind::Step => format!("{}  ┃{}  {message}{reset}", sgr("38;5;4"), sgr("38;5;14")),
+        LogKind::Ok => format!("{}  ✓{}  {message}{reset}", sgr("38;5;2"), sgr("1")),
+        LogKind::Warn => format!("{}warning: {message}{reset}", sgr("38;5;3")),
+        LogKind::Detail => format!("{}  ┃{}    {message}{reset}", sgr("38;5;4"), sgr("38;5;8")),
+    }
+}
+
+fn json(kind: LogKind, message: &str) -> String {
+    format!(
+        r#"{{"level":"{}","kind":"{}","message":"{}"}}"#,
+        kind.level(),
+        kind.name(),
+        escape_json(message)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LogKind, LogLevel, json, pretty};
+
+    #[test]
+    fn json_lines_are_escaped() {
+        assert_eq!(
+            json(LogKind::Warn, "Broken \"link\" in\n./index.html"),
+            r#"{"level":"warn","kind":"warn","message":"Broken \"link\" in\n./index.html"}"#
+        );
+        assert_eq!(
+            json(LogKind::JobStart, "Building\u{1b}"),
+            r#"{"level":"info","kind":"job_start","message":"Building\u001b"}"#
+        );
+    }
+
+    #[test]
+    fn kinds_are_filtered_by_level() {
+        let shown = |level| {
+            [
+                LogKind::Section,
+                LogKind::Step,
+                LogKind::Warn,
+                LogKind::Detail,
+            ]
+            .map(|kind| kind.shown_at(level))
+        };
+
+        assert_eq!(shown(LogLevel::Quiet), [false, false, true, false]);
+        assert_eq!(shown(LogLevel::Normal), [true, true, true, false]);
+        assert_eq!(shown(LogLevel::Verbose), [true, true, true, true]);
+    }
+
+    #[test]
+    fn colors_are_dropped_on_request() {
+        assert_eq!(
+            pretty(LogKind::Ok, format_args!("Done"), true),
+            "\x1b[38;5;2m  ✓\x1b[1m  Done\x1b[m"
+        );
+        assert_eq!(
+            pretty(LogKind::Ok, format_args!("Done"), false),
+            "  ✓  Done"
+        );
+    }
 }
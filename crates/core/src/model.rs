@@ -1,21 +1,176 @@
-use std::any::type_name;
+use std::{any::type_name, path::PathBuf};
 
 use loss72_platemaker_widgets::Widgets;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::util::get_slice_by_char;
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Default, Deserialize, Debug)]
 pub struct GenerationContext {
     #[serde(default)]
     pub release: bool,
+    #[serde(default)]
+    pub drafts: bool,
+    // Builds the articles dated in the future even in release builds
+    #[serde(default)]
+    pub future: bool,
+    #[serde(default)]
+    pub syntax_theme: SyntaxTheme,
+    #[serde(default)]
+    pub heading_links: bool,
+    // Renders math to MathML instead of leaving the TeX to client-side renderers
+    #[serde(default)]
+    pub mathml: bool,
+    #[serde(default)]
+    pub words_per_minute: Option<u32>,
+    #[serde(default)]
+    pub cjk_characters_per_minute: Option<u32>,
+    // Defaults to minifying release builds only
+    #[serde(default)]
+    pub minify: Option<bool>,
+    // Same as minify, asset file names get content hashes in release builds by default
+    #[serde(default)]
+    pub fingerprint: Option<bool>,
+    // What `:rocket:` style shortcodes in the text become
+    #[serde(default)]
+    pub emoji: EmojiStyle,
+    // Curly quotes, dashes for `--` and `---`, and ellipses for `...` outside of the code.
+    // Defaults to on, which can be turned off for the technical posts.
+    #[serde(default)]
+    pub smart_punctuation: Option<bool>,
+    // `loading="lazy"` and `decoding="async"` on the images, which are on by default
+    #[serde(default)]
+    pub lazy_images: Option<bool>,
+    // Loads the first image of each article right away, which is likely above the fold
+    #[serde(default)]
+    pub eager_first_image: bool,
+    // `target="_blank"` on the links to the other hosts, which get `rel="noopener noreferrer"`
+    #[serde(default)]
+    pub external_links_in_new_tab: bool,
+    // `[generation.markdown]`, the extensions of the syntax to recognize
+    #[serde(default)]
+    pub markdown: MarkdownOptions,
+    // of `base_url`, given with it rather than in this section
+    #[serde(skip)]
+    pub site_host: Option<String>,
+    // of all the source files, computed for each build as `${build_id}`
+    #[serde(skip)]
+    pub build_id: Option<String>,
+}
+
+// All of them but the definition lists are on by default. The ones turned off are left as
+// they are written, like `~~text~~` with the tildes.
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct MarkdownOptions {
+    pub tables: bool,
+    pub strikethrough: bool,
+    pub task_lists: bool,
+    // `<https://example.com>` and `<mail@example.com>`, which are only the text without it
+    pub autolinks: bool,
+    pub footnotes: bool,
+    // `> [!NOTE]` blockquotes as the boxes, which are `<blockquote>` with a class without it
+    pub callouts: bool,
+    // `Term` and `: definition` on the next lines as `<dl>`. Off by default, not to change
+    // the existing articles with the lines starting with `:`.
+    pub definition_lists: bool,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            strikethrough: true,
+            task_lists: true,
+            autolinks: true,
+            footnotes: true,
+            callouts: true,
+            definition_lists: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmojiStyle {
+    // `<img>` of Twemoji, which looks the same on every platform
+    #[default]
+    Twemoji,
+    Unicode,
+    // Shortcodes are left as they are
+    Disabled,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyntaxTheme {
+    #[default]
+    SolarizedLight,
+    SolarizedDark,
+    InspiredGithub,
+    Base16OceanLight,
+    Base16OceanDark,
+    Base16EightiesDark,
+    Base16MochaDark,
+    Disabled,
+}
+
+impl GenerationContext {
+    pub const DEFAULT_WORDS_PER_MINUTE: u32 = 200;
+    pub const DEFAULT_CJK_CHARACTERS_PER_MINUTE: u32 = 500;
+
+    pub fn words_per_minute(&self) -> u32 {
+        self.words_per_minute
+            .unwrap_or(Self::DEFAULT_WORDS_PER_MINUTE)
+            .max(1)
+    }
+
+    pub fn cjk_characters_per_minute(&self) -> u32 {
+        self.cjk_characters_per_minute
+            .unwrap_or(Self::DEFAULT_CJK_CHARACTERS_PER_MINUTE)
+            .max(1)
+    }
+
+    pub fn minifies(&self) -> bool {
+        self.minify.unwrap_or(self.release)
+    }
+
+    pub fn smart_punctuation(&self) -> bool {
+        self.smart_punctuation.unwrap_or(true)
+    }
+
+    pub fn lazy_images(&self) -> bool {
+        self.lazy_images.unwrap_or(true)
+    }
+
+    pub fn fingerprints(&self) -> bool {
+        self.fingerprint.unwrap_or(self.release)
+    }
+
+    pub fn publishes(&self, metadata: &ArticleMetadata) -> bool {
+        !metadata.draft || !self.release || self.drafts
+    }
+
+    // Only the days are compared, so the articles are published for the whole day of
+    // `today`. The ones without the day are published from the first day of the month.
+    pub fn publishes_on(&self, id: &ArticleIdentifier, today: (u32, u8, u8)) -> bool {
+        let (year, month, day) = id.date;
+        (year, month, day.unwrap_or(1)) <= today || !self.release || self.future
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ArticleIdentifier {
     pub group: String,
     pub slug: String,
-    pub date: (u32, u8, u8),
+    // The day is missing in the month-scoped articles, which come before the first day
+    pub date: (u32, u8, Option<u8>),
+}
+
+impl PartialOrd for ArticleIdentifier {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Ord for ArticleIdentifier {
@@ -24,11 +179,65 @@ impl Ord for ArticleIdentifier {
     }
 }
 
-#[derive(Clone)]
+// Order of the articles on the index, newest first by default. The ties of the titles and the
+// slugs are newest first too.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArticleOrder {
+    #[default]
+    DateDesc,
+    DateAsc,
+    // Ignoring the case
+    Title,
+    Slug,
+}
+
+impl ArticleOrder {
+    pub fn compare(&self, left: &Article, right: &Article) -> std::cmp::Ordering {
+        let newest_first = || left.id.cmp(&right.id).reverse();
+
+        match self {
+            ArticleOrder::DateDesc => newest_first(),
+            ArticleOrder::DateAsc => left.id.cmp(&right.id),
+            ArticleOrder::Title => left
+                .metadata
+                .title
+                .to_lowercase()
+                .cmp(&right.metadata.title.to_lowercase())
+                .then_with(newest_first),
+            ArticleOrder::Slug => left.id.slug.cmp(&right.id.slug).then_with(newest_first),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Article {
     pub id: ArticleIdentifier,
     pub metadata: ArticleMetadata,
     pub content: String,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    #[serde(default)]
+    pub toc: Vec<TocEntry>,
+    #[serde(default)]
+    pub images: Vec<ArticleImage>,
+}
+
+// Image in the article which is found in the local file system
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct ArticleImage {
+    // as it is written in the article, relative to the article page
+    pub src: String,
+    pub source: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub title: String,
+    pub id: String,
 }
 
 impl std::fmt::Debug for Article {
@@ -46,15 +255,84 @@ impl std::fmt::Debug for Article {
                     get_slice_by_char(&self.content, 0..30).escape_debug()
                 ),
             )
+            .field("excerpt", &self.excerpt.as_ref().map(|_| "..."))
+            .field("toc", &self.toc)
+            .field("images", &self.images)
             .finish()
     }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
 pub struct ArticleMetadata {
     pub title: String,
     pub brief: String,
     #[serde(default)]
     pub widgets: Widgets,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub lang: Option<String>,
+    // Image for the social cards, relative to the article like the images in it
+    #[serde(default)]
+    pub cover: Option<String>,
+    // `_article-{template}.html` used instead of `_article.html`
+    #[serde(default)]
+    pub template: Option<String>,
+    // Old urls of the article from the site root, which redirect to it
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    // Listed above the others on the index, by `pin_order` and then the ones without it.
+    // `pin_order` does nothing without `pinned`.
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub pin_order: Option<u32>,
+    // Name of the series the article is a part of, ordered by `series_order` and then the
+    // dates for the parts without it
+    #[serde(default)]
+    pub series: Option<String>,
+    #[serde(default)]
+    pub series_order: Option<u32>,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{ArticleIdentifier, ArticleMetadata, GenerationContext};
+
+    #[test]
+    fn drafts_are_left_out_of_release_builds() {
+        let draft = ArticleMetadata {
+            draft: true,
+            ..Default::default()
+        };
+        let context = |release, drafts| GenerationContext {
+            release,
+            drafts,
+            ..Default::default()
+        };
+
+        assert!(!context(true, false).publishes(&draft));
+        assert!(context(true, true).publishes(&draft));
+        assert!(context(false, false).publishes(&draft));
+        assert!(context(true, false).publishes(&ArticleMetadata::default()));
+    }
+
+    #[test]
+    fn future_articles_are_left_out_of_release_builds() {
+        let id = ArticleIdentifier {
+            group: "202501".to_string(),
+            slug: "hello".to_string(),
+            date: (2025, 1, None),
+        };
+        let release = GenerationContext {
+            release: true,
+            ..Default::default()
+        };
+
+        assert!(release.publishes_on(&id, (2025, 1, 1)));
+        assert!(!release.publishes_on(&id, (2024, 12, 31)));
+        assert!(GenerationContext::default().publishes_on(&id, (2024, 12, 31)));
+    }
+}
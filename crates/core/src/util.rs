@@ -1,5 +1,8 @@
 use std::ops::Range;
 
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+
 pub fn get_slice_by_char(str: &str, range: Range<usize>) -> &str {
     let mut char_indicies = str.char_indices();
 
@@ -14,9 +17,290 @@ pub fn get_slice_by_char(str: &str, range: Range<usize>) -> &str {
     &str[start_pos..end_pos]
 }
 
+// `&`, `<` and `>` of the text in the html
+pub fn escape_html(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// of the attribute values in double quotes
+pub fn escape_attribute(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// back to the value of the attribute which `escape_attribute` made
+pub fn unescape_attribute(str: &str) -> String {
+    str.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// of the paths in the urls, which are none with the broken escapes or the bytes out of UTF-8,
+// as they are not served
+pub fn percent_decode(str: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut chars = str.bytes();
+
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+// of the text and the attributes in XML, which are the feeds and the sitemaps too
+pub fn escape_xml(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// of the string in the double quotes, with the control characters as `\u`
+pub fn escape_json(str: &str) -> String {
+    let mut escaped = String::with_capacity(str.len());
+
+    for char in str.chars() {
+        match char {
+            '"' => escaped.push_str(r#"\""#),
+            '\\' => escaped.push_str(r"\\"),
+            '\n' => escaped.push_str(r"\n"),
+            '\r' => escaped.push_str(r"\r"),
+            '\t' => escaped.push_str(r"\t"),
+            char if char.is_control() => escaped.push_str(&format!("\\u{:04x}", char as u32)),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Hash of the files by their paths, which is the same for the same files in any order
+pub fn build_id<'f>(files: impl IntoIterator<Item = (&'f str, &'f str)>) -> String {
+    let mut files = files.into_iter().collect::<Vec<_>>();
+    files.sort();
+
+    let mut id = content_hash(
+        files
+            .iter()
+            .map(|(path, hash)| format!("{path}\0{hash}\n"))
+            .collect::<String>(),
+    );
+    id.truncate(BUILD_ID_LENGTH);
+    id
+}
+
+const BUILD_ID_LENGTH: usize = 16;
+
+// in the proleptic Gregorian calendar, of which the day may be left out
+pub fn date_exists(year: u32, month: u8, day: Option<u8>) -> bool {
+    i32::try_from(year).is_ok_and(|year| {
+        NaiveDate::from_ymd_opt(year, month.into(), day.unwrap_or(1).into()).is_some()
+    })
+}
+
+// Readable in the urls without encoding it: the letters lose the diacritics, the spaces and
+// the symbols become `-`, and the other characters are their code points in hex like `65e5`.
+// The slugs only of `[A-Za-z0-9_-]` are kept as they are.
+pub fn slugify(str: &str) -> String {
+    let mut slug = String::with_capacity(str.len());
+    let mut separated = false;
+
+    let push = |slug: &mut String, part: &str, separated: &mut bool| {
+        if std::mem::take(separated) && !slug.is_empty() && !slug.ends_with(['-', '_']) {
+            slug.push('-');
+        }
+        slug.push_str(part);
+    };
+
+    for char in str.chars() {
+        // Fullwidth forms, as in the Japanese names
+        let char = match char {
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(char as u32 - 0xfee0).unwrap_or(char),
+            char => char,
+        };
+
+        if char.is_ascii_alphanumeric() || char == '_' || char == '-' {
+            push(&mut slug, char.encode_utf8(&mut [0; 4]), &mut separated);
+        } else if let Some(latin) = transliterate(char) {
+            push(&mut slug, latin, &mut separated);
+        } else if char.is_alphanumeric() {
+            separated = true;
+            push(&mut slug, &format!("{:x}", char as u32), &mut separated);
+            separated = true;
+        } else {
+            separated = true;
+        }
+    }
+
+    slug
+}
+
+fn transliterate(char: char) -> Option<&'static str> {
+    Some(match char {
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Ĉ' | 'Č' => "C",
+        'ç' | 'ć' | 'ĉ' | 'č' => "c",
+        'Ð' | 'Ď' | 'Đ' => "D",
+        'ð' | 'ď' | 'đ' => "d",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'Ĝ' | 'Ğ' | 'Ģ' => "G",
+        'ĝ' | 'ğ' | 'ģ' => "g",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'Į' | 'İ' => "I",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'Ķ' => "K",
+        'ķ' => "k",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ł' => "L",
+        'ĺ' | 'ļ' | 'ľ' | 'ł' => "l",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ŕ' | 'Ř' => "R",
+        'ŕ' | 'ř' => "r",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'Ţ' | 'Ť' => "T",
+        'ţ' | 'ť' => "t",
+        'Þ' => "Th",
+        'þ' => "th",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'Ý' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+// Lowercased host of an `http(s)` url without the user and the port, e.g. `example.com`
+pub fn url_host(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !(scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")) {
+        return None;
+    }
+
+    let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // IPv6 addresses are bracketed, as they contain `:` too
+    let host = match host.find(']') {
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or(host),
+    };
+
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::get_slice_by_char;
+    use super::{
+        build_id, date_exists, escape_attribute, escape_json, escape_xml, get_slice_by_char,
+        percent_decode, slugify, unescape_attribute, url_host,
+    };
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(
+            escape_xml(r#"Tom & Jerry <"1">'s"#),
+            "Tom &amp; Jerry &lt;&quot;1&quot;&gt;&apos;s"
+        );
+
+        let attribute = escape_attribute(r#"a&b "c" <d>'s"#);
+        assert_eq!(attribute, "a&amp;b &quot;c&quot; &lt;d&gt;'s");
+        assert_eq!(unescape_attribute(&attribute), r#"a&b "c" <d>'s"#);
+        assert_eq!(unescape_attribute("&amp;lt;"), "&lt;");
+
+        assert_eq!(
+            escape_json("say \"hi\"\n\\\u{1}"),
+            r#"say \"hi\"\n\\\u0001"#
+        );
+    }
+
+    #[test]
+    fn percent_encoded_paths_are_decoded() {
+        assert_eq!(
+            percent_decode("/tags/%E6%97%A5%E8%A8%98/a%20b.html").as_deref(),
+            Some("/tags/日記/a b.html")
+        );
+        assert_eq!(percent_decode("/plain").as_deref(), Some("/plain"));
+        assert_eq!(percent_decode("/broken%2"), None);
+        assert_eq!(percent_decode("/not-hex%zz"), None);
+        assert_eq!(percent_decode("/not-utf8%ff"), None);
+    }
+
+    #[test]
+    fn dates_follow_the_calendar() {
+        assert!(date_exists(2024, 2, Some(29)));
+        assert!(!date_exists(2025, 2, Some(29)));
+        assert!(!date_exists(1900, 2, Some(29)));
+        assert!(date_exists(2000, 2, Some(29)));
+        assert!(!date_exists(2025, 4, Some(31)));
+        assert!(!date_exists(2025, 1, Some(0)));
+        assert!(date_exists(2025, 12, None));
+        assert!(!date_exists(2025, 13, None));
+    }
+
+    #[test]
+    fn build_ids_ignore_the_order() {
+        let id = build_id([("md/a.md", "1"), ("tpl/_index.html", "2")]);
+
+        assert_eq!(id.len(), 16);
+        assert_eq!(build_id([("tpl/_index.html", "2"), ("md/a.md", "1")]), id);
+        assert_ne!(build_id([("md/a.md", "1"), ("tpl/_index.html", "3")]), id);
+        assert_ne!(build_id([("md/b.md", "1"), ("tpl/_index.html", "2")]), id);
+    }
+
+    #[test]
+    fn slugs_are_safe_in_the_urls() {
+        assert_eq!(slugify("03_hello-World"), "03_hello-World");
+        assert_eq!(slugify("03_Café crème!"), "03_Cafe-creme");
+        assert_eq!(slugify("03_日記 ２０２５"), "03_65e5-8a18-2025");
+        assert_eq!(slugify("a & b"), "a-b");
+        assert_eq!(slugify("?"), "");
+    }
+
+    #[test]
+    fn hosts_are_taken_from_http_urls() {
+        assert_eq!(
+            url_host("https://Example.com/articles/").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            url_host("http://me@example.com:8080?q").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(url_host("http://[::1]:8000/").as_deref(), Some("[::1]"));
+        assert_eq!(url_host("mailto:me@example.com"), None);
+        assert_eq!(url_host("/articles/"), None);
+    }
 
     #[test]
     fn slices_correct_amount() {
@@ -1,7 +1,354 @@
-use loss72_platemaker_core::model::ArticleMetadata;
+use std::{collections::BTreeMap, fmt::Display, path::Path};
 
-use super::parse::{ParseError, ParseResult};
+use loss72_platemaker_core::{log, model::ArticleMetadata};
+use toml::{Spanned, Value};
 
-pub fn parse_toml_to_metadata(toml: &str) -> ParseResult<ArticleMetadata> {
-    toml::from_str::<ArticleMetadata>(toml).map_err(|e| ParseError::InvalidToml(format!("{e}")))
+const KNOWN_KEYS: [(&str, Expected); 13] = [
+    ("title", Expected::String),
+    ("brief", Expected::String),
+    ("widgets", Expected::Table),
+    ("draft", Expected::Boolean),
+    ("tags", Expected::StringArray),
+    ("lang", Expected::String),
+    ("cover", Expected::String),
+    ("template", Expected::String),
+    ("aliases", Expected::StringArray),
+    ("pinned", Expected::Boolean),
+    ("pin_order", Expected::Integer),
+    ("series", Expected::String),
+    ("series_order", Expected::Integer),
+];
+
+const REQUIRED_KEYS: [&str; 2] = ["title", "brief"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrontmatterFormat {
+    // wrapped with `+++`
+    Toml,
+    // wrapped with `---`
+    Yaml,
+    // an object starting at the first line and closed with `}` at the beginning of a line
+    Json,
+}
+
+impl FrontmatterFormat {
+    // of the markdown file where the frontmatter body starts
+    fn first_line(&self) -> usize {
+        match self {
+            FrontmatterFormat::Toml | FrontmatterFormat::Yaml => 2,
+            FrontmatterFormat::Json => 1,
+        }
+    }
+}
+
+impl Display for FrontmatterFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FrontmatterFormat::Toml => "TOML",
+            FrontmatterFormat::Yaml => "YAML",
+            FrontmatterFormat::Json => "JSON",
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct FrontmatterError {
+    pub format: FrontmatterFormat,
+    // in the markdown file, if the error can be pointed out
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Clone, Copy)]
+enum Expected {
+    String,
+    Boolean,
+    Integer,
+    StringArray,
+    Table,
+}
+
+impl Expected {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Expected::String => value.is_str(),
+            Expected::Boolean => value.is_bool(),
+            Expected::Integer => value.is_integer(),
+            Expected::StringArray => value
+                .as_array()
+                .is_some_and(|array| array.iter().all(Value::is_str)),
+            Expected::Table => value.is_table(),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Expected::String => "a string",
+            Expected::Boolean => "a boolean",
+            Expected::Integer => "an integer",
+            Expected::StringArray => "an array of strings",
+            Expected::Table => "a table",
+        }
+    }
+}
+
+struct Entry {
+    key: String,
+    value: Value,
+    // 0-based in the frontmatter body
+    line: Option<usize>,
+}
+
+pub fn parse_frontmatter_to_metadata(
+    body: &str,
+    format: FrontmatterFormat,
+    path: &Path,
+) -> Result<ArticleMetadata, FrontmatterError> {
+    let error_at = |line: Option<usize>, message: String| FrontmatterError {
+        format,
+        line: line.map(|line| format.first_line() + line),
+        message,
+    };
+
+    let mut entries =
+        parse_entries(body, format).map_err(|(line, message)| error_at(line, message))?;
+    entries.sort_by_key(|entry| entry.line);
+
+    for entry in &entries {
+        match KNOWN_KEYS.iter().find(|(name, _)| *name == entry.key) {
+            Some((name, expected)) if !expected.matches(&entry.value) => {
+                return Err(error_at(
+                    entry.line,
+                    format!(
+                        "The field `{name}` should be {} but is {}.",
+                        expected.description(),
+                        entry.value.type_str()
+                    ),
+                ));
+            }
+            Some(_) => {}
+            None => log!(
+                warn: "Unknown frontmatter key `{}` at ./{}{}",
+                entry.key,
+                path.display(),
+                entry.line.map(|line| format!(":{}", format.first_line() + line)).unwrap_or_default()
+            ),
+        }
+    }
+
+    if let Some(missing) = REQUIRED_KEYS
+        .iter()
+        .find(|required| !entries.iter().any(|entry| entry.key == **required))
+    {
+        return Err(error_at(
+            None,
+            format!("The required field `{missing}` is missing."),
+        ));
+    }
+
+    // Checked above up to the top level, nested ones like widgets are left to serde
+    deserialize(body, format).map_err(|(line, message)| error_at(line, message))
+}
+
+fn parse_entries(
+    body: &str,
+    format: FrontmatterFormat,
+) -> Result<Vec<Entry>, (Option<usize>, String)> {
+    match format {
+        FrontmatterFormat::Toml => {
+            let table = deserialize::<BTreeMap<Spanned<String>, Spanned<Value>>>(body, format)?;
+
+            Ok(table
+                .into_iter()
+                .map(|(key, value)| Entry {
+                    line: Some(line_of(body, key.span().start)),
+                    key: key.into_inner(),
+                    value: value.into_inner(),
+                })
+                .collect())
+        }
+        // Neither keep the positions, so the key is looked up in the body
+        FrontmatterFormat::Yaml | FrontmatterFormat::Json => {
+            let table = deserialize::<BTreeMap<String, Value>>(body, format)?;
+
+            Ok(table
+                .into_iter()
+                .map(|(key, value)| Entry {
+                    line: key_line(body, &key, format),
+                    key,
+                    value,
+                })
+                .collect())
+        }
+    }
+}
+
+fn deserialize<T: serde::de::DeserializeOwned>(
+    body: &str,
+    format: FrontmatterFormat,
+) -> Result<T, (Option<usize>, String)> {
+    match format {
+        FrontmatterFormat::Toml => toml::from_str(body).map_err(|e| {
+            (
+                e.span().map(|span| line_of(body, span.start)),
+                e.message().to_string(),
+            )
+        }),
+        FrontmatterFormat::Yaml => serde_yaml::from_str(body).map_err(|e| {
+            (
+                e.location().map(|location| location.line() - 1),
+                e.to_string(),
+            )
+        }),
+        FrontmatterFormat::Json => serde_json::from_str(body)
+            .map_err(|e| (Some(e.line().saturating_sub(1)), e.to_string())),
+    }
+}
+
+fn line_of(body: &str, offset: usize) -> usize {
+    body[..offset.min(body.len())].matches('\n').count()
+}
+
+fn key_line(body: &str, key: &str, format: FrontmatterFormat) -> Option<usize> {
+    body.lines().position(|line| match format {
+        FrontmatterFormat::Json => line.trim_start().starts_with(&format!("\"{key}\"")),
+        _ => line
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with(':')),
+    })
+}
+
+// JSON frontmatter is not known to the markdown parser, so it is cut out beforehand.
+// It is replaced with blank lines to keep the line numbers of the rest.
+pub fn split_json_frontmatter(content: &str) -> Option<(&str, String)> {
+    if !content.starts_with('{') {
+        return None;
+    }
+
+    let mut length = 0;
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        length += line.len();
+
+        if line.trim_end() == "}" {
+            let markdown = "\n".repeat(index + 1) + &content[length..];
+            return Some((&content[..length], markdown));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{FrontmatterFormat, parse_frontmatter_to_metadata, split_json_frontmatter};
+
+    fn error(body: &str, format: FrontmatterFormat) -> (Option<usize>, String) {
+        let error = parse_frontmatter_to_metadata(body, format, Path::new("article.md"))
+            .expect_err("the frontmatter to be invalid");
+
+        (error.line, error.message)
+    }
+
+    #[test]
+    fn points_out_invalid_fields() {
+        assert_eq!(
+            error("title = \"Title\"\n", FrontmatterFormat::Toml),
+            (None, "The required field `brief` is missing.".to_string())
+        );
+        assert_eq!(
+            error(
+                "title = \"Title\"\nbrief = \"\"\n\ndraft = \"yes\"\n",
+                FrontmatterFormat::Toml
+            ),
+            (
+                Some(5),
+                "The field `draft` should be a boolean but is string.".to_string()
+            )
+        );
+        assert_eq!(
+            error(
+                "title = \"Title\"\nbrief = \"\"\ntags = [\"a\", 1]\n",
+                FrontmatterFormat::Toml
+            )
+            .0,
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_allowed() {
+        let metadata = parse_frontmatter_to_metadata(
+            "title = \"Title\"\nbrief = \"Brief\"\ntag = [\"typo\"]\n",
+            FrontmatterFormat::Toml,
+            Path::new("article.md"),
+        )
+        .expect("unknown keys only to be warned");
+
+        assert_eq!(metadata.title, "Title");
+        assert!(metadata.tags.is_empty());
+    }
+
+    #[test]
+    fn pinned_articles_have_the_order() {
+        let metadata = parse_frontmatter_to_metadata(
+            "title: Title\nbrief: Brief\npinned: true\npin_order: 2\n",
+            FrontmatterFormat::Yaml,
+            Path::new("article.md"),
+        )
+        .expect("the frontmatter to be valid");
+
+        assert!(metadata.pinned);
+        assert_eq!(metadata.pin_order, Some(2));
+        assert_eq!(
+            error(
+                "title = \"Title\"\nbrief = \"\"\npinned = true\npin_order = \"first\"\n",
+                FrontmatterFormat::Toml
+            ),
+            (
+                Some(5),
+                "The field `pin_order` should be an integer but is string.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn points_out_invalid_fields_in_other_formats() {
+        assert_eq!(
+            error(
+                "title: Title\nbrief: Brief\ndraft: 1\n",
+                FrontmatterFormat::Yaml
+            ),
+            (
+                Some(4),
+                "The field `draft` should be a boolean but is integer.".to_string()
+            )
+        );
+        assert_eq!(
+            error(
+                "{\n  \"title\": \"Title\",\n  \"brief\": [\"Brief\"]\n}\n",
+                FrontmatterFormat::Json
+            ),
+            (
+                Some(3),
+                "The field `brief` should be a string but is array.".to_string()
+            )
+        );
+        assert_eq!(
+            error("{\n  \"title\": \"Title\",,\n}\n", FrontmatterFormat::Json).0,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn json_frontmatter_is_replaced_with_blank_lines() {
+        let (json, markdown) = split_json_frontmatter("{\n  \"title\": \"{}\"\n}\n# Heading\n")
+            .expect("JSON to be found");
+
+        assert_eq!(json, "{\n  \"title\": \"{}\"\n}\n");
+        assert_eq!(markdown, "\n\n\n# Heading\n");
+        assert_eq!(split_json_frontmatter("+++\ntitle = \"\"\n+++\n"), None);
+    }
 }
@@ -1,28 +1,64 @@
 #![deny(clippy::unwrap_used)]
 
-use loss72_platemaker_core::{fs::File, log, model::Article};
+use std::path::PathBuf;
+
+use frontmatter::FrontmatterError;
+pub use frontmatter::FrontmatterFormat;
+use loss72_platemaker_core::{
+    fs::File,
+    log,
+    model::{Article, GenerationContext},
+};
 use loss72_platemaker_structure::ArticleFile;
 use parse::{ParseError, make_article_from_markdown};
 
 mod frontmatter;
 mod parse;
 
+pub use parse::syntax_theme_css;
+
 #[derive(Debug, thiserror::Error)]
 pub enum MarkdownProcessError {
     #[error("Error during I/O: {0}")]
     IOError(#[from] std::io::Error),
 
-    #[error("Error during parsing Markdown:\n{0}")]
-    ParseError(ParseError),
+    #[error("Error during parsing Markdown ./{path}:\n{error}", path = .0.display(), error = .1)]
+    ParseError(PathBuf, ParseError),
+
+    #[error(
+        "Invalid {format} frontmatter in ./{path}{line}:\n{message}",
+        path = .path.display(),
+        line = .line.map(|line| format!(":{line}")).unwrap_or_default()
+    )]
+    Frontmatter {
+        path: PathBuf,
+        format: FrontmatterFormat,
+        line: Option<usize>,
+        message: String,
+    },
 }
 
 pub fn is_markdown_path(file: &File) -> bool {
     file.path().extension().is_some_and(|ext| ext == "md")
 }
 
-pub fn parse_markdown(file: &ArticleFile) -> Result<Article, MarkdownProcessError> {
+pub fn parse_markdown(
+    file: &ArticleFile,
+    ctx: &GenerationContext,
+) -> Result<Article, MarkdownProcessError> {
     log!(step: "Parsing ./{}", file.relative_path.display());
 
-    make_article_from_markdown(file, &file.file().read_to_string()?)
-        .map_err(MarkdownProcessError::ParseError)
+    make_article_from_markdown(file, &file.file().read_to_string()?, ctx).map_err(|e| match e {
+        ParseError::Frontmatter(FrontmatterError {
+            format,
+            line,
+            message,
+        }) => MarkdownProcessError::Frontmatter {
+            path: file.relative_path.clone(),
+            format,
+            line,
+            message,
+        },
+        e => MarkdownProcessError::ParseError(file.relative_path.clone(), e),
+    })
 }
@@ -1,18 +1,30 @@
+mod block;
+mod callout;
 mod control;
 mod full_service;
+mod heading;
+mod math;
+mod shortcode;
 mod sub_parser;
+mod task_list;
 
-use super::frontmatter::parse_toml_to_metadata;
+use std::path::Path;
+
+use super::frontmatter::{
+    FrontmatterError, FrontmatterFormat, parse_frontmatter_to_metadata, split_json_frontmatter,
+};
 use full_service::MarkdownParser;
-use loss72_platemaker_core::model::Article;
+use loss72_platemaker_core::model::{Article, ArticleImage, GenerationContext, TocEntry};
 use loss72_platemaker_structure::ArticleFile;
+use pulldown_cmark::Options;
+use shortcode::ShortcodeError;
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
     #[error(
-        "The file is at the invalid location. Expected markdown files to be placed at `./$year/$month/$day[-$num]_$slug.md`."
+        "The file is at the invalid location. Expected markdown files to be placed at `./$year/$month/[$day[-$num]_]$slug.md`."
     )]
     InvalidStructure,
 
@@ -20,39 +32,246 @@ pub enum ParseError {
     InvalidPath,
 
     #[error(
-        "No TOML frontmatter was found. Write TOML frontmatter wrapped with `+++` at the top of the markdown content."
+        "No frontmatter was found. Write TOML wrapped with `+++`, YAML wrapped with `---` or a JSON object at the top of the markdown content."
     )]
     NoFrontmatter,
 
-    #[error("The frontmatter could not be parsed or not valid metadata:\n{0}")]
-    InvalidToml(String),
+    #[error("The frontmatter is not valid metadata:\n{0}")]
+    Frontmatter(#[from] FrontmatterError),
+
+    #[error("The footnote(s) {} are referenced but never defined.", .0.iter().map(|id| format!("`[^{id}]`")).collect::<Vec<_>>().join(", "))]
+    UndefinedFootnote(Vec<String>),
+
+    #[error(
+        "The math at line {0} is not terminated. Escape it as `\\$` if the dollar sign is meant literally."
+    )]
+    UnterminatedMath(usize),
+
+    #[error("The shortcode `{0}` used in the article '{1}' is not known.")]
+    UnknownShortcode(String, String),
+
+    #[error("The shortcode `{0}` used in the article '{1}' is invalid: {2}")]
+    InvalidShortcode(String, String, String),
 }
 
-pub fn make_article_from_markdown(file: &ArticleFile, content: &str) -> ParseResult<Article> {
-    let content = parse_markdown(content)?;
-    let metadata = parse_toml_to_metadata(&content.frontmatter)?;
+pub use sub_parser::syntax_theme_css;
+
+const MORE_MARKER: &str = "<!-- more -->";
+
+pub fn make_article_from_markdown(
+    file: &ArticleFile,
+    content: &str,
+    ctx: &GenerationContext,
+) -> ParseResult<Article> {
+    let content = parse_markdown(content, ctx, file.file().path().parent())?;
+
+    if let Some(error) = content.shortcode_errors.into_iter().next() {
+        let article = file.id.slug.clone();
+        return Err(match error {
+            ShortcodeError::Unknown(name) => ParseError::UnknownShortcode(name, article),
+            ShortcodeError::Invalid(name, reason) => {
+                ParseError::InvalidShortcode(name, article, reason)
+            }
+        });
+    }
+
+    let (format, frontmatter) = &content.frontmatter;
+    let metadata = parse_frontmatter_to_metadata(frontmatter, *format, &file.relative_path)?;
 
     Ok(Article {
         id: file.id.clone(),
         metadata,
         content: content.html,
+        excerpt: content.excerpt,
+        toc: content.toc,
+        images: content.images,
     })
 }
 
 #[derive(Clone, Debug)]
 struct ParsedContent {
-    frontmatter: String,
+    frontmatter: (FrontmatterFormat, String),
     html: String,
+    excerpt: Option<String>,
+    toc: Vec<TocEntry>,
+    images: Vec<ArticleImage>,
+    shortcode_errors: Vec<ShortcodeError>,
 }
 
-fn parse_markdown(content: &str) -> ParseResult<ParsedContent> {
-    let parsed = MarkdownParser::parse(content, pulldown_cmark::Options::all());
+fn parse_markdown(
+    content: &str,
+    ctx: &GenerationContext,
+    article_dir: Option<&Path>,
+) -> ParseResult<ParsedContent> {
+    let json_frontmatter = split_json_frontmatter(content);
+    let markdown = json_frontmatter
+        .as_ref()
+        .map_or(content, |(_, markdown)| markdown.as_str());
+
+    let parsed = MarkdownParser::parse(markdown, parser_options(ctx), ctx, article_dir);
+
+    if let Some(line) = parsed.unterminated_math {
+        return Err(ParseError::UnterminatedMath(line));
+    }
+
+    if !parsed.undefined_footnotes.is_empty() {
+        return Err(ParseError::UndefinedFootnote(parsed.undefined_footnotes));
+    }
+
+    // The marker is passed through as a raw HTML comment, so the lead is everything before
+    // it in the output. Escaped ones, e.g. in code blocks, don't match.
+    let excerpt = parsed
+        .html()
+        .find(MORE_MARKER)
+        .map(|index| parsed.html()[..index].trim_end().to_string());
 
     Ok(ParsedContent {
         html: parsed.html().to_string(),
-        frontmatter: parsed
-            .frontmatter()
-            .ok_or(ParseError::NoFrontmatter)?
-            .to_string(),
+        excerpt,
+        frontmatter: json_frontmatter
+            .map(|(json, _)| (FrontmatterFormat::Json, json))
+            .or(parsed.frontmatter())
+            .map(|(format, body)| (format, body.to_string()))
+            .ok_or(ParseError::NoFrontmatter)?,
+        toc: parsed.toc,
+        images: parsed.images,
+        shortcode_errors: parsed.shortcode_errors,
     })
 }
+
+// Autolinks are not an option of the parser, which are turned into the text by the sub parser
+fn parser_options(ctx: &GenerationContext) -> Options {
+    let markdown = &ctx.markdown;
+
+    let mut options = Options::all();
+    options.set(Options::ENABLE_SMART_PUNCTUATION, ctx.smart_punctuation());
+    options.set(Options::ENABLE_TABLES, markdown.tables);
+    options.set(Options::ENABLE_STRIKETHROUGH, markdown.strikethrough);
+    options.set(Options::ENABLE_TASKLISTS, markdown.task_lists);
+    options.set(Options::ENABLE_FOOTNOTES, markdown.footnotes);
+    options.set(Options::ENABLE_DEFINITION_LIST, markdown.definition_lists);
+
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{GenerationContext, MarkdownOptions};
+
+    use super::parse_markdown;
+
+    #[test]
+    fn punctuation_is_smart_outside_of_code() {
+        let markdown =
+            "+++\ntitle = \"t\"\nbrief = \"b\"\n+++\n\n\"Don't\" -- wait --- well... `\"--\"`\n";
+        let html = |smart_punctuation| {
+            let ctx = GenerationContext {
+                smart_punctuation,
+                ..Default::default()
+            };
+            parse_markdown(markdown, &ctx, None)
+                .expect("markdown to be parsed")
+                .html
+        };
+
+        let smart = html(None);
+        assert!(smart.contains("“Don’t” – wait — well…"));
+        assert!(smart.contains(r#"<code>"--"</code>"#));
+
+        assert!(html(Some(false)).contains("\"Don't\" -- wait --- well..."));
+    }
+
+    #[test]
+    fn excerpt_is_the_lead_before_the_more_marker() {
+        let excerpt = |markdown: &str| {
+            parse_markdown(markdown, &GenerationContext::default(), None)
+                .expect("markdown to be parsed")
+                .excerpt
+        };
+
+        assert_eq!(
+            excerpt("+++\ntitle = \"t\"\n+++\n\nThe *lead*\n\n<!-- more -->\n\nThe rest\n")
+                .as_deref(),
+            Some("<p>The <em>lead</em></p>")
+        );
+        assert_eq!(
+            excerpt("+++\ntitle = \"t\"\n+++\n\n```\n<!-- more -->\n```\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn extensions_are_turned_off_one_by_one() {
+        let markdown = concat!(
+            "+++\ntitle = \"t\"\nbrief = \"b\"\n+++\n\n",
+            "| a |\n| - |\n| b |\n\n",
+            "~~gone~~ <https://example.com> [^1]\n\n",
+            "- [x] done\n\n",
+            "[^1]: note\n",
+        );
+        let html = |markdown_options| {
+            let ctx = GenerationContext {
+                markdown: markdown_options,
+                ..Default::default()
+            };
+            parse_markdown(markdown, &ctx, None)
+                .expect("markdown to be parsed")
+                .html
+        };
+
+        let all = html(MarkdownOptions::default());
+        assert!(all.contains("<table>"));
+        assert!(all.contains("<del>gone</del>"));
+        assert!(all.contains(r#"<a href="https://example.com" "#));
+        assert!(all.contains(r#"<section class="footnotes">"#));
+        assert!(
+            all.contains(r#"<li class="task-list-item"><input type="checkbox" disabled checked> "#)
+        );
+
+        let none = html(MarkdownOptions {
+            tables: false,
+            strikethrough: false,
+            task_lists: false,
+            autolinks: false,
+            footnotes: false,
+            callouts: false,
+            definition_lists: false,
+        });
+        assert!(!none.contains("<table>"));
+        assert!(none.contains("~~gone~~"));
+        assert!(none.contains("https://example.com"));
+        assert!(!none.contains(r#"href="https://example.com""#));
+        assert!(!none.contains(r#"<section class="footnotes">"#));
+        assert!(none.contains("[x] done"));
+
+        let only_tables = html(MarkdownOptions {
+            tables: false,
+            ..Default::default()
+        });
+        assert!(!only_tables.contains("<table>"));
+        assert!(only_tables.contains("<del>gone</del>"));
+    }
+
+    #[test]
+    fn definition_lists_are_opt_in() {
+        let markdown = "+++\ntitle = \"t\"\nbrief = \"b\"\n+++\n\nApple\n: A fruit\n: A company\n";
+        let html = |definition_lists| {
+            let ctx = GenerationContext {
+                markdown: MarkdownOptions {
+                    definition_lists,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            parse_markdown(markdown, &ctx, None)
+                .expect("markdown to be parsed")
+                .html
+        };
+
+        assert_eq!(
+            html(true),
+            "<dl>\n<dt>Apple</dt>\n<dd>A fruit</dd>\n<dd>A company</dd>\n</dl>\n"
+        );
+        assert!(!html(false).contains("<dl>"));
+    }
+}
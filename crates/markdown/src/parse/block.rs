@@ -0,0 +1,48 @@
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+// Display math and the shortcodes out of the paragraphs, which `<div class="math">` and the
+// `<div>`s of the shortcodes can't be in. The parser's html in the paragraphs is `InlineHtml`, so
+// `Html` in them is only of the shortcodes. The text around them is left in the paragraphs before
+// and after them, without the line breaks next to them.
+pub fn lift_blocks(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let is_break = |event: &Event| matches!(event, Event::SoftBreak | Event::HardBreak);
+
+    let mut lifted = Vec::with_capacity(events.len());
+    let mut in_paragraph = false;
+    let mut events = events.into_iter().peekable();
+
+    while let Some(event) = events.next() {
+        match &event {
+            Event::Start(Tag::Paragraph) => in_paragraph = true,
+            Event::End(TagEnd::Paragraph) => in_paragraph = false,
+            Event::DisplayMath(_) | Event::Html(_) if in_paragraph => {
+                while lifted.last().is_some_and(is_break) {
+                    lifted.pop();
+                }
+                if matches!(lifted.last(), Some(Event::Start(Tag::Paragraph))) {
+                    lifted.pop();
+                } else {
+                    lifted.push(Event::End(TagEnd::Paragraph));
+                }
+
+                lifted.push(event);
+
+                while events.next_if(is_break).is_some() {}
+                if events
+                    .next_if(|event| matches!(event, Event::End(TagEnd::Paragraph)))
+                    .is_some()
+                {
+                    in_paragraph = false;
+                } else {
+                    lifted.push(Event::Start(Tag::Paragraph));
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        lifted.push(event);
+    }
+
+    lifted
+}
@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+
+use pulldown_cmark::{BlockQuoteKind, Event, Tag, TagEnd};
+
+// GitHub style `> [!NOTE]` blockquotes as `<div class="callout callout-note">` with the title.
+// The kinds other than the ones of GitHub are the callouts without the kind class, titled
+// after the marker.
+pub fn render_callouts(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    // Whether each of the blockquotes not closed yet is a callout, the innermost one at the last
+    let mut quotes = vec![];
+
+    let mut events = events.into_iter().collect::<VecDeque<_>>();
+    while let Some(event) = events.pop_front() {
+        match event {
+            Event::Start(Tag::BlockQuote(kind)) => {
+                let callout = match kind {
+                    Some(kind) => Some((Some(kind_name(kind)), kind_title(kind).to_string())),
+                    None => take_marker(&mut events).map(|title| (None, title)),
+                };
+                quotes.push(callout.is_some());
+
+                let Some((kind, title)) = callout else {
+                    output.push(Event::Start(Tag::BlockQuote(None)));
+                    continue;
+                };
+                let class = kind.map_or(String::new(), |kind| format!(" callout-{kind}"));
+                output.push(Event::Html(
+                    format!(r#"<div class="callout{class}"><p class="callout-title">{title}</p>"#)
+                        .into(),
+                ));
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                if quotes.pop() == Some(true) {
+                    output.push(Event::Html("</div>\n".into()));
+                } else {
+                    output.push(event);
+                }
+            }
+            event => output.push(event),
+        }
+    }
+
+    output
+}
+
+fn kind_name(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "note",
+        BlockQuoteKind::Tip => "tip",
+        BlockQuoteKind::Important => "important",
+        BlockQuoteKind::Warning => "warning",
+        BlockQuoteKind::Caution => "caution",
+    }
+}
+
+fn kind_title(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "Note",
+        BlockQuoteKind::Tip => "Tip",
+        BlockQuoteKind::Important => "Important",
+        BlockQuoteKind::Warning => "Warning",
+        BlockQuoteKind::Caution => "Caution",
+    }
+}
+
+// `[!KIND]` alone on the first line of the blockquote, which the parser leaves in the text
+// for the unknown kinds. It is taken out of the events with the paragraph left empty.
+fn take_marker(events: &mut VecDeque<Event<'_>>) -> Option<String> {
+    if !matches!(events.front(), Some(Event::Start(Tag::Paragraph))) {
+        return None;
+    }
+
+    let texts = events
+        .iter()
+        .skip(1)
+        .map_while(|event| match event {
+            Event::Text(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let after = events.get(texts.len() + 1)?;
+    let kind = texts
+        .concat()
+        .trim_end()
+        .strip_prefix("[!")?
+        .strip_suffix(']')?
+        .to_string();
+
+    if kind.is_empty() || !kind.chars().all(|char| char.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let ends_paragraph = matches!(after, Event::End(TagEnd::Paragraph));
+    if !ends_paragraph && !matches!(after, Event::SoftBreak | Event::HardBreak) {
+        return None;
+    }
+
+    // The whole paragraph of the marker, or the texts and the line break after them
+    let count = texts.len();
+    if ends_paragraph {
+        events.drain(..count + 2);
+    } else {
+        events.drain(1..count + 2);
+    }
+
+    let mut chars = kind.chars();
+    Some(
+        chars
+            .next()
+            .map(|first| first.to_ascii_uppercase().to_string() + &chars.as_str().to_lowercase())
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::{Options, Parser, html::push_html};
+
+    use super::render_callouts;
+
+    fn render(markdown: &str) -> String {
+        let events = Parser::new_ext(markdown, Options::ENABLE_GFM).collect();
+        let mut html = String::new();
+        push_html(&mut html, render_callouts(events).into_iter());
+        html
+    }
+
+    #[test]
+    fn callouts_are_titled_by_the_kind() {
+        assert_eq!(
+            render("> [!WARNING]\n> Mind the *gap*\n"),
+            concat!(
+                r#"<div class="callout callout-warning"><p class="callout-title">Warning</p>"#,
+                "\n<p>Mind the <em>gap</em></p>\n</div>\n",
+            )
+        );
+        assert_eq!(
+            render("> [!question]\n> Why?\n\n> [!HINT]\n"),
+            concat!(
+                r#"<div class="callout"><p class="callout-title">Question</p>"#,
+                "\n<p>Why?</p>\n</div>\n",
+                r#"<div class="callout"><p class="callout-title">Hint</p>"#,
+                "</div>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn plain_blockquotes_are_left() {
+        assert_eq!(
+            render("> [!NOTE]\n> > plain\n\n> [link]\n"),
+            concat!(
+                r#"<div class="callout callout-note"><p class="callout-title">Note</p>"#,
+                "\n<blockquote>\n<p>plain</p>\n</blockquote>\n</div>\n",
+                "<blockquote>\n<p>[link]</p>\n</blockquote>\n",
+            )
+        );
+    }
+}
@@ -1,16 +1,30 @@
-use std::{collections::VecDeque, ops::ControlFlow};
+use std::{collections::VecDeque, ops::ControlFlow, path::Path, vec::IntoIter};
 
+use loss72_platemaker_core::model::{ArticleImage, GenerationContext, TocEntry};
 use pulldown_cmark::{Event, Options, Parser};
 
+use crate::frontmatter::FrontmatterFormat;
+
 use super::{
+    block::lift_blocks,
+    callout::render_callouts,
     control::{BreakingEventProcess, Ignore},
+    heading::assign_heading_ids,
+    math::unterminated_math_line,
+    shortcode::{ShortcodeError, expand_shortcodes},
     sub_parser::{SubParser, SubParsers},
+    task_list::mark_task_list_items,
 };
 
 #[derive(Default, Debug)]
 pub struct MarkdownParseResult {
-    pub frontmatter: Option<String>,
+    pub frontmatter: Option<(FrontmatterFormat, String)>,
     pub html: String,
+    pub toc: Vec<TocEntry>,
+    pub undefined_footnotes: Vec<String>,
+    pub images: Vec<ArticleImage>,
+    pub unterminated_math: Option<usize>,
+    pub shortcode_errors: Vec<ShortcodeError>,
 }
 
 impl MarkdownParseResult {
@@ -18,13 +32,18 @@ impl MarkdownParseResult {
         &self.html
     }
 
-    pub fn frontmatter(&self) -> Option<&str> {
-        self.frontmatter.as_deref()
+    pub fn frontmatter(&self) -> Option<(FrontmatterFormat, &str)> {
+        self.frontmatter
+            .as_ref()
+            .map(|(format, body)| (*format, body.as_str()))
     }
 }
 
 pub struct MarkdownParser<'p> {
-    parser: Parser<'p>,
+    events: IntoIter<Event<'p>>,
+    toc: Vec<TocEntry>,
+    unterminated_math: Option<usize>,
+    shortcode_errors: Vec<ShortcodeError>,
     sub_parser: SubParsers<'p>,
     ignore: Option<Ignore<'p>>,
     finalized: bool,
@@ -32,10 +51,36 @@ pub struct MarkdownParser<'p> {
 }
 
 impl<'p> MarkdownParser<'p> {
-    pub fn new(content: &'p str, parser_option: Options) -> Self {
+    pub fn new(
+        content: &'p str,
+        parser_option: Options,
+        ctx: &GenerationContext,
+        article_dir: Option<&Path>,
+    ) -> Self {
+        // Heading ids depend on the text which follows the heading start, so the events
+        // are collected up front to assign them before any sub parser sees the heading.
+        // Math delimiters are checked against the source, where `\$` is still escaped.
+        let events = Parser::new_ext(content, parser_option)
+            .into_offset_iter()
+            .collect::<Vec<_>>();
+        let unterminated_math = unterminated_math_line(content, &events);
+        let (events, shortcode_errors) =
+            expand_shortcodes(events.into_iter().map(|(event, _)| event).collect());
+        let (events, toc) = assign_heading_ids(events, ctx.heading_links);
+        let events = mark_task_list_items(events);
+        let events = lift_blocks(events);
+        let events = if ctx.markdown.callouts {
+            render_callouts(events)
+        } else {
+            events
+        };
+
         MarkdownParser {
-            sub_parser: SubParsers::default(),
-            parser: pulldown_cmark::Parser::new_ext(content, parser_option),
+            sub_parser: SubParsers::new(ctx, article_dir),
+            events: events.into_iter(),
+            toc,
+            unterminated_math,
+            shortcode_errors,
             ignore: None,
             finalized: false,
             last_append: VecDeque::new(),
@@ -49,11 +94,21 @@ impl<'p> MarkdownParser<'p> {
         MarkdownParseResult {
             frontmatter: self.sub_parser.frontmatter.compose_output().body,
             html,
+            toc: self.toc,
+            undefined_footnotes: self.sub_parser.footnote.compose_output(),
+            images: self.sub_parser.image.compose_output(),
+            unterminated_math: self.unterminated_math,
+            shortcode_errors: self.shortcode_errors,
         }
     }
 
-    pub fn parse(content: &'p str, parser_option: Options) -> MarkdownParseResult {
-        Self::new(content, parser_option).run()
+    pub fn parse(
+        content: &'p str,
+        parser_option: Options,
+        ctx: &GenerationContext,
+        article_dir: Option<&Path>,
+    ) -> MarkdownParseResult {
+        Self::new(content, parser_option, ctx, article_dir).run()
     }
 
     pub fn finalization(&mut self) {
@@ -66,7 +121,7 @@ impl<'p> Iterator for &mut MarkdownParser<'p> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let event = {
-            if let Some(event) = self.parser.next() {
+            if let Some(event) = self.events.next() {
                 event
             } else {
                 if !self.finalized {
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+
+use loss72_platemaker_core::model::TocEntry;
+use pulldown_cmark::{Event, HeadingLevel, Tag, TagEnd};
+
+pub fn assign_heading_ids<'p>(
+    events: Vec<Event<'p>>,
+    heading_links: bool,
+) -> (Vec<Event<'p>>, Vec<TocEntry>) {
+    let mut used_ids = HashSet::new();
+    let mut toc = vec![];
+    let mut output = Vec::with_capacity(events.len());
+
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        let Event::Start(Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        }) = event
+        else {
+            output.push(event);
+            continue;
+        };
+
+        let mut content = vec![];
+        while let Some(event) =
+            events.next_if(|event| !matches!(event, Event::End(TagEnd::Heading(_))))
+        {
+            content.push(event);
+        }
+
+        let title = content
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+
+        // Headings with `{#explicit-id}` keep it, and it is reserved for the rest.
+        // Others are resolved in document order, so the same source always gets the same ids
+        let id = match id {
+            Some(id) => {
+                used_ids.insert(id.to_string());
+                id.to_string()
+            }
+            None => unique_id(heading_slug(&title), &mut used_ids),
+        };
+
+        output.push(Event::Start(Tag::Heading {
+            level,
+            id: Some(id.clone().into()),
+            classes,
+            attrs,
+        }));
+        if heading_links {
+            output.push(Event::InlineHtml(
+                format!(r##"<a class="heading-link" href="#{id}" aria-hidden="true">#</a>"##)
+                    .into(),
+            ));
+        }
+        output.extend(content);
+
+        if matches!(level, HeadingLevel::H2 | HeadingLevel::H3) {
+            toc.push(TocEntry {
+                level: level as u8,
+                title,
+                id,
+            });
+        }
+    }
+
+    (output, toc)
+}
+
+fn heading_slug(title: &str) -> String {
+    let slug = title
+        .to_lowercase()
+        .split(|char: char| !char.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        "heading".to_string()
+    } else {
+        slug
+    }
+}
+
+fn unique_id(slug: String, used_ids: &mut HashSet<String>) -> String {
+    let mut id = slug.clone();
+    let mut suffix = 0;
+
+    while used_ids.contains(&id) {
+        suffix += 1;
+        id = format!("{slug}-{suffix}");
+    }
+
+    used_ids.insert(id.clone());
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::{Options, Parser, html::push_html};
+
+    use super::assign_heading_ids;
+
+    fn toc_ids(markdown: &str) -> Vec<(u8, String)> {
+        let events = Parser::new_ext(markdown, Options::all()).collect::<Vec<_>>();
+
+        assign_heading_ids(events, false)
+            .1
+            .into_iter()
+            .map(|entry| (entry.level, entry.id))
+            .collect()
+    }
+
+    #[test]
+    fn collects_second_and_third_level_headings() {
+        assert_eq!(
+            toc_ids("# Title\n## First `code`\n### Nested!\n#### Too deep\n## Second"),
+            [
+                (2, "first-code".to_string()),
+                (3, "nested".to_string()),
+                (2, "second".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn disambiguates_duplicate_headings() {
+        assert_eq!(
+            toc_ids("## Intro\n## Intro\n### Intro\n## Other {#intro-3}"),
+            [
+                (2, "intro".to_string()),
+                (2, "intro-1".to_string()),
+                (3, "intro-2".to_string()),
+                (2, "intro-3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_heading_gets_an_id() {
+        let events = Parser::new_ext(
+            "# Über Straße!\n#### 日本語 の 見出し\n# Über Straße",
+            Options::all(),
+        )
+        .collect::<Vec<_>>();
+        let (events, _) = assign_heading_ids(events, true);
+
+        let mut html = String::new();
+        push_html(&mut html, events.into_iter());
+
+        assert_eq!(
+            html,
+            concat!(
+                r##"<h1 id="über-straße"><a class="heading-link" href="#über-straße" aria-hidden="true">#</a>Über Straße!</h1>"##,
+                "\n",
+                r##"<h4 id="日本語-の-見出し"><a class="heading-link" href="#日本語-の-見出し" aria-hidden="true">#</a>日本語 の 見出し</h4>"##,
+                "\n",
+                r##"<h1 id="über-straße-1"><a class="heading-link" href="#über-straße-1" aria-hidden="true">#</a>Über Straße</h1>"##,
+                "\n",
+            )
+        );
+    }
+}
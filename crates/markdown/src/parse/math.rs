@@ -0,0 +1,31 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+// Line of the first `$` which was not parsed as a math delimiter nor escaped as `\$`
+pub fn unterminated_math_line(
+    content: &str,
+    events: &[(Event<'_>, Range<usize>)],
+) -> Option<usize> {
+    let mut in_verbatim = false;
+
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::MetadataBlock(_)) => in_verbatim = true,
+            Event::End(TagEnd::CodeBlock | TagEnd::MetadataBlock(_)) => in_verbatim = false,
+            Event::Text(text) if !in_verbatim && text.contains('$') => {
+                // The range of the escaped one starts after the backslash
+                let unescaped = content[range.clone()].char_indices().find(|(index, char)| {
+                    *char == '$' && !content[..range.start + index].ends_with('\\')
+                });
+
+                if let Some((index, _)) = unescaped {
+                    return Some(content[..range.start + index].lines().count().max(1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use loss72_platemaker_widgets::shortcode::{ShortcodeArgs, expand_shortcode};
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShortcodeError {
+    Unknown(String),
+    Invalid(String, String),
+}
+
+// Expands `{{< name args >}}` in the text. Code blocks, code spans and the frontmatter
+// are separate events from the text, so shortcodes in them are left literal.
+pub fn expand_shortcodes(events: Vec<Event<'_>>) -> (Vec<Event<'_>>, Vec<ShortcodeError>) {
+    let mut output = Vec::with_capacity(events.len());
+    let mut errors = vec![];
+    let mut text = String::new();
+    let mut in_verbatim = false;
+
+    for event in events {
+        match event {
+            // The parser splits text at some characters like `<`, so it is joined first
+            Event::Text(fragment) if !in_verbatim => {
+                text.push_str(&fragment);
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_) | Tag::MetadataBlock(_)) => in_verbatim = true,
+            Event::End(TagEnd::CodeBlock | TagEnd::MetadataBlock(_)) => in_verbatim = false,
+            _ => {}
+        }
+
+        output.extend(expand_in_text(&std::mem::take(&mut text), &mut errors));
+        output.push(event);
+    }
+    output.extend(expand_in_text(&text, &mut errors));
+
+    (output, errors)
+}
+
+fn expand_in_text(text: &str, errors: &mut Vec<ShortcodeError>) -> Vec<Event<'static>> {
+    let mut events = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{<") {
+        let Some(length) = rest[start..].find(">}}") else {
+            break;
+        };
+
+        let literal = &rest[start..start + length + 3];
+        let tokens = tokenize(&rest[start + 3..start + length]);
+        let [name, args @ ..] = tokens.as_slice() else {
+            break;
+        };
+
+        events.push(text_event(&rest[..start]));
+        events.push(match expand_shortcode(name, &parse_args(args)) {
+            Some(Ok(html)) => Event::Html(html.into()),
+            Some(Err(reason)) => {
+                errors.push(ShortcodeError::Invalid(name.clone(), reason));
+                text_event(literal)
+            }
+            None => {
+                errors.push(ShortcodeError::Unknown(name.clone()));
+                text_event(literal)
+            }
+        });
+
+        rest = &rest[start + length + 3..];
+    }
+
+    events.push(text_event(rest));
+    events.retain(|event| event != &Event::Text(CowStr::Borrowed("")));
+    events
+}
+
+fn text_event(text: &str) -> Event<'static> {
+    Event::Text(text.to_string().into())
+}
+
+fn tokenize(str: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = None::<String>;
+    let mut quote = None;
+
+    for char in str.chars() {
+        match (quote, char) {
+            // Smart punctuation has already curled the quotes when they reach here
+            (None, '"' | '\'' | '“' | '‘') => {
+                quote = Some(match char {
+                    '“' => '”',
+                    '‘' => '’',
+                    _ => char,
+                });
+                token.get_or_insert_default();
+            }
+            (Some(close), _) if close == char => quote = None,
+            (None, _) if char.is_whitespace() => tokens.extend(token.take()),
+            _ => token.get_or_insert_default().push(char),
+        }
+    }
+    tokens.extend(token);
+
+    tokens
+}
+
+fn parse_args(tokens: &[String]) -> ShortcodeArgs {
+    let mut positional = vec![];
+    let mut named = HashMap::new();
+
+    for token in tokens {
+        match token.split_once('=') {
+            Some((key, value))
+                if !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_') =>
+            {
+                named.insert(key.to_string(), value.to_string());
+            }
+            _ => positional.push(token.clone()),
+        }
+    }
+
+    ShortcodeArgs { positional, named }
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::GenerationContext;
+    use loss72_platemaker_widgets::shortcode::expand_shortcode;
+    use pulldown_cmark::{Options, Parser, html::push_html};
+
+    use super::{ShortcodeError, expand_shortcodes, parse_args};
+    use crate::parse::full_service::MarkdownParser;
+
+    fn expand(markdown: &str) -> (String, Vec<ShortcodeError>) {
+        let (events, errors) =
+            expand_shortcodes(Parser::new_ext(markdown, Options::all()).collect());
+
+        let mut html = String::new();
+        push_html(&mut html, events.into_iter());
+        (html, errors)
+    }
+
+    #[test]
+    fn expands_registered_shortcodes_outside_of_code() {
+        let (html, errors) = expand(concat!(
+            "Video: {{< youtube \"abc_123\" >}}\n\n",
+            "`{{< youtube inline >}}`\n\n",
+            "```\n{{< youtube fenced >}}\n```\n",
+        ));
+
+        assert!(errors.is_empty());
+        assert!(html.starts_with(concat!(
+            "<p>Video: ",
+            r#"<div class="youtube"><iframe src="https://www.youtube-nocookie.com/embed/abc_123""#
+        )));
+        assert!(html.contains("<code>{{&lt; youtube inline &gt;}}</code>"));
+        assert!(html.contains("<pre><code>{{&lt; youtube fenced &gt;}}\n</code></pre>"));
+    }
+
+    #[test]
+    fn shortcodes_are_out_of_the_paragraphs() {
+        let parsed = MarkdownParser::parse(
+            "Video:\n{{< youtube abc >}}\nafter\n\n{{< youtube def >}}\n",
+            Options::all(),
+            &GenerationContext::default(),
+            None,
+        );
+        let embed = |id: &str| {
+            expand_shortcode("youtube", &parse_args(&[id.to_string()]))
+                .and_then(Result::ok)
+                .expect("shortcode to be expanded")
+        };
+
+        assert_eq!(
+            parsed.html,
+            format!(
+                "<p>Video:</p>\n{}\n<p>after</p>\n{}",
+                embed("abc"),
+                embed("def")
+            )
+        );
+    }
+
+    #[test]
+    fn reports_unknown_and_invalid_shortcodes() {
+        let (_, errors) = expand("{{< tweet 1 >}} {{< youtube id=\"a b\" >}}");
+
+        assert_eq!(
+            errors,
+            [
+                ShortcodeError::Unknown("tweet".to_string()),
+                ShortcodeError::Invalid(
+                    "youtube".to_string(),
+                    "`a b` is not a valid video id".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,3 +1,6 @@
+use std::path::Path;
+
+use loss72_platemaker_core::model::GenerationContext;
 use pulldown_cmark::Event;
 
 use super::control::{EventProcessControl, Next};
@@ -5,8 +8,13 @@ use super::control::{EventProcessControl, Next};
 mod code_block;
 mod footnote;
 mod frontmatter;
+mod image;
+mod link;
+mod math;
 mod text;
 
+pub use code_block::syntax_theme_css;
+
 pub trait SubParser<'p> {
     type Output;
 
@@ -17,20 +25,38 @@ pub trait SubParser<'p> {
     fn compose_output(self) -> Self::Output;
 }
 
-#[derive(Default)]
 pub struct SubParsers<'p> {
     pub code_block: code_block::CodeBlockSubParser,
     pub footnote: footnote::FootnoteSubParser<'p>,
     pub frontmatter: frontmatter::FrontmatterSubParser,
+    pub image: image::ImageSubParser,
+    pub link: link::LinkSubParser,
+    pub math: math::MathSubParser,
     pub text: text::TextParser,
 }
 
 impl<'p> SubParsers<'p> {
+    pub fn new(ctx: &GenerationContext, article_dir: Option<&Path>) -> Self {
+        Self {
+            code_block: code_block::CodeBlockSubParser::new(ctx.syntax_theme),
+            footnote: Default::default(),
+            frontmatter: Default::default(),
+            image: image::ImageSubParser::new(article_dir, ctx),
+            link: link::LinkSubParser::new(ctx),
+            math: math::MathSubParser::new(ctx.mathml),
+            text: text::TextParser::new(ctx.emoji),
+        }
+    }
+
     pub fn receive_event(&mut self, event: &Event<'p>) -> EventProcessControl<'p> {
         let mut next = Next::default();
         next.update_by(self.code_block.receive_event(next.next_event(event))?);
+        // Before the footnotes, which keep the events of their definitions
+        next.update_by(self.link.receive_event(next.next_event(event))?);
         next.update_by(self.footnote.receive_event(next.next_event(event))?);
         next.update_by(self.frontmatter.receive_event(next.next_event(event))?);
+        next.update_by(self.image.receive_event(next.next_event(event))?);
+        next.update_by(self.math.receive_event(next.next_event(event))?);
         next.update_by(self.text.receive_event(next.next_event(event))?);
 
         EventProcessControl::Continue(next)
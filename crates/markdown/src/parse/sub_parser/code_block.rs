@@ -1,9 +1,12 @@
-use loss72_platemaker_core::log;
+use std::sync::LazyLock;
+
+use loss72_platemaker_core::{log, model::SyntaxTheme, util::escape_html};
 use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
 use syntect::{
     highlighting::ThemeSet,
-    html::highlighted_html_for_string,
+    html::{ClassStyle, ClassedHTMLGenerator, css_for_theme_with_class_style},
     parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
 };
 
 use crate::parse::control::{EventProcessControl, discard, use_html, use_next};
@@ -15,12 +18,22 @@ struct CodeBlockParseState {
     has_content: bool,
 }
 
-#[derive(Default)]
 pub struct CodeBlockSubParser {
     parse_state: Option<CodeBlockParseState>,
     highlighter: SyntaxHighlighter,
 }
 
+impl CodeBlockSubParser {
+    pub fn new(theme: SyntaxTheme) -> Self {
+        Self {
+            parse_state: None,
+            highlighter: SyntaxHighlighter {
+                enabled: theme != SyntaxTheme::Disabled,
+            },
+        }
+    }
+}
+
 impl<'p> SubParser<'p> for CodeBlockSubParser {
     type Output = ();
 
@@ -52,8 +65,10 @@ impl<'p> SubParser<'p> for CodeBlockSubParser {
                     use_html(self.highlighter.generate_highlighted_html("", "").into())
                 }
             }
+            // of the passes before the sub parsers, which are not meant to be in the code blocks
             (Some(_), _) => {
-                panic!("Unexpected event during parsing:{event:#?}")
+                log!(warn: "Skipping an unexpected event in a code block: {:?}", event);
+                discard()
             }
         }
     }
@@ -61,47 +76,110 @@ impl<'p> SubParser<'p> for CodeBlockSubParser {
     fn compose_output(self) -> Self::Output {}
 }
 
-struct SyntaxHighlighter {
-    syntax_set: SyntaxSet,
+// Loading the syntax definitions is expensive, so every article in the build shares them
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "syntax-" };
+
+pub fn syntax_theme_css(theme: SyntaxTheme) -> Option<String> {
+    let name = match theme {
+        SyntaxTheme::SolarizedLight => "Solarized (light)",
+        SyntaxTheme::SolarizedDark => "Solarized (dark)",
+        SyntaxTheme::InspiredGithub => "InspiredGitHub",
+        SyntaxTheme::Base16OceanLight => "base16-ocean.light",
+        SyntaxTheme::Base16OceanDark => "base16-ocean.dark",
+        SyntaxTheme::Base16EightiesDark => "base16-eighties.dark",
+        SyntaxTheme::Base16MochaDark => "base16-mocha.dark",
+        SyntaxTheme::Disabled => return None,
+    };
+
+    Some(
+        css_for_theme_with_class_style(&ThemeSet::load_defaults().themes[name], CLASS_STYLE)
+            .expect("built-in themes to be converted to CSS"),
+    )
 }
 
-impl Default for SyntaxHighlighter {
-    fn default() -> Self {
-        Self {
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-        }
-    }
+struct SyntaxHighlighter {
+    enabled: bool,
 }
 
 impl SyntaxHighlighter {
     fn generate_highlighted_html(&self, lang: &str, content: &str) -> String {
+        let highlighted = if self.enabled {
+            self.find_language(lang)
+                .map(|syntax_ref| self.generate_html(syntax_ref, content))
+        } else {
+            None
+        };
+
         format!(
-            r#"<code class="block">{}</code>"#,
-            self.generate_html(self.find_language(lang), content)
+            r#"<pre><code class="block">{}</code></pre>"#,
+            highlighted.unwrap_or_else(|| escape_html(content))
         )
     }
 
-    fn find_language(&self, lang: &str) -> &SyntaxReference {
+    fn find_language(&self, lang: &str) -> Option<&'static SyntaxReference> {
         if lang.is_empty() {
-            self.syntax_set.find_syntax_plain_text()
-        } else {
-            self.syntax_set
-                .find_syntax_by_name(lang)
-                .or_else(|| self.syntax_set.find_syntax_by_extension(lang))
-                .unwrap_or_else(|| {
-                    log!(warn: "Unknown syntax highlight language: {}", lang);
-                    self.syntax_set.find_syntax_plain_text()
-                })
+            return None;
         }
+
+        let syntax_ref = SYNTAX_SET
+            .find_syntax_by_name(lang)
+            .or_else(|| SYNTAX_SET.find_syntax_by_token(lang));
+
+        if syntax_ref.is_none() {
+            log!(warn: "Unknown syntax highlight language: {}", lang);
+        }
+
+        syntax_ref
     }
 
     fn generate_html(&self, syntax_ref: &SyntaxReference, content: &str) -> String {
-        highlighted_html_for_string(
-            content,
-            &self.syntax_set,
-            syntax_ref,
-            &ThemeSet::load_defaults().themes["Solarized (light)"],
-        )
-        .expect("syntax highlight parsing not to fail")
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax_ref, &SYNTAX_SET, CLASS_STYLE);
+
+        for line in LinesWithEndings::from(content) {
+            generator
+                .parse_html_for_line_which_includes_newline(line)
+                .expect("syntax highlight parsing not to fail");
+        }
+
+        generator.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use loss72_platemaker_core::model::SyntaxTheme;
+    use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+
+    use super::CodeBlockSubParser;
+    use crate::parse::{control::BreakingEventProcess, sub_parser::SubParser};
+
+    #[test]
+    fn unexpected_events_in_code_blocks_are_skipped() {
+        let mut parser = CodeBlockSubParser::new(SyntaxTheme::Disabled);
+        let html = [
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced("".into()))),
+            Event::Text("a < b\n".into()),
+            Event::InlineHtml("<span>".into()),
+            Event::End(TagEnd::CodeBlock),
+        ]
+        .iter()
+        .filter_map(|event| match parser.receive_event(event) {
+            ControlFlow::Break(BreakingEventProcess::UseThisInstead(Event::Html(html))) => {
+                Some(html.to_string())
+            }
+            _ => None,
+        })
+        .collect::<String>();
+
+        assert_eq!(
+            html,
+            r#"<pre><code class="block">a &lt; b
+</code></pre>"#
+        );
     }
 }
@@ -93,7 +93,8 @@ pub struct FootnoteSubParser<'p> {
 }
 
 impl<'p> SubParser<'p> for FootnoteSubParser<'p> {
-    type Output = ();
+    // Ids of the footnotes which are referenced but never defined
+    type Output = Vec<String>;
 
     fn receive_event(&mut self, event: &Event<'p>) -> EventProcessControl<'p> {
         if self.building_footnotes.is_some() {
@@ -122,7 +123,7 @@ impl<'p> SubParser<'p> for FootnoteSubParser<'p> {
 
         let mut events = vec![];
         events.push(Event::Html(
-            r#"<aside class="footnote-def"><h1>脚注</h1><ol>"#.into(),
+            r#"<section class="footnotes"><h1>脚注</h1><ol>"#.into(),
         ));
 
         let mut footnotes = self
@@ -159,12 +160,18 @@ impl<'p> SubParser<'p> for FootnoteSubParser<'p> {
             events.push(Event::Html(r#"</li>"#.into()));
         }
 
-        events.push(Event::Html(r#"</ol></aside>"#.into()));
+        events.push(Event::Html(r#"</ol></section>"#.into()));
 
         Some(events)
     }
 
-    fn compose_output(self) -> Self::Output {}
+    fn compose_output(self) -> Self::Output {
+        self.footnote_refs
+            .into_iter()
+            .filter(|refer| !self.footnotes.iter().any(|def| def.id == refer.id))
+            .map(|refer| refer.id)
+            .collect()
+    }
 }
 
 impl<'p> FootnoteSubParser<'p> {
@@ -187,3 +194,44 @@ impl<'p> FootnoteSubParser<'p> {
         footnote.events.push(event.clone());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::GenerationContext;
+    use pulldown_cmark::Options;
+
+    use crate::parse::full_service::MarkdownParser;
+
+    fn parse(markdown: &str) -> (String, Vec<String>) {
+        let parsed = MarkdownParser::parse(
+            markdown,
+            Options::all(),
+            &GenerationContext::default(),
+            None,
+        );
+        (parsed.html, parsed.undefined_footnotes)
+    }
+
+    #[test]
+    fn footnotes_are_ordered_by_first_reference() {
+        let (html, undefined) = parse("b[^b] a[^a] b[^b]\n\n[^a]: A\n\n[^b]: B\n");
+
+        assert!(undefined.is_empty());
+        assert!(html.contains(r##"<a href="#fn_b">#1</a>"##));
+        assert!(html.contains(r##"<a href="#fn_a">#2</a>"##));
+
+        let section = &html[html
+            .find(r#"<section class="footnotes">"#)
+            .expect("footnotes section")..];
+        assert!(section.find(r#"id="fn_b""#) < section.find(r#"id="fn_a""#));
+        assert!(section.contains(r##"href="#fnref_b_1""##));
+        assert!(section.contains(r##"href="#fnref_b_2""##));
+    }
+
+    #[test]
+    fn undefined_references_are_reported() {
+        let (_, undefined) = parse("a[^a] missing[^missing]\n\n[^a]: A\n");
+
+        assert_eq!(undefined, ["missing"]);
+    }
+}
@@ -1,21 +1,19 @@
 use pulldown_cmark::{Event, MetadataBlockKind, Tag, TagEnd};
 
-use crate::parse::control::{EventProcessControl, discard, use_next};
+use crate::{
+    frontmatter::FrontmatterFormat,
+    parse::control::{EventProcessControl, discard, use_next},
+};
 
 use super::SubParser;
 
-const METADATA_TOML_START: Event<'_> =
-    Event::Start(Tag::MetadataBlock(MetadataBlockKind::PlusesStyle));
-const METADATA_TOML_END: Event<'_> =
-    Event::End(TagEnd::MetadataBlock(MetadataBlockKind::PlusesStyle));
-
 pub struct Frontmatter {
-    pub body: Option<String>,
+    pub body: Option<(FrontmatterFormat, String)>,
 }
 
 #[derive(Default)]
 pub struct FrontmatterSubParser {
-    body: Option<String>,
+    body: Option<(FrontmatterFormat, String)>,
     in_frontmatter: bool,
 }
 
@@ -24,31 +22,34 @@ impl SubParser<'_> for FrontmatterSubParser {
 
     fn receive_event<'e>(&mut self, event: &Event<'e>) -> EventProcessControl<'e> {
         match (&self.in_frontmatter, event) {
-            (false, &METADATA_TOML_START) => {
+            (false, Event::Start(Tag::MetadataBlock(kind))) => {
                 if self.body.is_some() {
-                    panic!("Encountered to the toml beginning twice");
+                    panic!("Encountered to the frontmatter beginning twice");
                 }
 
+                let format = match kind {
+                    MetadataBlockKind::PlusesStyle => FrontmatterFormat::Toml,
+                    MetadataBlockKind::YamlStyle => FrontmatterFormat::Yaml,
+                };
+                self.body = Some((format, String::new()));
                 self.in_frontmatter = true;
                 discard()
             }
             (false, _) => use_next(),
             (true, Event::Text(text)) => {
-                if let Some(body) = self.body.as_mut() {
+                if let Some((_, body)) = self.body.as_mut() {
                     body.push_str(text.as_ref());
-                } else {
-                    self.body = Some(text.to_string());
                 }
 
                 discard()
             }
-            (true, &METADATA_TOML_END) => {
+            (true, Event::End(TagEnd::MetadataBlock(_))) => {
                 self.in_frontmatter = false;
                 discard()
             }
             (true, _) => {
                 panic!(
-                    "Only Event::Text() and METADATA_TOML_END should come within frontmatter, but received...\n{event:#?}"
+                    "Only Event::Text() and the end of metadata block should come within frontmatter, but received...\n{event:#?}"
                 );
             }
         }
@@ -0,0 +1,223 @@
+use std::path::{Component, Path, PathBuf};
+
+use loss72_platemaker_core::{
+    log,
+    model::{ArticleImage, GenerationContext},
+    util::escape_attribute,
+};
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+use crate::parse::control::{EventProcessControl, discard, use_html, use_next};
+
+use super::SubParser;
+
+#[derive(Debug)]
+struct Image {
+    src: String,
+    title: String,
+    alt: String,
+    size: Option<(u32, u32)>,
+    lazy: bool,
+}
+
+#[derive(Default, Debug)]
+pub struct ImageSubParser {
+    article_dir: Option<PathBuf>,
+    lazy_images: bool,
+    eager_first_image: bool,
+    rendered: usize,
+    building_image: Option<Image>,
+    images: Vec<ArticleImage>,
+}
+
+impl ImageSubParser {
+    pub fn new(article_dir: Option<&Path>, ctx: &GenerationContext) -> Self {
+        Self {
+            article_dir: article_dir.map(Path::to_path_buf),
+            lazy_images: ctx.lazy_images(),
+            eager_first_image: ctx.eager_first_image,
+            rendered: 0,
+            building_image: None,
+            images: vec![],
+        }
+    }
+}
+
+impl<'p> SubParser<'p> for ImageSubParser {
+    type Output = Vec<ArticleImage>;
+
+    fn receive_event(&mut self, event: &Event<'p>) -> EventProcessControl<'p> {
+        if let Some(image) = &mut self.building_image {
+            return match event {
+                Event::End(TagEnd::Image) => {
+                    let image = self
+                        .building_image
+                        .take()
+                        .expect("building_image to be available when active");
+                    use_html(image.render().into())
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    image.alt.push_str(text);
+                    discard()
+                }
+                _ => discard(),
+            };
+        }
+
+        let Event::Start(Tag::Image {
+            dest_url, title, ..
+        }) = event
+        else {
+            return use_next();
+        };
+
+        // Alt text is collected here, as the text parser turns it into HTML which is not
+        // rendered into the attribute
+        let local = self.local_image(dest_url);
+        let eager = self.eager_first_image && self.rendered == 0;
+        self.building_image = Some(Image {
+            src: dest_url.to_string(),
+            title: title.to_string(),
+            alt: String::new(),
+            size: local.as_ref().map(|image| (image.width, image.height)),
+            lazy: self.lazy_images && !eager,
+        });
+        self.rendered += 1;
+        self.images.extend(local);
+
+        discard()
+    }
+
+    fn compose_output(self) -> Self::Output {
+        self.images
+    }
+}
+
+impl ImageSubParser {
+    fn local_image(&self, url: &str) -> Option<ArticleImage> {
+        // External ones, `data:` and absolute paths are not article assets
+        if url.contains(':') || url.starts_with('/') {
+            return None;
+        }
+
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let path = normalize(&self.article_dir.as_ref()?.join(path));
+
+        match imagesize::size(&path) {
+            Ok(size) => Some(ArticleImage {
+                src: url.to_string(),
+                source: path,
+                width: size.width as u32,
+                height: size.height as u32,
+            }),
+            Err(e) => {
+                log!(warn: "Size of the image {} could not be determined: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+impl Image {
+    fn render(&self) -> String {
+        let title = if self.title.is_empty() {
+            String::new()
+        } else {
+            format!(r#" title="{}""#, escape_attribute(&self.title))
+        };
+
+        // Images which are not resolved are left as they are, only with the warning
+        let size = self
+            .size
+            .map(|(width, height)| format!(r#" width="{width}" height="{height}""#))
+            .unwrap_or_default();
+
+        let loading = if self.lazy {
+            r#" loading="lazy" decoding="async""#
+        } else {
+            ""
+        };
+
+        format!(
+            r#"<img src="{}" alt="{}"{title}{size}{loading} />"#,
+            escape_attribute(&self.src),
+            escape_attribute(&self.alt),
+        )
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::GenerationContext;
+    use pulldown_cmark::Options;
+
+    use crate::parse::full_service::MarkdownParser;
+
+    #[test]
+    fn adds_size_to_local_images() {
+        let dir = std::env::temp_dir().join("platemaker-image-size-test");
+        std::fs::create_dir_all(dir.join("assets")).expect("temporary directory to be created");
+
+        // 1x1 transparent GIF, with the size patched to 3x2
+        let mut gif = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xff\xff\xff!\xf9\x04\x01\x00\x00\x00\x00,\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02D\x01\x00;".to_vec();
+        gif[6] = 3;
+        gif[8] = 2;
+        std::fs::write(dir.join("assets/pic.gif"), gif).expect("image to be written");
+
+        let parse = |ctx: &GenerationContext| {
+            MarkdownParser::parse(
+                "![A & B](./assets/pic.gif \"Title\") ![missing](./assets/none.png) ![remote](https://example.com/a.png)",
+                Options::all(),
+                ctx,
+                Some(&dir),
+            )
+            .html
+        };
+
+        assert_eq!(
+            parse(&GenerationContext {
+                lazy_images: Some(false),
+                ..Default::default()
+            }),
+            concat!(
+                r#"<p><img src="./assets/pic.gif" alt="A &amp; B" title="Title" width="3" height="2" /> "#,
+                r#"<img src="./assets/none.png" alt="missing" /> "#,
+                r#"<img src="https://example.com/a.png" alt="remote" /></p>"#,
+                "\n",
+            )
+        );
+
+        assert_eq!(
+            parse(&GenerationContext {
+                eager_first_image: true,
+                ..Default::default()
+            }),
+            concat!(
+                r#"<p><img src="./assets/pic.gif" alt="A &amp; B" title="Title" width="3" height="2" /> "#,
+                r#"<img src="./assets/none.png" alt="missing" loading="lazy" decoding="async" /> "#,
+                r#"<img src="https://example.com/a.png" alt="remote" loading="lazy" decoding="async" /></p>"#,
+                "\n",
+            )
+        );
+        assert!(
+            parse(&GenerationContext::default())
+                .starts_with(r#"<p><img src="./assets/pic.gif" alt="A &amp; B" title="Title" width="3" height="2" loading="lazy" decoding="async" />"#)
+        );
+    }
+}
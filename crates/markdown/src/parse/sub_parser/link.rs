@@ -0,0 +1,214 @@
+use loss72_platemaker_core::{
+    model::GenerationContext,
+    util::{escape_attribute, url_host},
+};
+use pulldown_cmark::{Event, LinkType, Tag, TagEnd};
+
+use crate::parse::control::{EventProcessControl, Next, discard, use_next, use_next_with};
+
+use super::SubParser;
+
+// Links to the other hosts than the site, which are all the absolute ones without the site
+// host. Internal links are left to the renderer.
+// `article:2024/03/slug` links to the article of the id, which is left as `article:202403/slug`
+// for the renderer to link to wherever the article is. `[[slug]]` and `[[slug|label]]` are
+// left as `wikilink:slug` in the same way, which are looked up by the slugs.
+// Autolinks are only their text when they are turned off.
+pub struct LinkSubParser {
+    site_host: Option<String>,
+    new_tab: bool,
+    autolinks: bool,
+    in_unlinked: bool,
+}
+
+impl LinkSubParser {
+    pub fn new(ctx: &GenerationContext) -> Self {
+        Self {
+            site_host: ctx.site_host.clone(),
+            new_tab: ctx.external_links_in_new_tab,
+            autolinks: ctx.markdown.autolinks,
+            in_unlinked: false,
+        }
+    }
+}
+
+impl<'p> SubParser<'p> for LinkSubParser {
+    type Output = ();
+
+    fn receive_event(&mut self, event: &Event<'p>) -> EventProcessControl<'p> {
+        // The end of an autolink which was turned off
+        if self.in_unlinked && matches!(event, Event::End(TagEnd::Link)) {
+            self.in_unlinked = false;
+            return discard();
+        }
+
+        let Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            ..
+        }) = event
+        else {
+            return use_next();
+        };
+
+        if !self.autolinks && matches!(link_type, LinkType::Autolink | LinkType::Email) {
+            self.in_unlinked = true;
+            return discard();
+        }
+
+        if let LinkType::WikiLink { .. } = link_type {
+            return use_next_with(Next {
+                replacement: Some(Event::Html(
+                    format!(
+                        r#"<a href="wikilink:{}" class="wikilink">"#,
+                        escape_attribute(dest_url)
+                    )
+                    .into(),
+                )),
+                ignore: None,
+            });
+        }
+
+        if let Some(reference) = dest_url.strip_prefix("article:") {
+            let Some(reference) = article_reference(reference) else {
+                return use_next();
+            };
+
+            return use_next_with(Next {
+                replacement: Some(Event::Html(
+                    format!(
+                        r#"<a href="article:{}"{}>"#,
+                        escape_attribute(&reference),
+                        title_attribute(title)
+                    )
+                    .into(),
+                )),
+                ignore: None,
+            });
+        }
+
+        let Some(host) = url_host(dest_url) else {
+            return use_next();
+        };
+        if self.site_host.as_ref() == Some(&host) {
+            return use_next();
+        }
+
+        let title = title_attribute(title);
+        let target = if self.new_tab {
+            r#" target="_blank""#
+        } else {
+            ""
+        };
+
+        // `</a>` is rendered as usual
+        use_next_with(Next {
+            replacement: Some(Event::Html(
+                format!(
+                    r#"<a href="{}"{title} rel="noopener noreferrer"{target}>"#,
+                    escape_attribute(dest_url)
+                )
+                .into(),
+            )),
+            ignore: None,
+        })
+    }
+
+    fn compose_output(self) -> Self::Output {}
+}
+
+fn title_attribute(title: &str) -> String {
+    if title.is_empty() {
+        String::new()
+    } else {
+        format!(r#" title="{}""#, escape_attribute(title))
+    }
+}
+
+// `2024/03/slug` or `2024/3/slug` as `202403/slug`, which is the group in the ids. The ones
+// already in it are as they are.
+fn article_reference(reference: &str) -> Option<String> {
+    let (year, rest) = reference.split_once('/')?;
+    if year.len() == 6 && year.chars().all(|char| char.is_ascii_digit()) {
+        return Some(reference.to_string());
+    }
+
+    let (month, slug) = rest.split_once('/')?;
+    let year = year.parse::<u32>().ok()?;
+    let month = month
+        .parse::<u8>()
+        .ok()
+        .filter(|month| (1..=12).contains(month))?;
+
+    Some(format!("{year:0>4}{month:0>2}/{slug}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::GenerationContext;
+    use pulldown_cmark::Options;
+
+    use crate::parse::full_service::MarkdownParser;
+
+    fn parse(markdown: &str, ctx: &GenerationContext) -> String {
+        MarkdownParser::parse(markdown, Options::all(), ctx, None).html
+    }
+
+    #[test]
+    fn links_to_other_hosts_are_external() {
+        let markdown = "[a](https://other.example/?a=1&b=2 \"Other\") [b](https://Example.com/x) [c](/x) [d](./y.html)";
+        let ctx = GenerationContext {
+            site_host: Some("example.com".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse(markdown, &ctx),
+            concat!(
+                r#"<p><a href="https://other.example/?a=1&amp;b=2" title="Other" rel="noopener noreferrer">a</a> "#,
+                r#"<a href="https://Example.com/x">b</a> <a href="/x">c</a> <a href="./y.html">d</a></p>"#,
+                "\n",
+            )
+        );
+
+        let html = parse(
+            "[b](https://example.com/x)",
+            &GenerationContext {
+                external_links_in_new_tab: true,
+                ..Default::default()
+            },
+        );
+        assert!(html.contains(r#"rel="noopener noreferrer" target="_blank">b</a>"#));
+    }
+
+    #[test]
+    fn wikilinks_are_left_by_the_slug() {
+        assert_eq!(
+            parse(
+                "[[03_hello]] and [[202501/04_bye#end|the *last* one]]",
+                &Default::default()
+            ),
+            concat!(
+                r#"<p><a href="wikilink:03_hello" class="wikilink">03_hello</a> and "#,
+                r#"<a href="wikilink:202501/04_bye#end" class="wikilink">the <em>last</em> one</a></p>"#,
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn article_links_are_left_by_the_group() {
+        assert_eq!(
+            parse(
+                "[a](article:2024/3/03_hello#top \"Hello\") [b](article:202403/série/01_intro) [c](article:hello)",
+                &Default::default()
+            ),
+            concat!(
+                r#"<p><a href="article:202403/03_hello#top" title="Hello">a</a> "#,
+                r#"<a href="article:202403/série/01_intro">b</a> <a href="article:hello">c</a></p>"#,
+                "\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use latex2mathml::{DisplayStyle, latex_to_mathml};
+use loss72_platemaker_core::{log, util::escape_html};
+use pulldown_cmark::Event;
+
+use crate::parse::control::{EventProcessControl, use_html, use_next};
+
+use super::SubParser;
+
+#[derive(Default, Debug)]
+pub struct MathSubParser {
+    mathml: bool,
+}
+
+impl MathSubParser {
+    pub fn new(mathml: bool) -> Self {
+        Self { mathml }
+    }
+}
+
+impl<'p> SubParser<'p> for MathSubParser {
+    type Output = ();
+
+    fn receive_event(&mut self, event: &Event<'p>) -> EventProcessControl<'p> {
+        match event {
+            Event::InlineMath(tex) => use_html(self.render(tex, DisplayStyle::Inline).into()),
+            Event::DisplayMath(tex) => use_html(self.render(tex, DisplayStyle::Block).into()),
+            _ => use_next(),
+        }
+    }
+
+    fn compose_output(self) -> Self::Output {}
+}
+
+impl MathSubParser {
+    fn render(&self, tex: &str, display: DisplayStyle) -> String {
+        // Display math is out of the paragraphs, on its own line as the other blocks
+        let (tag, end) = match display {
+            DisplayStyle::Inline => ("span", ""),
+            DisplayStyle::Block => ("div", "\n"),
+        };
+
+        // The raw TeX is kept for client-side renderers, also when MathML could not be made
+        let mathml = self
+            .mathml
+            .then(|| latex_to_mathml(tex, display))
+            .and_then(|mathml| {
+                mathml
+                    .inspect_err(|e| log!(warn: "`{}` could not be rendered to MathML: {}", tex, e))
+                    .ok()
+            });
+
+        match mathml {
+            Some(mathml) => format!(r#"<{tag} class="math">{mathml}</{tag}>{end}"#),
+            None => format!(r#"<{tag} class="math">{}</{tag}>{end}"#, escape_html(tex)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::GenerationContext;
+    use pulldown_cmark::Options;
+
+    use crate::parse::full_service::MarkdownParser;
+
+    fn parse(markdown: &str, mathml: bool) -> (String, Option<usize>) {
+        let ctx = GenerationContext {
+            mathml,
+            ..Default::default()
+        };
+        let parsed = MarkdownParser::parse(markdown, Options::all(), &ctx, None);
+
+        (parsed.html, parsed.unterminated_math)
+    }
+
+    #[test]
+    fn wraps_math_with_raw_tex() {
+        assert_eq!(
+            parse("$a < b$ costs \\$5 `$code$`\n\n$$\\sum_i i$$\n", false),
+            (
+                concat!(
+                    r#"<p><span class="math">a &lt; b</span> costs $5 <code>$code$</code></p>"#,
+                    "\n",
+                    r#"<div class="math">\sum_i i</div>"#,
+                    "\n",
+                )
+                .to_string(),
+                None
+            )
+        );
+
+        assert_eq!(
+            parse("before\n$$x$$\nafter  \n$$y$$\n", false).0,
+            concat!(
+                "<p>before</p>\n",
+                r#"<div class="math">x</div>"#,
+                "\n<p>after</p>\n",
+                r#"<div class="math">y</div>"#,
+                "\n",
+            )
+        );
+
+        let (html, _) = parse("$x$", true);
+        assert!(html.starts_with(r#"<p><span class="math"><math"#));
+    }
+
+    #[test]
+    fn reports_unterminated_math() {
+        assert_eq!(parse("fine $x$\n\nnot $x here\n", false).1, Some(3));
+        assert_eq!(parse("```\n$ echo\n```\n", false).1, None);
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use emojis::Emoji;
+use loss72_platemaker_core::{log, model::EmojiStyle};
 use loss72_platemaker_template::Placeholder;
 use pulldown_cmark::Event;
 use regex::Regex;
@@ -9,7 +10,6 @@ use crate::parse::control::{EventProcessControl, Ignore, Next, use_next, use_nex
 
 use super::SubParser;
 
-#[derive(Default)]
 pub struct TextParser {
     emoji: EmojiReplacer,
 }
@@ -32,18 +32,31 @@ impl<'p> SubParser<'p> for TextParser {
 }
 
 impl TextParser {
+    pub fn new(style: EmojiStyle) -> Self {
+        Self {
+            emoji: EmojiReplacer {
+                style,
+                discovered_emojis: HashMap::new(),
+            },
+        }
+    }
+
     fn ignore_if_softbreak(event: &Event) -> bool {
         event == &Event::SoftBreak
     }
 }
 
-#[derive(Default)]
 struct EmojiReplacer {
+    style: EmojiStyle,
     discovered_emojis: HashMap<String, String>,
 }
 
 impl EmojiReplacer {
     fn replace(&mut self, text: &str) -> String {
+        if self.style == EmojiStyle::Disabled {
+            return text.to_string();
+        }
+
         let emoji_extracted = Placeholder::from_strs(
             ":",
             ":",
@@ -59,10 +72,12 @@ impl EmojiReplacer {
             }
 
             let replace_to = match emojis::get_by_shortcode(&shortcode) {
+                Some(emoji) if self.style == EmojiStyle::Unicode => emoji.as_str().to_string(),
                 Some(emoji) => Self::emoji_to_html_tag(emoji),
+                // Left as it is, which may not be meant for an emoji like `12:user_id:34`
                 None => {
-                    println!("warning: emoji {shortcode} is not resolved");
-                    format!("<!-- Unresolved emoji --> :{shortcode}:")
+                    log!(step: "emoji {shortcode} is not resolved");
+                    format!(":{shortcode}:")
                 }
             };
 
@@ -91,3 +106,32 @@ impl EmojiReplacer {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{EmojiStyle, GenerationContext};
+    use pulldown_cmark::Options;
+
+    use crate::parse::full_service::MarkdownParser;
+
+    fn parse(markdown: &str, emoji: EmojiStyle) -> String {
+        let ctx = GenerationContext {
+            emoji,
+            ..Default::default()
+        };
+        MarkdownParser::parse(markdown, Options::all(), &ctx, None).html
+    }
+
+    #[test]
+    fn shortcodes_outside_of_code_are_converted() {
+        let markdown = "Launch :rocket: :no_such_emoji:\n\n`:rocket:`\n\n```\n:rocket:\n```\n";
+
+        let html = parse(markdown, EmojiStyle::Unicode);
+        assert!(html.contains("Launch 🚀 :no_such_emoji:"));
+        assert!(html.contains("<code>:rocket:</code>"));
+        assert_eq!(html.matches("🚀").count(), 1);
+
+        assert!(parse(markdown, EmojiStyle::Twemoji).contains(r#"alt="🚀""#));
+        assert!(!parse(markdown, EmojiStyle::Disabled).contains("🚀"));
+    }
+}
@@ -0,0 +1,64 @@
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+// GitHub style task lists, of which the items have `task-list-item` for the styles without the
+// bullets. The markers are only there with `task_lists` of the markdown options.
+pub fn mark_task_list_items(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    // Indices of the items not closed yet in the output, the innermost one at the last
+    let mut items = vec![];
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Item) => {
+                items.push(output.len());
+                output.push(event);
+            }
+            Event::End(TagEnd::Item) => {
+                items.pop();
+                output.push(event);
+            }
+            Event::TaskListMarker(checked) => {
+                // The marker comes first in the item, which is still its start
+                if let Some(&item) = items.last() {
+                    output[item] = Event::Html(r#"<li class="task-list-item">"#.into());
+                }
+
+                let checked = if checked { " checked" } else { "" };
+                output.push(Event::InlineHtml(
+                    format!(r#"<input type="checkbox" disabled{checked}> "#).into(),
+                ));
+            }
+            event => output.push(event),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::{Options, Parser, html::push_html};
+
+    use super::mark_task_list_items;
+
+    fn render(markdown: &str) -> String {
+        let events = Parser::new_ext(markdown, Options::ENABLE_TASKLISTS).collect();
+        let mut html = String::new();
+        push_html(&mut html, mark_task_list_items(events).into_iter());
+        html
+    }
+
+    #[test]
+    fn task_items_are_marked_in_the_nested_lists() {
+        assert_eq!(
+            render("- [ ] todo\n  - [x] done\n  - plain\n- plain\n"),
+            concat!(
+                "<ul>\n",
+                r#"<li class="task-list-item"><input type="checkbox" disabled> todo"#,
+                "\n<ul>\n",
+                r#"<li class="task-list-item"><input type="checkbox" disabled checked> done</li>"#,
+                "\n<li>plain</li>\n</ul>\n</li>\n<li>plain</li>\n</ul>\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+// `[day]_slug.md`, or `slug.md` for the articles without the day.
+// Names led by a numeric prefix which is not a day, like `03-2_slug.md`, are not articles.
+// The extension is checked against the markdown extensions apart from it.
+pub const DEFAULT_FILENAME_PATTERN: &str =
+    r"^(?<slug>(?<day>[0-9]+)_[^.]*|[^._]*|(?:[^0-9._][^._]*)?_[^.]*)\.[^.]+$";
+
+pub const DEFAULT_MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+const REQUIRED_CAPTURES: [&str; 1] = ["slug"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum FilenamePatternError {
+    #[error("The article filename pattern `{0}` is not a valid regex:\n{1}")]
+    InvalidRegex(String, regex::Error),
+
+    #[error("The article filename pattern `{0}` should have the named capture `(?<{1}>...)`.")]
+    MissingCapture(String, String),
+}
+
+// Regex for the names of the article files, with the named captures `slug` and optionally
+// `day`. The files not matching to it, or without any of the extensions, are not articles.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FilenamePattern {
+    regex: Regex,
+    // compared ignoring the case, without the leading dot
+    extensions: Vec<String>,
+}
+
+impl Default for FilenamePattern {
+    fn default() -> Self {
+        FilenamePattern {
+            regex: Regex::new(DEFAULT_FILENAME_PATTERN).expect("default pattern to be valid"),
+            extensions: default_extensions(),
+        }
+    }
+}
+
+fn default_extensions() -> Vec<String> {
+    DEFAULT_MARKDOWN_EXTENSIONS.map(str::to_string).to_vec()
+}
+
+impl TryFrom<String> for FilenamePattern {
+    type Error = FilenamePatternError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let regex =
+            Regex::new(&value).map_err(|e| FilenamePatternError::InvalidRegex(value.clone(), e))?;
+
+        if let Some(missing) = REQUIRED_CAPTURES
+            .iter()
+            .find(|required| !regex.capture_names().any(|name| name == Some(**required)))
+        {
+            return Err(FilenamePatternError::MissingCapture(
+                value,
+                missing.to_string(),
+            ));
+        }
+
+        Ok(FilenamePattern {
+            regex,
+            extensions: default_extensions(),
+        })
+    }
+}
+
+impl FilenamePattern {
+    pub fn with_extensions(self, extensions: &[String]) -> Self {
+        FilenamePattern {
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_string())
+                .collect(),
+            ..self
+        }
+    }
+
+    // Whether the file has one of the markdown extensions, whichever name it has
+    pub fn is_markdown(&self, file_name: &str) -> bool {
+        Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                self.extensions
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(extension))
+            })
+    }
+
+    // The slug and the day, which should be a number if it is captured
+    pub fn parse<'name>(&self, file_name: &'name str) -> Option<(&'name str, Option<u8>)> {
+        if !self.is_markdown(file_name) {
+            return None;
+        }
+
+        let captures = self.regex.captures(file_name)?;
+        let slug = captures.name("slug")?.as_str();
+
+        let day = match captures.name("day") {
+            Some(day) => Some(day.as_str().parse::<u8>().ok()?),
+            None => None,
+        };
+
+        Some((slug, day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilenamePattern, FilenamePatternError};
+
+    #[test]
+    fn default_pattern_matches_to_day_and_slug() {
+        let pattern = FilenamePattern::default();
+
+        assert_eq!(pattern.parse("03_hello.md"), Some(("03_hello", Some(3))));
+        assert_eq!(
+            pattern.parse("monthly_notes.md"),
+            Some(("monthly_notes", None))
+        );
+        assert_eq!(pattern.parse("notes.md"), Some(("notes", None)));
+        assert_eq!(pattern.parse("03-2_hello.md"), None);
+        assert_eq!(pattern.parse("300_hello.md"), None);
+        assert_eq!(pattern.parse("03_hello.en.md"), None);
+        assert_eq!(pattern.parse("03_hello.txt"), None);
+    }
+
+    #[test]
+    fn extensions_are_matched_ignoring_the_case() {
+        let pattern = FilenamePattern::default();
+        assert_eq!(pattern.parse("03_hello.MD"), Some(("03_hello", Some(3))));
+        assert_eq!(
+            pattern.parse("03_hello.markdown"),
+            Some(("03_hello", Some(3)))
+        );
+
+        let pattern = pattern.with_extensions(&[".mdx".to_string()]);
+        assert_eq!(pattern.parse("notes.mdx"), Some(("notes", None)));
+        assert_eq!(pattern.parse("notes.md"), None);
+        assert!(pattern.is_markdown("03-2_notes.MDX"));
+        assert!(!pattern.is_markdown("pic.png"));
+    }
+
+    #[test]
+    fn custom_pattern_needs_slug() {
+        let pattern =
+            FilenamePattern::try_from(r"^(?<slug>.+)-(?<day>[0-9]{2})\.markdown$".to_string())
+                .expect("pattern to be valid");
+        assert_eq!(pattern.parse("hello-03.markdown"), Some(("hello", Some(3))));
+        assert_eq!(pattern.parse("03_hello.md"), None);
+
+        assert!(matches!(
+            FilenamePattern::try_from(r"^(?<day>[0-9]+)_.*\.md$".to_string()),
+            Err(FilenamePatternError::MissingCapture(_, capture)) if capture == "slug"
+        ));
+        assert!(matches!(
+            FilenamePattern::try_from(r"^(?<slug>.*\.md$".to_string()),
+            Err(FilenamePatternError::InvalidRegex(..))
+        ));
+    }
+}
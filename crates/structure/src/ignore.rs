@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use loss72_platemaker_core::fs::{Directory, FSNode};
+
+pub const IGNORE_FILE: &str = ".platemakerignore";
+
+// Patterns in `.platemakerignore` at the article root, written in the gitignore syntax
+#[derive(Clone, Debug, Default)]
+pub struct IgnorePatterns {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Clone, Debug)]
+struct Pattern {
+    segments: Vec<String>,
+    negated: bool,
+    directory_only: bool,
+}
+
+impl IgnorePatterns {
+    // Nothing is ignored without the file
+    pub fn load(root: &Directory) -> std::io::Result<Self> {
+        match std::fs::read_to_string(root.path().join(IGNORE_FILE)) {
+            Ok(content) => Ok(Self::parse(&content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        Self {
+            patterns: content.lines().filter_map(Pattern::parse).collect(),
+        }
+    }
+
+    // `relative` is the path from the article root. The ones in ignored directories are
+    // ignored too, and cannot be included again with negations as git does.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let components = components(relative);
+
+        (1..=components.len())
+            .any(|length| self.matches(&components[..length], length < components.len() || is_dir))
+    }
+
+    fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(components, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    // Walks the tree under `dir` without descending into the ignored directories
+    pub(crate) fn walk(&self, dir: &Directory, root: &Directory) -> std::io::Result<Vec<FSNode>> {
+        let mut nodes = vec![];
+
+        for node in dir.try_iter_content()? {
+            let node = node?;
+            let relative = node.path().strip_prefix(root.path()).unwrap_or(node.path());
+
+            if self.matches(&components(relative), node.directory().is_some()) {
+                continue;
+            }
+
+            if let Some(child) = node.directory() {
+                let children = self.walk(child, root)?;
+                nodes.push(node);
+                nodes.extend(children);
+            } else {
+                nodes.push(node);
+            }
+        }
+
+        Ok(nodes)
+    }
+}
+
+fn components(path: &Path) -> Vec<&str> {
+    path.iter()
+        .filter_map(|component| component.to_str())
+        .collect()
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+
+        let (directory_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        // Ones with a slash other than the trailing one are relative to the root,
+        // and the others match at any level
+        let anchored = line.contains('/');
+        let mut segments = line
+            .trim_start_matches('/')
+            .split('/')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if !anchored {
+            segments.insert(0, "**".to_string());
+        }
+
+        if segments.iter().all(String::is_empty) {
+            return None;
+        }
+
+        Some(Self {
+            segments,
+            negated,
+            directory_only,
+        })
+    }
+
+    fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+        (is_dir || !self.directory_only) && match_segments(&self.segments, components)
+    }
+}
+
+fn match_segments(segments: &[String], components: &[&str]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        // Trailing `**` matches everything inside, but not the directory itself
+        Some((segment, [])) if segment == "**" => !components.is_empty(),
+        Some((segment, rest)) if segment == "**" => {
+            (0..=components.len()).any(|skip| match_segments(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => components.split_first().is_some_and(|(component, others)| {
+            match_wildcard(
+                &segment.chars().collect::<Vec<_>>(),
+                &component.chars().collect::<Vec<_>>(),
+            ) && match_segments(rest, others)
+        }),
+    }
+}
+
+// `*`, `?` and `[...]` in a single path component
+fn match_wildcard(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| match_wildcard(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && match_wildcard(rest, &text[1..]),
+        Some(('[', rest)) => match (match_class(rest, text.first()), text.split_first()) {
+            (Some((true, after)), Some((_, text))) => match_wildcard(after, text),
+            (Some(_), _) => false,
+            // Unclosed brackets are taken literally
+            (None, Some(('[', text))) => match_wildcard(rest, text),
+            (None, _) => false,
+        },
+        Some(('\\', [escaped, rest @ ..])) => {
+            text.first() == Some(escaped) && match_wildcard(rest, &text[1..])
+        }
+        Some((char, rest)) => text.first() == Some(char) && match_wildcard(rest, &text[1..]),
+    }
+}
+
+// Whether `char` is in the class, and the pattern after the closing bracket
+fn match_class<'p>(class: &'p [char], char: Option<&char>) -> Option<(bool, &'p [char])> {
+    let (negated, class) = match class.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+
+    // `]` right after the opening bracket is a member
+    let end = class.iter().skip(1).position(|c| *c == ']')? + 1;
+    let (members, after) = (&class[..end], &class[end + 1..]);
+
+    let Some(char) = char else {
+        return Some((false, after));
+    };
+
+    let mut found = false;
+    let mut index = 0;
+    while index < members.len() {
+        if let [start, '-', end, ..] = members[index..] {
+            found |= (start..=end).contains(char);
+            index += 3;
+        } else {
+            found |= members[index] == *char;
+            index += 1;
+        }
+    }
+
+    Some((found != negated, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use loss72_platemaker_core::fs::Directory;
+
+    use super::IgnorePatterns;
+    use crate::{ContentDirectory, DEFAULT_ASSETS_DIR};
+
+    #[test]
+    fn matches_in_gitignore_syntax() {
+        let ignore = IgnorePatterns::parse(
+            "# notes\n*.txt\n!keep.txt\n/2025/1/_*.md\n**/wip/\ndrafts/**\n2024/[1-6]/\\#*\n",
+        );
+        let ignored = |path: &str| ignore.is_ignored(Path::new(path), false);
+
+        assert!(ignored("2025/1/notes.txt"));
+        assert!(!ignored("2025/1/keep.txt"));
+        assert!(ignored("2025/1/_template.md"));
+        assert!(!ignored("2025/2/_template.md"));
+        assert!(!ignored("2025/1/01_hello.md"));
+        assert!(ignored("2025/1/wip/01_hello.md"));
+        assert!(!ignored("2025/1/wip"));
+        assert!(ignore.is_ignored(Path::new("2025/1/wip"), true));
+        assert!(ignored("drafts/2025/1/01_hello.md"));
+        assert!(!ignore.is_ignored(Path::new("drafts"), true));
+        assert!(ignored("2024/3/#01_hash.md"));
+        assert!(!ignored("2024/7/#01_hash.md"));
+    }
+
+    #[test]
+    fn ignored_directories_are_not_descended_into() {
+        let root = std::env::temp_dir().join(format!("platemaker-ignore-{}", std::process::id()));
+        for (path, content) in [
+            (
+                ".platemakerignore",
+                "2025/2/\n!2025/2/01_kept.md\n2025/1/notes/\n_*.md\n",
+            ),
+            ("2025/1/01_hello.md", ""),
+            ("2025/1/_template.md", ""),
+            ("2025/1/notes/02_note.md", ""),
+            ("2025/2/01_kept.md", ""),
+            ("2025/3/01_spring.md", ""),
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().expect("path to have parent"))
+                .expect("temp dir to be writable");
+            std::fs::write(path, content).expect("temp dir to be writable");
+        }
+
+        let dir = Directory::new(&root).expect("temp dir to exist");
+        let content = ContentDirectory::new(&dir, &Default::default(), DEFAULT_ASSETS_DIR)
+            .expect("content to be scanned");
+
+        assert_eq!(
+            content
+                .article_group
+                .iter()
+                .map(|group| (group.year, group.month))
+                .collect::<Vec<_>>(),
+            [(2025, 1), (2025, 3)]
+        );
+
+        let ignore = IgnorePatterns::load(&dir).expect("ignore file to be read");
+        let walked = ignore.walk(&dir, &dir).expect("tree to be walked");
+        assert!(walked.iter().all(
+            |node| !node.path().starts_with(dir.path().join("2025/1/notes"))
+                && !node.path().starts_with(dir.path().join("2025/2"))
+        ));
+
+        let mut slugs = content
+            .markdown_files
+            .iter()
+            .map(|file| file.id.slug.as_str())
+            .collect::<Vec<_>>();
+        slugs.sort();
+        assert_eq!(slugs, ["01_hello", "01_spring"]);
+
+        std::fs::remove_dir_all(root).ok();
+    }
+}
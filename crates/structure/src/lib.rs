@@ -1,3 +1,5 @@
+pub mod filename;
+pub mod ignore;
 pub mod template;
 
 use std::{
@@ -7,8 +9,42 @@ use std::{
 
 use loss72_platemaker_core::{
     fs::{Directory, FSNode, File},
+    log,
     model::ArticleIdentifier,
+    util::{date_exists, slugify},
 };
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{filename::FilenamePattern, ignore::IgnorePatterns};
+
+// of the directories of the assets next to the articles, unless configured
+pub const DEFAULT_ASSETS_DIR: &str = "assets";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContentError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    // The paths relative to the content directory, of each id
+    #[error(
+        "Some articles have the same group and slug, and would overwrite each other:{}",
+        duplicates_list(.0)
+    )]
+    DuplicateArticles(Vec<(ArticleIdentifier, Vec<PathBuf>)>),
+}
+
+fn duplicates_list(duplicates: &[(ArticleIdentifier, Vec<PathBuf>)]) -> String {
+    duplicates
+        .iter()
+        .map(|(id, paths)| {
+            let paths = paths
+                .iter()
+                .map(|path| format!("./{}", path.display()))
+                .collect::<Vec<_>>();
+            format!("\n  {}/{}: {}", id.group, id.slug, paths.join(", "))
+        })
+        .collect()
+}
 
 pub struct ContentDirectory<'dir> {
     pub dir: &'dir Directory,
@@ -17,24 +53,43 @@ pub struct ContentDirectory<'dir> {
 }
 
 impl<'dir> ContentDirectory<'dir> {
-    pub fn new(dir: &'dir Directory) -> Result<Self, std::io::Error> {
-        let mut article_group = ArticleGroup::scan(dir)?;
+    pub fn new(
+        dir: &'dir Directory,
+        pattern: &FilenamePattern,
+        assets_dir: &str,
+    ) -> Result<Self, ContentError> {
+        let ignore = IgnorePatterns::load(dir)?;
+
+        let mut article_group = ArticleGroup::scan_ignoring(dir, &ignore)?;
+        for month_dir in ArticleGroup::invalid_month_dirs(dir, &ignore)? {
+            log!(warn: "Skipping ./{}, which is not a month", month_dir.display());
+        }
         article_group.sort();
         article_group.dedup();
 
+        // Including the ones in the subdirectories of the groups. The groups are walked in
+        // parallel, but collected in their order, so that the first error is the one of the
+        // earliest group.
         let markdown_files = article_group
-            .iter()
+            .par_iter()
             .map(|group| {
                 Directory::new(dir.path().join(group.group_dir_path()))
-                    .and_then(|dir| dir.try_iter_content()?.collect::<Result<Vec<_>, _>>())
+                    .and_then(|group_dir| ignore.walk(&group_dir, dir))
             })
+            .collect::<Vec<_>>()
+            .into_iter()
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
             .flatten()
             .filter_map(|node| node.into_file())
-            .filter_map(|file| ArticleFile::from_file(&file, dir))
+            .filter_map(|file| ArticleFile::from_file(&file, dir, pattern, assets_dir))
             .collect::<Vec<_>>();
 
+        let duplicates = ArticleFile::duplicates(&markdown_files);
+        if !duplicates.is_empty() {
+            return Err(ContentError::DuplicateArticles(duplicates));
+        }
+
         Ok(Self {
             dir,
             markdown_files,
@@ -47,13 +102,22 @@ impl<'dir> ContentDirectory<'dir> {
 pub struct ArticleGroup {
     pub year: u32,
     pub month: u8,
+    // as it is named in the article root, e.g. `2024/03` rather than `2024/3`
+    dir: PathBuf,
 }
 
 impl ArticleGroup {
+    // Skips the directories ignored in `.platemakerignore`
     pub fn scan(root: &Directory) -> std::io::Result<Vec<ArticleGroup>> {
-        Ok(root
-            .try_iter_tree()?
-            .collect::<Result<Vec<_>, _>>()?
+        Self::scan_ignoring(root, &IgnorePatterns::load(root)?)
+    }
+
+    fn scan_ignoring(
+        root: &Directory,
+        ignore: &IgnorePatterns,
+    ) -> std::io::Result<Vec<ArticleGroup>> {
+        Ok(ignore
+            .walk(root, root)?
             .into_iter()
             .filter_map(|node| node.into_directory())
             .filter_map(|dir| Self::from_path(dir.path().strip_prefix(root.path()).unwrap()))
@@ -62,10 +126,70 @@ impl ArticleGroup {
             .collect::<Vec<_>>())
     }
 
+    // `$year/$month` whose month is out of 1 to 12, which are not taken as the groups. The
+    // ignored ones are not either, but without the warnings.
+    fn invalid_month_dirs(
+        root: &Directory,
+        ignore: &IgnorePatterns,
+    ) -> std::io::Result<Vec<PathBuf>> {
+        let mut invalid = vec![];
+
+        for year_dir in root
+            .try_iter_content()?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter_map(FSNode::into_directory)
+        {
+            for month_dir in year_dir
+                .try_iter_content()?
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter_map(FSNode::into_directory)
+            {
+                let Ok(path) = month_dir.path().strip_prefix(root.path()) else {
+                    continue;
+                };
+                if ignore.is_ignored(path, true) {
+                    continue;
+                }
+                let mut components = path.iter().filter_map(|component| component.to_str());
+                let numeric = components
+                    .next()
+                    .is_some_and(|year| year.parse::<u32>().is_ok())
+                    && components
+                        .next()
+                        .is_some_and(|month| month.parse::<u8>().is_ok());
+
+                if numeric && Self::from_path(path).is_none() {
+                    invalid.push(path.to_path_buf());
+                }
+            }
+        }
+
+        Ok(invalid)
+    }
+
     pub fn group_dir_path(&self) -> PathBuf {
-        PathBuf::new()
-            .join(self.year.to_string())
-            .join(self.month.to_string())
+        self.dir.clone()
+    }
+
+    // Directories which hold the assets, at the top of the group and in its subdirectories
+    pub fn scan_asset_dirs(
+        &self,
+        root: &Directory,
+        assets_dir: &str,
+    ) -> std::io::Result<Vec<AssetRootDir>> {
+        let Some(group_dir) = root.get_child(self.group_dir_path()) else {
+            return Ok(vec![]);
+        };
+
+        Ok(group_dir?
+            .try_iter_tree()?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter_map(|node| node.into_directory())
+            .filter_map(|dir| AssetRootDir::from_dir(&dir, root, assets_dir))
+            .collect())
     }
 
     pub fn group_dir_flat_path(&self) -> PathBuf {
@@ -75,8 +199,12 @@ impl ArticleGroup {
     fn from_path(value: &Path) -> Option<(Self, Vec<String>)> {
         let mut components = value.iter().take(2).filter_map(|cmp| cmp.to_str());
 
-        let year = components.next()?.parse::<u32>().ok()?;
-        let month = components.next()?.parse::<u8>().ok()?;
+        let (year_dir, month_dir) = (components.next()?, components.next()?);
+        let year = year_dir.parse::<u32>().ok()?;
+        let month = month_dir
+            .parse::<u8>()
+            .ok()
+            .filter(|month| date_exists(year, *month, None))?;
 
         let suffix_components = value
             .iter()
@@ -84,7 +212,14 @@ impl ArticleGroup {
             .map(|cmp| cmp.to_str().map(|str| str.to_string()))
             .collect::<Option<Vec<_>>>()?;
 
-        Some((Self { year, month }, suffix_components))
+        Some((
+            Self {
+                year,
+                month,
+                dir: Path::new(year_dir).join(month_dir),
+            },
+            suffix_components,
+        ))
     }
 }
 
@@ -116,37 +251,102 @@ pub struct ArticleFile {
 }
 
 impl ArticleFile {
-    pub fn from_file(file: &File, root: &Directory) -> Option<Self> {
+    pub fn from_file(
+        file: &File,
+        root: &Directory,
+        pattern: &FilenamePattern,
+        assets_dir: &str,
+    ) -> Option<Self> {
         let file = ArticleGroupNode::from_node(file.clone().into(), root)?;
-
-        // matches to files in /path/to/root/[numeric]_*.md
-        let [first] = file.suffix_components.as_slice() else {
+        let id = Self::identifier(&file, pattern, assets_dir)?;
+
+        let (year, month, day) = id.date;
+        if !date_exists(year, month, day) {
+            log!(
+                warn: "Skipping ./{}, dated to the day {} which does not exist in {year}-{month:02}",
+                file.relative_path.display(),
+                day.unwrap_or_default()
+            );
             return None;
-        };
+        }
 
-        let slug_and_ext = first.split(".").collect::<Vec<_>>();
-        let [slug, "md"] = slug_and_ext.as_slice() else {
+        Some(Self { node: file, id })
+    }
+
+    // The ids with more than one file, like `03_hello.md` and `03_hello.MD`, or the names
+    // which the slugs turned the same. Their pages would be at the same path.
+    fn duplicates(files: &[ArticleFile]) -> Vec<(ArticleIdentifier, Vec<PathBuf>)> {
+        let mut ids: Vec<(ArticleIdentifier, Vec<PathBuf>)> = vec![];
+
+        for file in files {
+            match ids
+                .iter_mut()
+                .find(|(id, _)| (&id.group, &id.slug) == (&file.id.group, &file.id.slug))
+            {
+                Some((_, paths)) => paths.push(file.relative_path.clone()),
+                None => ids.push((file.id.clone(), vec![file.relative_path.clone()])),
+            }
+        }
+
+        ids.retain_mut(|(_, paths)| {
+            paths.sort();
+            paths.len() > 1
+        });
+        ids
+    }
+
+    // For the paths which may not exist anymore, e.g. removed ones
+    pub fn identify(
+        path: &Path,
+        root: &Directory,
+        pattern: &FilenamePattern,
+        assets_dir: &str,
+    ) -> Option<ArticleIdentifier> {
+        Self::identifier(
+            &ArticleGroupNode::from_node(FSNode::Unknown(path.to_path_buf()), root)?,
+            pattern,
+            assets_dir,
+        )
+        .filter(|id| {
+            let (year, month, day) = id.date;
+            date_exists(year, month, day)
+        })
+    }
+
+    fn identifier(
+        file: &ArticleGroupNode,
+        pattern: &FilenamePattern,
+        assets_dir: &str,
+    ) -> Option<ArticleIdentifier> {
+        // matches to files in /path/to/root/ named after the pattern, `[numeric]_*.md` by default.
+        // The subdirectories in the group, except for the assets, lead the slug.
+        let [subdirs @ .., first] = file.suffix_components.as_slice() else {
             return None;
         };
 
-        let day_and_rest = slug.split("_").collect::<Vec<_>>();
-        let [day, ..] = day_and_rest.as_slice() else {
+        if subdirs.iter().any(|dir| dir == assets_dir) {
             return None;
-        };
+        }
 
-        let day = day.parse::<u8>().ok()?;
+        // The subdirectories are slugified as the names are, and so are the ones of the assets
+        // copied next to the pages
+        let (slug, day) = pattern.parse(first)?;
+        let slug = subdir_slugs(subdirs)
+            .chain([slugify(slug)])
+            .collect::<Vec<_>>();
+        if slug.iter().any(String::is_empty) {
+            return None;
+        }
 
-        let id = ArticleIdentifier {
+        Some(ArticleIdentifier {
             group: file
                 .group
                 .group_dir_flat_path()
                 .to_string_lossy()
                 .to_string(),
-            slug: slug.to_string(),
+            slug: slug.join("/"),
             date: (file.group.year, file.group.month, day),
-        };
-
-        Some(Self { node: file, id })
+        })
     }
 
     pub fn file(&self) -> &File {
@@ -165,19 +365,47 @@ impl Deref for ArticleFile {
     }
 }
 
+// The subdirectories of the group leading the slugs of the articles in them, as they are in the urls
+fn subdir_slugs(subdirs: &[String]) -> impl Iterator<Item = String> {
+    subdirs.iter().map(|dir| slugify(dir))
+}
+
+// with the index of `assets_dir` in the components
 #[derive(Debug)]
-pub struct AssetFile(ArticleGroupNode);
+pub struct AssetFile(ArticleGroupNode, usize);
 
 impl AssetFile {
-    pub fn from_file(file: &File, root: &Directory) -> Option<Self> {
-        let file = ArticleGroupNode::from_node(file.clone().into(), root)?;
+    pub fn from_file(file: &File, root: &Directory, assets_dir: &str) -> Option<Self> {
+        Self::from_node(file.clone().into(), root, assets_dir)
+    }
 
-        // matches to files in /path/to/root/assets/(something)/
-        if matches!(file.suffix_components.as_slice(), [first, _, ..] if first == "assets") {
-            Some(Self(file))
-        } else {
-            None
-        }
+    // For the paths which may not exist anymore, e.g. removed ones.
+    // `file()` is not available on them.
+    pub fn from_removed_path(path: &Path, root: &Directory, assets_dir: &str) -> Option<Self> {
+        Self::from_node(FSNode::Unknown(path.to_path_buf()), root, assets_dir)
+    }
+
+    fn from_node(node: FSNode, root: &Directory, assets_dir: &str) -> Option<Self> {
+        let file = ArticleGroupNode::from_node(node, root)?;
+
+        // matches to files in /path/to/root/[subdirectories/]{assets_dir}/(something)/
+        let [dirs @ .., _] = file.suffix_components.as_slice() else {
+            return None;
+        };
+
+        let index = dirs.iter().position(|dir| dir == assets_dir)?;
+        Some(Self(file, index))
+    }
+
+    // Relative to the destination, where the asset is copied to under `base`, the directory of
+    // the articles. The subdirectories of the articles are slugified as their slugs are.
+    pub fn destination_path(&self, base: &Path) -> PathBuf {
+        let (subdirs, assets) = self.suffix_components.split_at(self.1);
+
+        subdir_slugs(subdirs).chain(assets.iter().cloned()).fold(
+            base.join(self.group.group_dir_flat_path()),
+            |path, component| path.join(component),
+        )
     }
 
     pub fn file(&self) -> &File {
@@ -199,21 +427,34 @@ impl Deref for AssetFile {
 pub struct AssetRootDir(ArticleGroupNode);
 
 impl AssetRootDir {
-    pub fn from_dir(dir: &Directory, root: &Directory) -> Option<Self> {
+    pub fn from_dir(dir: &Directory, root: &Directory, assets_dir: &str) -> Option<Self> {
         let dir = ArticleGroupNode::from_node(dir.clone().into(), root)?;
 
-        if dir.suffix_components.len() == 1
-            && dir
-                .suffix_components
-                .first()
-                .is_some_and(|folder| folder == "assets")
-        {
-            Some(Self(dir))
-        } else {
-            None
+        // `assets_dir` in the group or its subdirectories, but not the ones inside other assets
+        match dir.suffix_components.as_slice() {
+            [subdirs @ .., last]
+                if last == assets_dir && !subdirs.iter().any(|dir| dir == assets_dir) =>
+            {
+                Some(Self(dir))
+            }
+            _ => None,
         }
     }
 
+    // Relative to the destination, where the assets are copied to under `base`, the directory of
+    // the articles. The subdirectories of the articles are slugified as their slugs are.
+    pub fn destination_path(&self, base: &Path) -> PathBuf {
+        let (assets_dir, subdirs) = self
+            .suffix_components
+            .split_last()
+            .expect("Components to end with `assets_dir` as `from_dir` initializes");
+
+        subdir_slugs(subdirs).chain([assets_dir.clone()]).fold(
+            base.join(self.group.group_dir_flat_path()),
+            |path, component| path.join(component),
+        )
+    }
+
     pub fn directory(&self) -> &Directory {
         self.node
             .directory()
@@ -228,3 +469,239 @@ impl Deref for AssetRootDir {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use loss72_platemaker_core::fs::{Directory, FSNode};
+
+    use super::{
+        ArticleFile, ArticleGroupNode, AssetFile, ContentDirectory, ContentError,
+        DEFAULT_ASSETS_DIR,
+    };
+
+    #[test]
+    fn day_prefix_is_optional() {
+        let root = Directory::new_unchecked("/site/md");
+        let identify = |path: &str| {
+            ArticleFile::identify(
+                &Path::new("/site/md").join(path),
+                &root,
+                &Default::default(),
+                DEFAULT_ASSETS_DIR,
+            )
+        };
+
+        let dated = identify("2025/1/03_hello.md").expect("article to be identified");
+        assert_eq!(
+            (dated.slug.as_str(), dated.date),
+            ("03_hello", (2025, 1, Some(3)))
+        );
+
+        let day_less = identify("2025/1/monthly_notes.md").expect("article to be identified");
+        assert_eq!(
+            (
+                day_less.group.as_str(),
+                day_less.slug.as_str(),
+                day_less.date
+            ),
+            ("202501", "monthly_notes", (2025, 1, None))
+        );
+        assert!(day_less < dated);
+
+        assert!(identify("2025/1/03-2_hello.md").is_none());
+        assert!(identify("2025/1/hello.txt").is_none());
+
+        assert!(identify("2024/2/29_leap.md").is_some());
+        assert!(identify("2025/2/29_leap.md").is_none());
+        assert!(identify("2024/13/45_x.md").is_none());
+        assert!(identify("2024/13/notes.md").is_none());
+        assert!(identify("2024/0/notes.md").is_none());
+    }
+
+    #[test]
+    fn unsafe_slugs_are_normalized() {
+        let root = Directory::new_unchecked("/site/md");
+        let slug = |path: &str| {
+            ArticleFile::identify(
+                &Path::new("/site/md").join(path),
+                &root,
+                &Default::default(),
+                DEFAULT_ASSETS_DIR,
+            )
+            .map(|id| id.slug)
+        };
+
+        assert_eq!(
+            slug("2025/1/03_Café au lait.md").as_deref(),
+            Some("03_Cafe-au-lait")
+        );
+        assert_eq!(slug("2025/1/série/04_#1.md").as_deref(), Some("serie/04_1"));
+        assert_eq!(slug("2025/1/03_hello.md").as_deref(), Some("03_hello"));
+    }
+
+    #[test]
+    fn duplicate_ids_are_errors() {
+        let root =
+            std::env::temp_dir().join(format!("platemaker-duplicates-{}", std::process::id()));
+        for path in [
+            "2025/1/03_hello.md",
+            "2025/01/03_hello.MD",
+            "2025/1/04_once.md",
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().expect("path to have parent"))
+                .expect("temp dir to be writable");
+            std::fs::write(path, "").expect("temp dir to be writable");
+        }
+
+        let dir = Directory::new(&root).expect("temp dir to exist");
+        let result = ContentDirectory::new(&dir, &Default::default(), DEFAULT_ASSETS_DIR);
+        std::fs::remove_dir_all(&root).ok();
+
+        let Err(ContentError::DuplicateArticles(duplicates)) = result else {
+            panic!("duplicates to be an error");
+        };
+        assert_eq!(
+            duplicates
+                .iter()
+                .map(|(id, paths)| (id.slug.as_str(), paths.clone()))
+                .collect::<Vec<_>>(),
+            [(
+                "03_hello",
+                vec![
+                    Path::new("2025/01/03_hello.MD").to_path_buf(),
+                    Path::new("2025/1/03_hello.md").to_path_buf()
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn ignored_month_dirs_are_not_invalid() {
+        let root =
+            std::env::temp_dir().join(format!("platemaker-month-dirs-{}", std::process::id()));
+        for path in ["2025/13", "2025/14", "2025/1"] {
+            std::fs::create_dir_all(root.join(path)).expect("temp dir to be writable");
+        }
+        std::fs::write(root.join(super::ignore::IGNORE_FILE), "2025/14/\n")
+            .expect("temp dir to be writable");
+
+        let dir = Directory::new(&root).expect("temp dir to exist");
+        let ignore = super::ignore::IgnorePatterns::load(&dir).expect("ignore file to be readable");
+        let invalid = super::ArticleGroup::invalid_month_dirs(&dir, &ignore);
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(
+            invalid.expect("temp dir to be readable"),
+            [Path::new("2025/13").to_path_buf()]
+        );
+    }
+
+    #[test]
+    fn subdirectories_lead_the_slug() {
+        let root = Directory::new_unchecked("/site/md");
+        let path = Path::new("/site/md/2024/03/series-a/01_intro.md");
+
+        let id = ArticleFile::identify(path, &root, &Default::default(), DEFAULT_ASSETS_DIR)
+            .expect("article to be identified");
+        assert_eq!(id.group, "202403");
+        assert_eq!(id.slug, "series-a/01_intro");
+        assert_eq!(id.date, (2024, 3, Some(1)));
+
+        let node = ArticleGroupNode::from_node(FSNode::Unknown(path.to_path_buf()), &root)
+            .expect("path to be in a group");
+        assert_eq!(
+            node.suffix_components.iter().fold(
+                root.path().join(node.group.group_dir_path()),
+                |path, component| { path.join(component) }
+            ),
+            path
+        );
+
+        let asset = AssetFile::from_removed_path(
+            Path::new("/site/md/2024/03/series-a/assets/pic.png"),
+            &root,
+            DEFAULT_ASSETS_DIR,
+        )
+        .expect("asset to be identified");
+        assert_eq!(
+            asset.destination_path(Path::new("articles")),
+            Path::new("articles/202403/series-a/assets/pic.png")
+        );
+
+        assert!(
+            ArticleFile::identify(
+                Path::new("/site/md/2024/03/assets/01_note.md"),
+                &root,
+                &Default::default(),
+                DEFAULT_ASSETS_DIR
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn subdirectories_are_slugified_with_their_assets() {
+        let root = Directory::new_unchecked("/site/md");
+        let id = ArticleFile::identify(
+            Path::new("/site/md/2024/03/My Notes/a#b/01_intro.md"),
+            &root,
+            &Default::default(),
+            DEFAULT_ASSETS_DIR,
+        )
+        .expect("article to be identified");
+        assert_eq!(id.slug, "My-Notes/a-b/01_intro");
+
+        let asset = AssetFile::from_removed_path(
+            Path::new("/site/md/2024/03/My Notes/a#b/assets/My Pic.png"),
+            &root,
+            DEFAULT_ASSETS_DIR,
+        )
+        .expect("asset to be identified");
+        assert_eq!(
+            asset.destination_path(Path::new("articles")),
+            Path::new("articles/202403/My-Notes/a-b/assets/My Pic.png")
+        );
+
+        assert!(
+            ArticleFile::identify(
+                Path::new("/site/md/2024/03/!!/01_intro.md"),
+                &root,
+                &Default::default(),
+                DEFAULT_ASSETS_DIR,
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn assets_are_in_the_configured_directory() {
+        let root = Directory::new_unchecked("/site/md");
+        let asset = |path: &str| {
+            AssetFile::from_removed_path(&Path::new("/site/md").join(path), &root, "media")
+                .map(|asset| asset.destination_path(Path::new("articles")))
+        };
+
+        assert_eq!(
+            asset("2024/03/media/pic.png").as_deref(),
+            Some(Path::new("articles/202403/media/pic.png"))
+        );
+        assert_eq!(asset("2024/03/assets/pic.png"), None);
+
+        let identify = |path: &str| {
+            ArticleFile::identify(
+                &Path::new("/site/md").join(path),
+                &root,
+                &Default::default(),
+                "media",
+            )
+        };
+        assert!(identify("2024/03/media/01_note.md").is_none());
+        assert_eq!(
+            identify("2024/03/assets/01_note.md").map(|id| id.slug),
+            Some("assets/01_note".to_string())
+        );
+    }
+}
@@ -1,17 +1,65 @@
 use std::path::{Path, PathBuf};
 
+use loss72_platemaker_core::fs::{Directory, FSNode};
+
 pub const ARTICLE_TEMPLATE: &str = "_article.html";
 pub const INDEX_TEMPLATE: &str = "_index.html";
 pub const INDEX_LIST_TEMPLATE: &str = "_index-list.html";
+pub const TAG_TEMPLATE: &str = "_tag.html";
+pub const ARCHIVE_TEMPLATE: &str = "_archive.html";
+pub const GROUP_TEMPLATE: &str = "_group.html";
+pub const SERIES_TEMPLATE: &str = "_series.html";
 
-pub const TEMPLATE_FILES: [&str; 3] = [ARTICLE_TEMPLATE, INDEX_TEMPLATE, INDEX_LIST_TEMPLATE];
+pub const TEMPLATE_FILES: [&str; 7] = [
+    ARTICLE_TEMPLATE,
+    INDEX_TEMPLATE,
+    INDEX_LIST_TEMPLATE,
+    TAG_TEMPLATE,
+    ARCHIVE_TEMPLATE,
+    GROUP_TEMPLATE,
+    SERIES_TEMPLATE,
+];
 
-pub fn template_file_paths() -> [PathBuf; 3] {
-    TEMPLATE_FILES.map(PathBuf::from)
+// The ones in `TEMPLATE_FILES`, and the partials like `_header.html` which they include
+pub fn is_template_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|file| file.to_str())
+        .is_some_and(|file| file.starts_with('_') && file.ends_with(".html"))
 }
 
-pub fn is_template_file(path: &Path) -> bool {
-    TEMPLATE_FILES
-        .iter()
-        .any(|template| path.file_name().is_some_and(|file| &file == template))
+// Relative to the template directory, sorted
+pub fn template_files(template_dir: &Directory) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = template_dir
+        .try_iter_tree()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+        .filter_map(|file| {
+            file.path()
+                .strip_prefix(template_dir.path())
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .filter(|path| is_template_file(path))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    Ok(files)
+}
+
+pub fn template_pages(template_dir: &Directory) -> std::io::Result<Vec<PathBuf>> {
+    Ok(template_dir
+        .try_iter_tree()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+        .filter_map(|file| {
+            file.path()
+                .strip_prefix(template_dir.path())
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .filter(|path| !is_template_file(path))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect())
 }
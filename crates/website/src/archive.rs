@@ -0,0 +1,257 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use loss72_platemaker_construct::ConstructFile;
+use loss72_platemaker_core::{log, model::GenerationContext};
+use loss72_platemaker_template::Placeholder;
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError,
+    articles::{ArticlePage, listing_variables, render_article_list},
+    groups::group_url,
+};
+
+pub struct ArchivePage {
+    pub html: String,
+    pub path: PathBuf,
+}
+
+impl<'p> From<&'p ArchivePage> for ConstructFile<'p> {
+    fn from(value: &'p ArchivePage) -> Self {
+        ConstructFile {
+            path: &value.path,
+            content: &value.html,
+        }
+    }
+}
+
+pub fn generate_archive_pages(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[ArticlePage],
+    ctx: &GenerationContext,
+) -> OutputResult<Vec<ArchivePage>> {
+    if articles.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let Some(template) = &html_templates.archive else {
+        log!(step: "_archive.html is not found, skipping archive pages");
+        return Ok(vec![]);
+    };
+
+    // year -> month -> articles, only months which have any articles are present
+    let mut years = BTreeMap::<u32, BTreeMap<u8, Vec<ArticlePage>>>::new();
+    for page in articles {
+        let (year, month, _) = page.article.id.date;
+        years
+            .entry(year)
+            .or_default()
+            .entry(month)
+            .or_default()
+            .push(page.clone());
+    }
+
+    for months in years.values_mut() {
+        for pages in months.values_mut() {
+            pages.sort_by(|left, right| left.article.id.cmp(&right.article.id).reverse());
+        }
+    }
+
+    log!(section: "Generating HTML for archive pages of {} years", years.len());
+
+    let placeholder = Placeholder::from_strs("${", "}", None)
+        .expect("Regex is validated to include the capture group");
+
+    let render =
+        |title: String, year: u32, month: Option<u8>, months: String, pages: &[ArticlePage]| {
+            let default_url = archive_url(year, month);
+            let canonical = html_templates.canonical_link(&html_templates.page_url(&default_url));
+
+            let mut placeholder_contents = HashMap::from([
+                ("title", title),
+                ("year", year.to_string()),
+                (
+                    "month",
+                    month.map(|month| month.to_string()).unwrap_or_default(),
+                ),
+                (
+                    "MM",
+                    month.map(|month| format!("{month:02}")).unwrap_or_default(),
+                ),
+                ("months", months),
+                ("articles", render_article_list(html_templates, pages, ctx)?),
+                ("style", html_templates.index_style.clone()),
+                ("canonical", canonical),
+            ]);
+            placeholder_contents.extend(listing_variables(ctx));
+
+            let html = placeholder
+                .partially_fill_placeholders(template, |name| {
+                    placeholder_contents
+                        .get(name)
+                        .cloned()
+                        .or_else(|| html_templates.site.get(name))
+                })
+                .map_err(WebsiteGenerationError::InvalidPlaceholder)?;
+
+            Ok::<_, WebsiteGenerationError>(html_templates.place_page(&html, &default_url))
+        };
+
+    let mut pages = vec![];
+    for (year, months) in years.iter().rev() {
+        let month_links = months
+            .iter()
+            .rev()
+            .map(|(month, pages)| {
+                format!(
+                    r#"<li><a href="{}">{year}/{month:02}</a> ({}){}</li>"#,
+                    html_templates.page_url(&archive_url(*year, Some(*month))),
+                    pages.len(),
+                    group_links(html_templates, pages)
+                )
+            })
+            .collect::<String>();
+
+        let year_articles = months.values().rev().flatten().cloned().collect::<Vec<_>>();
+        let (html, path) = render(
+            year.to_string(),
+            *year,
+            None,
+            format!(r#"<ul class="archive-months">{month_links}</ul>"#),
+            &year_articles,
+        )?;
+        pages.push(ArchivePage { html, path });
+
+        for (month, month_articles) in months.iter().rev() {
+            let (html, path) = render(
+                format!("{year}/{month:02}"),
+                *year,
+                Some(*month),
+                String::new(),
+                month_articles,
+            )?;
+            pages.push(ArchivePage { html, path });
+        }
+    }
+
+    Ok(pages)
+}
+
+// to the index pages of the groups in the month, which are only there with `_group.html`
+fn group_links(html_templates: &WebPageHtmlTemplates, pages: &[ArticlePage]) -> String {
+    if html_templates.group.is_none() {
+        return String::new();
+    }
+
+    let groups = pages
+        .iter()
+        .map(|page| (page.article.id.group.as_str(), &page.article.id))
+        .collect::<BTreeMap<_, _>>();
+
+    groups
+        .into_iter()
+        .rev()
+        .map(|(group, id)| {
+            format!(
+                r#" <a class="archive-group" href="{}">{group}</a>"#,
+                html_templates.page_url(&group_url(html_templates, id))
+            )
+        })
+        .collect()
+}
+
+pub fn archive_url(year: u32, month: Option<u8>) -> String {
+    match month {
+        Some(month) => format!("/archive/{year}/{month:02}/"),
+        None => format!("/archive/{year}/"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{
+        Article, ArticleIdentifier, ArticleMetadata, GenerationContext,
+    };
+
+    use super::generate_archive_pages;
+    use crate::{WebPageHtmlTemplates, generate_article_pages};
+
+    fn article((year, month, day): (u32, u8, u8)) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: format!("{year}{month:02}"),
+                slug: format!("{day:02}_article"),
+                date: (year, month, Some(day)),
+            },
+            metadata: ArticleMetadata {
+                title: format!("Article {year}/{month}/{day}"),
+                brief: "brief".to_string(),
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn archive_pages_only_cover_months_with_articles() {
+        let mut templates = WebPageHtmlTemplates {
+            index_list: "[${title}]".to_string(),
+            archive: Some("${title}|${months}|${articles}".to_string()),
+            ..Default::default()
+        };
+        let ctx = GenerationContext::default();
+        let articles = [(2024, 12, 24), (2025, 1, 3), (2025, 3, 1), (2025, 3, 9)].map(article);
+        let pages = generate_article_pages(&templates, &articles, &ctx).expect("article pages");
+
+        let archive = generate_archive_pages(&templates, &pages, &ctx)
+            .expect("archive pages")
+            .into_iter()
+            .map(|page| (page.path.to_string_lossy().to_string(), page.html))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            archive,
+            [
+                (
+                    "archive/2025/index.html",
+                    concat!(
+                        r#"2025|<ul class="archive-months">"#,
+                        r#"<li><a href="/archive/2025/03/">2025/03</a> (2)</li>"#,
+                        r#"<li><a href="/archive/2025/01/">2025/01</a> (1)</li></ul>"#,
+                        "|[Article 2025/3/9][Article 2025/3/1][Article 2025/1/3]",
+                    )
+                ),
+                (
+                    "archive/2025/03/index.html",
+                    "2025/03||[Article 2025/3/9][Article 2025/3/1]"
+                ),
+                ("archive/2025/01/index.html", "2025/01||[Article 2025/1/3]"),
+                (
+                    "archive/2024/index.html",
+                    concat!(
+                        r#"2024|<ul class="archive-months">"#,
+                        r#"<li><a href="/archive/2024/12/">2024/12</a> (1)</li></ul>"#,
+                        "|[Article 2024/12/24]",
+                    )
+                ),
+                (
+                    "archive/2024/12/index.html",
+                    "2024/12||[Article 2024/12/24]"
+                ),
+            ]
+            .map(|(path, html)| (path.to_string(), html.to_string()))
+        );
+
+        templates.group = Some(String::new());
+        let archive = generate_archive_pages(&templates, &pages, &ctx).expect("archive pages");
+        assert!(archive[0].html.contains(concat!(
+            r#"<li><a href="/archive/2025/01/">2025/01</a> (1)"#,
+            r#" <a class="archive-group" href="/articles/202501/">202501</a></li>"#,
+        )));
+    }
+}
@@ -1,13 +1,27 @@
 use loss72_platemaker_construct::ConstructFile;
-use loss72_platemaker_core::{log, model::{Article, GenerationContext}, util::get_slice_by_char};
+use loss72_platemaker_core::{
+    log,
+    model::{Article, GenerationContext, TocEntry},
+    util::{escape_xml, get_slice_by_char},
+};
 use loss72_platemaker_template::Placeholder;
-use std::{
-    any::type_name,
-    collections::HashMap,
-    path::{Path, PathBuf},
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::{any::type_name, collections::HashMap, path::PathBuf};
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError,
+    assets::fill_asset_helpers,
+    dates::date_variables,
+    links::{linked_articles_in, resolve_article_links, resolve_wikilinks},
+    permalink::absolutize_relative_urls,
+    reading_time::reading_minutes,
+    related::related_articles,
+    series::{Series, collect_series, render_series_navigation},
+    social::render_social_meta,
+    text::{strip_tags, truncate_text},
 };
 
-use crate::{OutputResult, WebPageHtmlTemplates, WebsiteGenerationError};
+const EXCERPT_LENGTH: usize = 140;
 
 pub struct IndexPage {
     pub html: String,
@@ -23,10 +37,25 @@ impl<'p> From<&'p IndexPage> for ConstructFile<'p> {
     }
 }
 
+#[derive(Clone)]
 pub struct ArticlePage<'article> {
     pub article: &'article Article,
     pub html: String,
+    // relative to the destination
     pub path: PathBuf,
+    pub url: String,
+}
+
+impl<'article> ArticlePage<'article> {
+    // Without the html, only to list the article which is not built this time
+    pub fn listing(html_templates: &WebPageHtmlTemplates, article: &'article Article) -> Self {
+        ArticlePage {
+            article,
+            html: String::new(),
+            path: html_templates.permalink.path(&article.id),
+            url: html_templates.permalink.url(&article.id),
+        }
+    }
 }
 
 impl<'p> From<&'p ArticlePage<'_>> for ConstructFile<'p> {
@@ -53,6 +82,7 @@ impl std::fmt::Debug for ArticlePage<'_> {
                 ),
             )
             .field("path", &self.path)
+            .field("url", &self.url)
             .finish()
     }
 }
@@ -72,82 +102,453 @@ pub fn generate_index_html(
         .expect("Regex is validated to include the capture group");
 
     // We create list elements first
-    let article_tag_iter = article
+    let article_tag_iter = render_article_list(html_templates, article, ctx)?;
+
+    let mut placeholder_contents = HashMap::from([
+        ("articles", article_tag_iter),
+        ("style", html_templates.index_style.clone()),
+        ("canonical", html_templates.canonical_link("/")),
+    ]);
+    placeholder_contents.extend(listing_variables(ctx));
+
+    let html = placeholder
+        .partially_fill_placeholders(&html_templates.index, |name| {
+            placeholder_contents
+                .get(name)
+                .cloned()
+                .or_else(|| html_templates.site.get(name))
+        })
+        .map_err(|invalids| WebsiteGenerationError::InvalidPlaceholder(invalids.clone()))?;
+
+    Ok(IndexPage {
+        path: PathBuf::from("index.html"),
+        html: html_templates.assets.rewrite(&html, "/index.html"),
+    })
+}
+
+pub(crate) fn render_article_list(
+    html_templates: &WebPageHtmlTemplates,
+    article: &[ArticlePage],
+    ctx: &GenerationContext,
+) -> OutputResult<String> {
+    let placeholder = Placeholder::from_strs("${", "}", None)
+        .expect("Regex is validated to include the capture group");
+
+    article
         .iter()
         .map(|page| {
-            let mut placeholder_contents = article_to_placeholder_content(page.article, ctx);
-                placeholder_contents.insert(
-                "url",
-                Path::new("/articles")
-                    .join(&page.path)
-                    .to_string_lossy()
-                    .to_string(),
-            );
+            let mut placeholder_contents =
+                article_to_placeholder_content(html_templates, page.article, ctx);
+            placeholder_contents.insert("brief", listing_summary(page.article));
+            placeholder_contents.insert("url", page.url.clone());
+            // for the styles of the pinned ones on the index
+            placeholder_contents.insert("is_pinned", page.article.metadata.pinned.to_string());
 
             placeholder
                 .partially_fill_placeholders(&html_templates.index_list, |name| {
-                    placeholder_contents.get(name).cloned()
+                    placeholder_contents
+                        .get(name)
+                        .cloned()
+                        .or_else(|| html_templates.site.get(name))
                 })
                 .map_err(|invalids| WebsiteGenerationError::InvalidPlaceholder(invalids.clone()))
         })
-        .collect::<Result<String, _>>()?;
+        .collect::<Result<String, _>>()
+}
 
-    let placeholder_contents = HashMap::from([
-        ("articles", article_tag_iter),
-        ("style", html_templates.index_style.clone()),
-        ("if-debug", if ctx.release { "<!-- (if-debug: false) ".to_string() } else { "".to_string() }),
-        ("end-if-debug", if ctx.release { " (end-if-debug: false) -->".to_string() } else { "".to_string() }),
-        ("if-release", if ctx.release { "".to_string() } else { "<!-- (if-release: false) ".to_string() }),
-        ("end-if-release", if ctx.release { "".to_string() } else { " (end-if-release: false) -->".to_string() }),
-    ]);
+// Listings prefer the manual brief, then the lead before `<!-- more -->` as the plain text, which
+// is in the paragraphs of the listings, and finally a plain text cut of the content for articles
+// which have neither
+fn listing_summary(article: &Article) -> String {
+    if !article.metadata.brief.trim().is_empty() {
+        return article.metadata.brief.clone();
+    }
 
-    Ok(IndexPage {
-        path: PathBuf::from("index.html"),
-        html: placeholder
-            .partially_fill_placeholders(&html_templates.index, |name| {
-                placeholder_contents.get(name).cloned()
-            })
-            .map_err(|invalids| WebsiteGenerationError::InvalidPlaceholder(invalids.clone()))?,
-    })
+    if let Some(excerpt) = &article.excerpt {
+        return strip_tags(excerpt)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+
+    truncate_text(&strip_tags(&article.content), EXCERPT_LENGTH)
+}
+
+pub(crate) fn listing_variables(ctx: &GenerationContext) -> [(&'static str, String); 5] {
+    [
+        ("build_id", ctx.build_id.clone().unwrap_or_default()),
+        (
+            "if-debug",
+            if ctx.release {
+                "<!-- (if-debug: false) ".to_string()
+            } else {
+                "".to_string()
+            },
+        ),
+        (
+            "end-if-debug",
+            if ctx.release {
+                " (end-if-debug: false) -->".to_string()
+            } else {
+                "".to_string()
+            },
+        ),
+        (
+            "if-release",
+            if ctx.release {
+                "".to_string()
+            } else {
+                "<!-- (if-release: false) ".to_string()
+            },
+        ),
+        (
+            "end-if-release",
+            if ctx.release {
+                "".to_string()
+            } else {
+                " (end-if-release: false) -->".to_string()
+            },
+        ),
+    ]
+}
+
+// Other articles which an article page links to
+#[derive(Clone, Debug, Default)]
+pub struct LinkedArticles<'article> {
+    pub related: Vec<&'article Article>,
+    // The older one
+    pub prev: Option<&'article Article>,
+    // The newer one
+    pub next: Option<&'article Article>,
+    pub series: Option<Series<'article>>,
+    // The others linking to it with `article:` or `[[slug]]`, newest first
+    pub backlinks: Vec<&'article Article>,
 }
 
 pub fn generate_article_html<'article>(
     html_templates: &WebPageHtmlTemplates,
     article: &'article Article,
+    links: &LinkedArticles,
     ctx: &GenerationContext,
 ) -> OutputResult<ArticlePage<'article>> {
     log!(step: "Generating HTML for slug '{}'", &article.id.slug);
 
-    let path = Path::new(&article.id.group).join(format!("{}.html", &article.id.slug));
+    let path = html_templates.permalink.path(&article.id);
+    let url = html_templates.permalink.url(&article.id);
 
     let placeholder = Placeholder::from_strs("${", "}", None)
         .expect("Regex is validated to include the capture group");
 
-    let mut placeholder_contents = article_to_placeholder_content(article, ctx);
-    placeholder_contents.insert("content", article.content.clone());
+    let mut placeholder_contents = article_to_placeholder_content(html_templates, article, ctx);
     placeholder_contents.insert(
-        "path", 
-                Path::new("/articles")
-                    .join(&path)
-                    .to_string_lossy()
-                    .to_string());
+        "content",
+        match html_templates.back_to_top_after {
+            Some(after) => insert_back_to_top(&article.content, after, &html_templates.back_to_top),
+            None => article.content.clone(),
+        },
+    );
+    placeholder_contents.insert("toc", render_toc(&article.toc));
+    placeholder_contents.insert(
+        "related",
+        render_article_links(html_templates, &links.related, "related"),
+    );
+    placeholder_contents.insert(
+        "backlinks",
+        render_article_links(html_templates, &links.backlinks, "backlinks"),
+    );
+    placeholder_contents.insert("prev", render_neighbor(html_templates, links.prev, "prev"));
+    placeholder_contents.insert("next", render_neighbor(html_templates, links.next, "next"));
+    placeholder_contents.extend(series_variables(
+        html_templates,
+        links.series.as_ref(),
+        article,
+    ));
+    placeholder_contents.insert("og", render_social_meta(html_templates, article, &url));
+    placeholder_contents.insert("canonical", html_templates.canonical_link(&url));
+    placeholder_contents.insert("syntax_style", html_templates.syntax_style.clone());
+    placeholder_contents.insert("path", url.clone());
     placeholder_contents.extend(article.metadata.widgets.render_to_placeholder_content());
 
+    let default_url = html_templates.default_permalink.url(&article.id);
+    let template = fill_asset_helpers(
+        html_templates.article_template(article)?,
+        &default_url,
+        &html_templates.assets_dir,
+    );
+
+    let html = placeholder
+        .partially_fill_placeholders(&template, |name| {
+            placeholder_contents
+                .get(name)
+                .cloned()
+                .or_else(|| html_templates.site.get(name))
+        })
+        .map_err(|invalids| WebsiteGenerationError::InvalidPlaceholder(invalids.clone()))?;
+
+    let html = if url == default_url {
+        html
+    } else {
+        absolutize_relative_urls(&html, &default_url)
+    };
+
     Ok(ArticlePage {
         article,
-        html: placeholder
-            .partially_fill_placeholders(&html_templates.article, |name| {
-                placeholder_contents.get(name).cloned()
-            })
-            .map_err(|invalids| WebsiteGenerationError::InvalidPlaceholder(invalids.clone()))?,
+        html: html_templates.assets.rewrite(&html, &url),
         path,
+        url,
     })
 }
 
-fn article_to_placeholder_content(article: &Article, ctx: &GenerationContext) -> HashMap<&'static str, String> {
+pub(crate) const DEFAULT_BACK_TO_TOP: &str =
+    r##"<p class="back-to-top"><a href="#top">Back to top</a></p>"##;
+
+// `snippet` at the end of each `<h2>` section past the first `after` ones, and the anchor
+// it links to at the start
+fn insert_back_to_top(content: &str, after: usize, snippet: &str) -> String {
+    let mut html = String::from(r#"<a id="top"></a>"#);
+
+    let mut sections = 0;
+    let mut rest = content;
+    loop {
+        let next = rest
+            .match_indices("<h2")
+            .map(|(index, _)| index)
+            .find(|index| rest[index + 3..].starts_with(['>', ' ']));
+
+        let (section, after_section) = rest.split_at(next.unwrap_or(rest.len()));
+        html.push_str(section);
+        if sections > after {
+            html.push_str(snippet);
+        }
+
+        if next.is_none() {
+            return html;
+        }
+
+        sections += 1;
+        html.push_str("<h2");
+        rest = &after_section[3..];
+    }
+}
+
+fn render_toc(toc: &[TocEntry]) -> String {
+    if toc.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from(r#"<ul class="toc">"#);
+    let mut nested = false;
+    let mut has_parent = false;
+
+    for (index, entry) in toc.iter().enumerate() {
+        // Third level headings before any second level one are listed at the top level
+        let is_child = entry.level > 2 && has_parent;
+        has_parent |= entry.level == 2;
+
+        match (nested, is_child) {
+            (false, true) => html.push_str("<ul>"),
+            (true, false) => html.push_str("</li></ul></li>"),
+            (true, true) => html.push_str("</li>"),
+            (false, false) if index > 0 => html.push_str("</li>"),
+            (false, false) => {}
+        }
+        nested = is_child;
+
+        html.push_str(&format!(
+            r##"<li><a href="#{}">{}</a>"##,
+            escape_xml(&entry.id),
+            escape_xml(&entry.title)
+        ));
+    }
+
+    html.push_str(if nested {
+        "</li></ul></li></ul>"
+    } else {
+        "</li></ul>"
+    });
+    html
+}
+
+// `<ul>` of the class, which is empty without any of the articles
+fn render_article_links(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[&Article],
+    class: &str,
+) -> String {
+    if articles.is_empty() {
+        return String::new();
+    }
+
+    let items = articles
+        .iter()
+        .map(|article| {
+            format!(
+                r#"<li><a href="{}">{}</a></li>"#,
+                escape_xml(&html_templates.permalink.url(&article.id)),
+                escape_xml(&article.metadata.title)
+            )
+        })
+        .collect::<String>();
+
+    format!(r#"<ul class="{class}">{items}</ul>"#)
+}
+
+fn render_neighbor(
+    html_templates: &WebPageHtmlTemplates,
+    neighbor: Option<&Article>,
+    class: &str,
+) -> String {
+    neighbor
+        .map(|article| {
+            format!(
+                r#"<a class="{class}" href="{}">{}</a>"#,
+                escape_xml(&html_templates.permalink.url(&article.id)),
+                escape_xml(&article.metadata.title)
+            )
+        })
+        .unwrap_or_default()
+}
+
+// `${series}` and the numbers of the part in it, which are empty for the articles without it
+fn series_variables(
+    html_templates: &WebPageHtmlTemplates,
+    series: Option<&Series>,
+    article: &Article,
+) -> [(&'static str, String); 4] {
+    let part = series.and_then(|series| Some((series, series.part_of(article)?)));
+
+    [
+        (
+            "series",
+            series
+                .map(|series| render_series_navigation(html_templates, series, article))
+                .unwrap_or_default(),
+        ),
+        (
+            "series_name",
+            part.map(|(series, _)| series.name.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "series_part",
+            part.map(|(_, part)| (part + 1).to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "series_count",
+            part.map(|(series, _)| series.parts.len().to_string())
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+pub fn generate_article_pages<'article>(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &'article [Article],
+    ctx: &GenerationContext,
+) -> OutputResult<Vec<ArticlePage<'article>>> {
+    generate_each_article_page(html_templates, articles, &[], ctx)
+        .into_iter()
+        .collect()
+}
+
+// Results are in the same order as `articles`, for the failures to be reported one by one.
+// `others` are the articles not built this time, of which the series are the same as the
+// ones of `articles`, and which `article:` links and `[[slug]]` may link to as well.
+pub fn generate_each_article_page<'article>(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &'article [Article],
+    others: &[&'article Article],
+    ctx: &GenerationContext,
+) -> Vec<OutputResult<ArticlePage<'article>>> {
+    // The others in `articles` are the same ones before they are built
+    let others = others
+        .iter()
+        .copied()
+        .filter(|other| articles.iter().all(|article| article.id != other.id))
+        .collect::<Vec<_>>();
+    let links = linked_articles(articles, &others, html_templates.related_count);
+    let linkable = articles.iter().chain(others).collect::<Vec<_>>();
+
+    articles
+        .par_iter()
+        .zip(links.par_iter())
+        .map(|(article, links)| {
+            let mut page = generate_article_html(html_templates, article, links, ctx)?;
+            page.html = resolve_article_links(html_templates, &page.html, article, &linkable)?;
+            page.html = resolve_wikilinks(html_templates, &page.html, article, &linkable);
+            Ok(page)
+        })
+        .collect()
+}
+
+// In the same order as `articles`, whichever order they are in. `others` are linked to as
+// well, which are none of `articles`.
+fn linked_articles<'article>(
+    articles: &'article [Article],
+    others: &[&'article Article],
+    related_count: usize,
+) -> Vec<LinkedArticles<'article>> {
+    // `articles` come first, of which the indices are the same in `links`
+    let linkable = articles
+        .iter()
+        .chain(others.iter().copied())
+        .collect::<Vec<_>>();
+
+    let mut links = related_articles(articles, others, related_count)
+        .into_iter()
+        .map(|related| LinkedArticles {
+            related,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut chronological = (0..linkable.len()).collect::<Vec<_>>();
+    chronological.sort_by(|left, right| linkable[*left].id.cmp(&linkable[*right].id));
+
+    for pair in chronological.windows(2) {
+        let [older, newer] = [pair[0], pair[1]];
+        if let Some(links) = links.get_mut(older) {
+            links.next = Some(linkable[newer]);
+        }
+        if let Some(links) = links.get_mut(newer) {
+            links.prev = Some(linkable[older]);
+        }
+    }
+
+    let series = collect_series(linkable.iter().copied());
+    for (article, links) in articles.iter().zip(&mut links) {
+        links.series = series
+            .iter()
+            .find(|series| series.part_of(article).is_some())
+            .cloned();
+    }
+
+    // after all of them are parsed, from the links in the contents before they are resolved
+    for source in &linkable {
+        for target in linked_articles_in(&source.content, source, &linkable) {
+            if let Some(index) = articles.iter().position(|article| article.id == target.id) {
+                links[index].backlinks.push(source);
+            }
+        }
+    }
+    for links in &mut links {
+        links
+            .backlinks
+            .sort_by(|left, right| right.id.cmp(&left.id));
+    }
+
+    links
+}
+
+fn article_to_placeholder_content(
+    html_templates: &WebPageHtmlTemplates,
+    article: &Article,
+    ctx: &GenerationContext,
+) -> HashMap<&'static str, String> {
     let (year, month, day) = article.id.date;
 
-    HashMap::from([
+    let mut placeholder_contents = HashMap::from([
         (
             "type_class",
             article
@@ -170,13 +571,374 @@ fn article_to_placeholder_content(article: &Article, ctx: &GenerationContext) ->
         ("brief", article.metadata.brief.clone()),
         ("year", year.to_string()),
         ("month", month.to_string()),
-        ("day", day.to_string()),
+        ("day", day.map(|day| day.to_string()).unwrap_or_default()),
         ("MM", format!("{:02}", month)),
-        ("DD", format!("{:02}", day)),
-        ("if-debug", if ctx.release { "".to_string() } else { "<!-- (debug) ".to_string() }),
-        ("end-if-debug", if ctx.release { "".to_string() } else { " (debug) -->".to_string() }),
-        ("if-release", if ctx.release { "<!-- (release) ".to_string() } else { "".to_string() }),
-        ("end-if-release", if ctx.release { " (release) -->".to_string() } else { "".to_string() }),
-    ])
+        (
+            "DD",
+            day.map(|day| format!("{:02}", day)).unwrap_or_default(),
+        ),
+        ("reading_time", reading_minutes(article, ctx).to_string()),
+        ("build_id", ctx.build_id.clone().unwrap_or_default()),
+        (
+            "if-debug",
+            if ctx.release {
+                "".to_string()
+            } else {
+                "<!-- (debug) ".to_string()
+            },
+        ),
+        (
+            "end-if-debug",
+            if ctx.release {
+                "".to_string()
+            } else {
+                " (debug) -->".to_string()
+            },
+        ),
+        (
+            "if-release",
+            if ctx.release {
+                "<!-- (release) ".to_string()
+            } else {
+                "".to_string()
+            },
+        ),
+        (
+            "end-if-release",
+            if ctx.release {
+                " (release) -->".to_string()
+            } else {
+                "".to_string()
+            },
+        ),
+    ]);
+    placeholder_contents.extend(date_variables(html_templates, article.id.date));
+
+    placeholder_contents
 }
 
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use loss72_platemaker_core::model::{
+        Article, ArticleIdentifier, ArticleMetadata, GenerationContext, TocEntry,
+    };
+
+    use super::{
+        generate_article_pages, generate_each_article_page, insert_back_to_top, linked_articles,
+        listing_summary, render_toc,
+    };
+    use crate::{WebPageHtmlTemplates, WebsiteGenerationError};
+
+    fn article(day: u8) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: format!("{day:02}_article"),
+                date: (2025, 1, Some(day)),
+            },
+            metadata: ArticleMetadata {
+                title: format!("Article {day}"),
+                brief: "brief".to_string(),
+                ..Default::default()
+            },
+            content: format!("<p>{day}</p>"),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn article_pages_are_deterministic_regardless_of_threads() {
+        let templates = WebPageHtmlTemplates {
+            article: "<h1>${title}</h1>${content}".to_string(),
+            ..Default::default()
+        };
+        let ctx = GenerationContext {
+            release: true,
+            ..Default::default()
+        };
+        let articles = (1..=28).map(article).collect::<Vec<_>>();
+
+        let generate_with = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("thread pool to be built")
+                .install(|| generate_article_pages(&templates, &articles, &ctx))
+                .expect("pages to be generated")
+                .into_iter()
+                .map(|page| (page.path, page.html))
+                .collect::<Vec<_>>()
+        };
+
+        let sequential = generate_with(1);
+        assert_eq!(sequential.len(), articles.len());
+
+        for threads in [2, 4, 8] {
+            assert_eq!(generate_with(threads), sequential);
+        }
+    }
+
+    #[test]
+    fn canonical_link_needs_base_url() {
+        let mut templates = WebPageHtmlTemplates {
+            article: "${canonical}".to_string(),
+            ..Default::default()
+        };
+        let ctx = GenerationContext::default();
+        let articles = [article(3)];
+
+        let html = |templates: &WebPageHtmlTemplates| {
+            generate_article_pages(templates, &articles, &ctx).expect("pages to be generated")[0]
+                .html
+                .clone()
+        };
+
+        assert_eq!(html(&templates), "");
+
+        templates.base_url = Some("https://example.com/".to_string());
+        assert_eq!(
+            html(&templates),
+            r#"<link rel="canonical" href="https://example.com/articles/202501/03_article.html">"#
+        );
+    }
+
+    #[test]
+    fn articles_choose_their_template() {
+        let templates = WebPageHtmlTemplates {
+            article: "<article>${title}</article>".to_string(),
+            article_templates: HashMap::from([(
+                "landing".to_string(),
+                "<main>${title}</main>".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let ctx = GenerationContext::default();
+        let mut articles = [article(1), article(2)];
+        articles[1].metadata.template = Some("landing".to_string());
+
+        let pages =
+            generate_article_pages(&templates, &articles, &ctx).expect("pages to be generated");
+        assert_eq!(pages[0].html, "<article>Article 1</article>");
+        assert_eq!(pages[1].html, "<main>Article 2</main>");
+
+        articles[0].metadata.template = Some("missing".to_string());
+        assert!(matches!(
+            generate_article_pages(&templates, &articles, &ctx),
+            Err(WebsiteGenerationError::MissingTemplate(slug, name))
+                if slug == "01_article" && name == "missing"
+        ));
+    }
+
+    #[test]
+    fn article_links_are_resolved_to_the_urls() {
+        let templates = WebPageHtmlTemplates {
+            article: "${content}".to_string(),
+            ..Default::default()
+        };
+        let ctx = GenerationContext::default();
+        let other = article(3);
+        let mut articles = [article(1), article(2)];
+        articles[0].content = r#"<a href="article:202501/02_article#top">2</a>"#.to_string();
+        articles[1].content = r#"<a href="article:202501/03_article">3</a>"#.to_string();
+
+        let pages = generate_each_article_page(&templates, &articles, &[&other], &ctx)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("pages to be generated");
+        assert_eq!(
+            pages[0].html,
+            r#"<a href="/articles/202501/02_article.html#top">2</a>"#
+        );
+        assert_eq!(
+            pages[1].html,
+            r#"<a href="/articles/202501/03_article.html">3</a>"#
+        );
+
+        assert!(matches!(
+            generate_article_pages(&templates, &articles, &ctx),
+            Err(WebsiteGenerationError::UnknownArticleLink { article, target })
+                if article == "02_article" && target == "202501/03_article"
+        ));
+
+        articles[0].content =
+            r#"<a href="article:2025/01/02_article">2</a><a href="article:2025/1/03_article">3</a>"#
+                .to_string();
+        let pages = generate_each_article_page(&templates, &articles, &[&other], &ctx)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("pages to be generated");
+        assert_eq!(
+            pages[0].html,
+            concat!(
+                r#"<a href="/articles/202501/02_article.html">2</a>"#,
+                r#"<a href="/articles/202501/03_article.html">3</a>"#
+            )
+        );
+    }
+
+    #[test]
+    fn wikilinks_are_resolved_by_the_slugs() {
+        let templates = WebPageHtmlTemplates {
+            article: "${content}".to_string(),
+            ..Default::default()
+        };
+        let mut older = article(2);
+        older.id.group = "202412".to_string();
+        let mut articles = [article(1), article(2), older];
+        articles[0].content = concat!(
+            r#"<a href="wikilink:02_article" class="wikilink">two</a> "#,
+            r#"<a href="wikilink:202412/02_article#top" class="wikilink">older</a> "#,
+            r#"<a href="wikilink:01_article" class="wikilink">self</a> "#,
+            r#"<a href="wikilink:09_article" class="wikilink">nine</a>"#,
+        )
+        .to_string();
+        articles[1].content = String::new();
+        articles[2].content = String::new();
+
+        let pages = generate_article_pages(&templates, &articles, &GenerationContext::default())
+            .expect("pages to be generated");
+        assert_eq!(
+            pages[0].html,
+            concat!(
+                r#"<span class="wikilink wikilink-broken" title="02_article">two</span> "#,
+                r#"<a href="/articles/202412/02_article.html#top" class="wikilink">older</a> "#,
+                r#"<a href="/articles/202501/01_article.html" class="wikilink">self</a> "#,
+                r#"<span class="wikilink wikilink-broken" title="09_article">nine</span>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn back_to_top_follows_later_sections() {
+        let content =
+            "<p>lead</p><h2 id=\"a\">A</h2><p>a</p><h2>B</h2><h3>b</h3><h2>C</h2><p>c</p>";
+
+        assert_eq!(
+            insert_back_to_top(content, 1, "[top]"),
+            concat!(
+                r#"<a id="top"></a><p>lead</p><h2 id="a">A</h2><p>a</p>"#,
+                "<h2>B</h2><h3>b</h3>[top]<h2>C</h2><p>c</p>[top]",
+            )
+        );
+        assert_eq!(
+            insert_back_to_top(content, 3, "[top]"),
+            format!(r#"<a id="top"></a>{content}"#)
+        );
+    }
+
+    #[test]
+    fn neighbors_are_linked_in_chronological_order() {
+        let articles = [article(3), article(1), article(2)];
+        let links = linked_articles(&articles, &[], 0);
+
+        fn slug(article: Option<&Article>) -> Option<&str> {
+            article.map(|article| article.id.slug.as_str())
+        }
+        let neighbors = links
+            .iter()
+            .map(|links| (slug(links.prev), slug(links.next)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            neighbors,
+            [
+                (Some("02_article"), None),
+                (None, Some("02_article")),
+                (Some("01_article"), Some("03_article")),
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbors_of_a_single_article_are_the_others() {
+        let templates = WebPageHtmlTemplates {
+            article: "${prev}|${next}".to_string(),
+            ..Default::default()
+        };
+        let ctx = GenerationContext::default();
+        let articles = [article(2)];
+        let [older, newer] = [article(1), article(3)];
+
+        let pages = generate_each_article_page(&templates, &articles, &[&newer, &older], &ctx)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("pages to be generated");
+        assert_eq!(
+            pages[0].html,
+            concat!(
+                r#"<a class="prev" href="/articles/202501/01_article.html">Article 1</a>|"#,
+                r#"<a class="next" href="/articles/202501/03_article.html">Article 3</a>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn backlinks_are_the_others_linking_to_the_article() {
+        let mut articles = [article(1), article(2), article(3)];
+        articles[0].content = concat!(
+            r#"<a href="article:202501/02_article#top">2</a>"#,
+            r#"<a href="wikilink:02_article" class="wikilink">2</a>"#,
+        )
+        .to_string();
+        articles[1].content =
+            r#"<a href="wikilink:02_article" class="wikilink">self</a>"#.to_string();
+        let mut other = article(4);
+        other.content =
+            r#"<a href="wikilink:202501/02_article" class="wikilink">2</a>"#.to_string();
+        articles[2].content = r#"<a href="wikilink:04_article" class="wikilink">4</a>"#.to_string();
+
+        let links = linked_articles(&articles, &[&other], 0);
+        let backlinks = links
+            .iter()
+            .map(|links| {
+                links
+                    .backlinks
+                    .iter()
+                    .map(|article| article.id.slug.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            backlinks,
+            [vec![], vec!["04_article", "01_article"], vec![]]
+        );
+    }
+
+    #[test]
+    fn listings_prefer_the_brief_then_the_lead() {
+        let mut article = article(1);
+        article.excerpt =
+            Some("<p>The <em>lead</em>\nof it</p>\n<ul>\n<li>item</li>\n</ul>".to_string());
+        assert_eq!(listing_summary(&article), "brief");
+
+        article.metadata.brief = " ".to_string();
+        assert_eq!(listing_summary(&article), "The lead of it item");
+
+        article.excerpt = None;
+        assert_eq!(listing_summary(&article), "1");
+    }
+
+    #[test]
+    fn toc_nests_third_level_headings() {
+        let entry = |level, id: &str| TocEntry {
+            level,
+            title: id.to_uppercase(),
+            id: id.to_string(),
+        };
+
+        assert_eq!(
+            render_toc(&[entry(2, "a"), entry(3, "b"), entry(3, "c"), entry(2, "d")]),
+            concat!(
+                r##"<ul class="toc"><li><a href="#a">A</a>"##,
+                r##"<ul><li><a href="#b">B</a></li><li><a href="#c">C</a></li></ul></li>"##,
+                r##"<li><a href="#d">D</a></li></ul>"##,
+            )
+        );
+        assert_eq!(render_toc(&[]), "");
+    }
+}
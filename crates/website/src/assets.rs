@@ -0,0 +1,335 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use loss72_platemaker_core::util::content_hash;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+pub const ASSET_MANIFEST_FILE: &str = "asset-manifest.json";
+
+// of the article assets which are the same in multiple places, from the destination
+pub const SHARED_ASSET_DIR: &str = "assets/_shared";
+
+const HASH_LENGTH: usize = 8;
+
+// Longer than the fingerprints, as the content is the only thing in the names
+const SHARED_HASH_LENGTH: usize = 16;
+
+// `${asset:name.png}` in the article templates, of the file in `assets` of each article
+static ASSET_HELPER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{\s*asset:([^}]*?)\s*\}").expect("Statically provided regex to be valid")
+});
+
+// url path of the asset -> url path which the asset is written to
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AssetManifest(BTreeMap<String, String>);
+
+impl AssetManifest {
+    // `path` is relative to the destination, which is also the url path of the asset
+    pub fn insert(&mut self, path: &Path, content: &[u8]) {
+        self.insert_hashed(path, &content_hash(content));
+    }
+
+    // with `content_hash` of the content, which may be of a previous build
+    pub fn insert_hashed(&mut self, path: &Path, hash: &str) {
+        let hash = &hash[..HASH_LENGTH];
+        let fingerprinted = match (path.file_stem(), path.extension()) {
+            (Some(stem), Some(ext)) => path.with_file_name(format!(
+                "{}.{hash}.{}",
+                stem.to_string_lossy(),
+                ext.to_string_lossy()
+            )),
+            _ => path.with_file_name(format!(
+                "{}.{hash}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            )),
+        };
+
+        self.0.insert(url_path(path), url_path(&fingerprinted));
+    }
+
+    // The copies of the same content are all written to `assets/_shared/<hash>.<ext>` instead,
+    // which is referred from every page by the path from the root. `hash` is `content_hash` of it.
+    pub fn insert_shared(&mut self, path: &Path, hash: &str) {
+        let hash = &hash[..SHARED_HASH_LENGTH];
+        let shared = match path.extension() {
+            Some(ext) => format!("{hash}.{}", ext.to_string_lossy()),
+            None => hash.to_string(),
+        };
+
+        self.0.insert(
+            url_path(path),
+            url_path(&Path::new(SHARED_ASSET_DIR).join(shared)),
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Pairs of the paths relative to the destination, to rename the copied assets
+    pub fn renames(&self) -> impl Iterator<Item = (PathBuf, PathBuf)> {
+        self.0.iter().map(|(original, fingerprinted)| {
+            (
+                PathBuf::from(original.trim_start_matches('/')),
+                PathBuf::from(fingerprinted.trim_start_matches('/')),
+            )
+        })
+    }
+
+    pub fn rewrite(&self, html: &str, page_url: &str) -> String {
+        if self.is_empty() {
+            return html.to_string();
+        }
+
+        map_url_attributes(html, |url| {
+            self.rewrite_url(url, page_url)
+                .unwrap_or_else(|| url.to_string())
+        })
+    }
+
+    pub(crate) fn rewrite_url(&self, url: &str, page_url: &str) -> Option<String> {
+        // External urls, `mailto:`, `data:` and fragments are never ours
+        if url.starts_with("//") || url.starts_with('#') || url.contains(':') {
+            return None;
+        }
+
+        let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+        let fingerprinted = self.0.get(&resolve(path, page_url))?;
+
+        if fingerprinted.starts_with(&format!("/{SHARED_ASSET_DIR}/")) {
+            return Some(format!("{fingerprinted}{suffix}"));
+        }
+
+        // Only the file name is replaced, to keep the reference relative if it was
+        let file_name = fingerprinted.rsplit('/').next()?;
+        let dir = &path[..path.rfind('/').map_or(0, |index| index + 1)];
+
+        Some(format!("{dir}{file_name}{suffix}"))
+    }
+}
+
+// Names in `${asset:...}` of the template, relative to the assets of the articles
+pub fn referenced_assets(template: &str) -> Vec<String> {
+    let mut names = ASSET_HELPER_REGEX
+        .captures_iter(template)
+        .map(|captures| asset_name(&captures).to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    names
+}
+
+// `${asset:...}` filled with the url from the root of the file in `assets_dir` next to the
+// article at `article_url`, which is rewritten as the other references to the assets are
+pub(crate) fn fill_asset_helpers(template: &str, article_url: &str, assets_dir: &str) -> String {
+    ASSET_HELPER_REGEX
+        .replace_all(template, |captures: &Captures| {
+            resolve(
+                &format!("{assets_dir}/{}", asset_name(captures)),
+                article_url,
+            )
+        })
+        .into_owned()
+}
+
+fn asset_name<'c>(captures: &Captures<'c>) -> &'c str {
+    let name = captures.get(1).map_or("", |name| name.as_str());
+    name.trim_start_matches("./")
+}
+
+fn url_path(path: &Path) -> String {
+    let components = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+
+    format!("/{}", components.join("/"))
+}
+
+pub(crate) fn resolve(path: &str, page_url: &str) -> String {
+    let base = if path.starts_with('/') {
+        ""
+    } else {
+        page_url.rsplit_once('/').map_or("", |(dir, _)| dir)
+    };
+
+    let mut segments = vec![];
+    for segment in base.split('/').chain(path.split('/')) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+// Replaces the urls in `href`, `src` and each candidate of `srcset`
+pub(crate) fn map_url_attributes(html: &str, map: impl Fn(&str) -> String) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some((name, start, end)) = next_attribute(rest, &["href=", "src=", "srcset="]) {
+        let value = &rest[start..end];
+        output.push_str(&rest[..start]);
+
+        if name == "srcset=" {
+            let candidates = value
+                .split(',')
+                .map(|candidate| {
+                    let candidate = candidate.trim();
+                    let (url, descriptor) = candidate.split_at(
+                        candidate
+                            .find(char::is_whitespace)
+                            .unwrap_or(candidate.len()),
+                    );
+                    format!("{}{descriptor}", map(url))
+                })
+                .collect::<Vec<_>>();
+            output.push_str(&candidates.join(", "));
+        } else {
+            output.push_str(&map(value));
+        }
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Name and range of the value of the next quoted attribute among `names`, e.g. `href=`
+pub(crate) fn next_attribute<'n>(html: &str, names: &[&'n str]) -> Option<(&'n str, usize, usize)> {
+    let mut offset = 0;
+
+    loop {
+        let (index, name) = names
+            .iter()
+            .copied()
+            .filter_map(|name| {
+                html[offset..]
+                    .find(name)
+                    .map(|index| (offset + index, name))
+            })
+            .min()?;
+        offset = index + name.len();
+
+        // e.g. `data-src=` is not a reference to load
+        if !html[..index].ends_with(char::is_whitespace) {
+            continue;
+        }
+
+        let Some(quote) = html[offset..]
+            .chars()
+            .next()
+            .filter(|char| matches!(char, '"' | '\''))
+        else {
+            continue;
+        };
+
+        let start = offset + 1;
+        return html[start..]
+            .find(quote)
+            .map(|len| (name, start, start + len));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use loss72_platemaker_core::util::content_hash;
+
+    use super::{AssetManifest, fill_asset_helpers, referenced_assets};
+
+    #[test]
+    fn rewrites_references_to_fingerprinted_assets() {
+        let mut manifest = AssetManifest::default();
+        manifest.insert(Path::new("styles/index.css"), b"body {}");
+        manifest.insert(Path::new("articles/202501/assets/pic.png"), b"png");
+
+        let [css, png] = ["/styles/index.css", "/articles/202501/assets/pic.png"].map(|path| {
+            manifest.0[path]
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        assert!(css.starts_with("index.") && css.ends_with(".css") && css.len() == 18);
+
+        assert_eq!(
+            manifest.rewrite(
+                concat!(
+                    r#"<link rel="stylesheet" href="/styles/index.css?v=1">"#,
+                    r#"<link href='../../styles/index.css'>"#,
+                    r#"<img src="./assets/pic.png" data-src="./assets/pic.png" srcset="./assets/pic-200w.png 200w, ./assets/pic.png 400w">"#,
+                    r#"<a href="https://example.com/styles/index.css">"#,
+                    r#"<a href="/styles/other.css">"#,
+                ),
+                "/articles/202501/03_hello.html",
+            ),
+            [
+                format!(r#"<link rel="stylesheet" href="/styles/{css}?v=1">"#),
+                format!(r#"<link href='../../styles/{css}'>"#),
+                format!(
+                    r#"<img src="./assets/{png}" data-src="./assets/pic.png" srcset="./assets/pic-200w.png 200w, ./assets/{png} 400w">"#
+                ),
+                r#"<a href="https://example.com/styles/index.css">"#.to_string(),
+                r#"<a href="/styles/other.css">"#.to_string(),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn shared_assets_are_referred_from_the_root() {
+        let mut manifest = AssetManifest::default();
+        let hash = content_hash(b"logo");
+        manifest.insert_shared(Path::new("articles/202501/assets/logo.png"), &hash);
+        manifest.insert_shared(Path::new("articles/202412/assets/logo-copy.png"), &hash);
+
+        let shared = &manifest.0["/articles/202501/assets/logo.png"];
+        assert_eq!(shared, &manifest.0["/articles/202412/assets/logo-copy.png"]);
+        assert!(shared.starts_with("/assets/_shared/") && shared.ends_with(".png"));
+
+        assert_eq!(
+            manifest.rewrite(
+                r#"<img src="./assets/logo.png?v=1"><img src="../202412/assets/logo-copy.png">"#,
+                "/articles/202501/03_hello.html",
+            ),
+            format!(r#"<img src="{shared}?v=1"><img src="{shared}">"#)
+        );
+    }
+
+    #[test]
+    fn asset_helpers_refer_to_the_assets_of_the_article() {
+        let template =
+            r#"<img src="${asset:pic.png}"><link href="${ asset:./pic.png }">${asset:css/a.css}"#;
+
+        assert_eq!(
+            fill_asset_helpers(template, "/articles/202501/03_hello.html", "assets"),
+            concat!(
+                r#"<img src="/articles/202501/assets/pic.png">"#,
+                r#"<link href="/articles/202501/assets/pic.png">"#,
+                "/articles/202501/assets/css/a.css",
+            )
+        );
+        assert_eq!(referenced_assets(template), ["css/a.css", "pic.png"]);
+        assert_eq!(
+            fill_asset_helpers(template, "/articles/202501/03_hello.html", "media"),
+            concat!(
+                r#"<img src="/articles/202501/media/pic.png">"#,
+                r#"<link href="/articles/202501/media/pic.png">"#,
+                "/articles/202501/media/css/a.css",
+            )
+        );
+    }
+}
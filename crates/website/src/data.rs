@@ -0,0 +1,372 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use loss72_platemaker_core::{
+    fs::{Directory, FSNode, File},
+    util::escape_xml,
+};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::{OutputResult, WebsiteGenerationError};
+
+static DATA_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{\s*(?:each\s+((?:data|item)(?:\.[A-Za-z0-9_-]+)*)|(endeach)|((?:data|item)(?:\.[A-Za-z0-9_-]+)*))\s*\}")
+        .expect("Statically provided regex to be valid")
+});
+
+// The files in the data directory by their names without the extension, like `${data.projects}`
+// for `projects.json`. In the templates, `${data.site.title}` is a value in them, and
+// `${each data.projects}...${endeach}` repeats its content with `${item.name}` for each of
+// the list.
+#[derive(Clone, Debug, Default)]
+pub struct SiteData(HashMap<String, DataValue>);
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum DataValue {
+    Null,
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    List(Vec<DataValue>),
+    Table(HashMap<String, DataValue>),
+}
+
+pub fn load_data(data_dir: &Directory) -> OutputResult<SiteData> {
+    let mut data = HashMap::new();
+
+    for file in data_dir
+        .try_iter_content()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+    {
+        let path = file.path();
+        let (Some(name), Some(extension)) = (
+            path.file_stem().and_then(|name| name.to_str()),
+            path.extension().and_then(|extension| extension.to_str()),
+        ) else {
+            continue;
+        };
+
+        let value = match extension {
+            "json" => parse_file(&file, |content| {
+                serde_json::from_str(content).map_err(|e| (Some(e.line()), e.to_string()))
+            })?,
+            "yaml" | "yml" => parse_file(&file, |content| {
+                serde_yaml::from_str(content)
+                    .map_err(|e| (e.location().map(|location| location.line()), e.to_string()))
+            })?,
+            "csv" => parse_file(&file, parse_csv)?,
+            _ => continue,
+        };
+
+        data.insert(name.to_string(), value);
+    }
+
+    Ok(SiteData(data))
+}
+
+fn parse_file(
+    file: &File,
+    parse: impl FnOnce(&str) -> Result<DataValue, (Option<usize>, String)>,
+) -> OutputResult<DataValue> {
+    parse(&file.read_to_string()?).map_err(|(line, message)| {
+        WebsiteGenerationError::InvalidDataFile {
+            path: file.path().to_path_buf(),
+            line,
+            message,
+        }
+    })
+}
+
+// A list of the rows by the names in the header. Fields may be quoted with `"`, in which
+// `""` is a quote and the separators and the line breaks are kept.
+fn parse_csv(content: &str) -> Result<DataValue, (Option<usize>, String)> {
+    let mut records: Vec<(usize, Vec<String>)> = vec![];
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = content.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '"' if quoted => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut fields)));
+                line += 1;
+                record_line = line;
+            }
+            char => {
+                if char == '\n' {
+                    line += 1;
+                }
+                field.push(char);
+            }
+        }
+    }
+
+    if quoted {
+        return Err((
+            Some(record_line),
+            "The quoted field is not closed".to_string(),
+        ));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+
+    let mut records = records
+        .into_iter()
+        .filter(|(_, fields)| !matches!(fields.as_slice(), [field] if field.is_empty()));
+    let Some((_, header)) = records.next() else {
+        return Ok(DataValue::List(vec![]));
+    };
+
+    records
+        .map(|(line, fields)| {
+            if fields.len() != header.len() {
+                return Err((
+                    Some(line),
+                    format!(
+                        "{} fields are in the row, but the header has {}",
+                        fields.len(),
+                        header.len()
+                    ),
+                ));
+            }
+
+            Ok(DataValue::Table(
+                header
+                    .iter()
+                    .cloned()
+                    .zip(fields.into_iter().map(DataValue::String))
+                    .collect(),
+            ))
+        })
+        .collect::<Result<_, _>>()
+        .map(DataValue::List)
+}
+
+impl SiteData {
+    // `${data...}` and `${each}` in the template filled, leaving the other placeholders
+    pub(crate) fn render(&self, template: &str) -> OutputResult<String> {
+        self.render_with(template, None)
+    }
+
+    fn render_with(&self, template: &str, item: Option<&DataValue>) -> OutputResult<String> {
+        let mut html = String::with_capacity(template.len());
+        let mut matches = DATA_REGEX.captures_iter(template);
+        let mut rest = 0;
+
+        while let Some(captures) = matches.next() {
+            let whole = captures.get(0).expect("Regex to have the whole match");
+            html.push_str(&template[rest..whole.start()]);
+            rest = whole.end();
+
+            if captures.get(2).is_some() {
+                return Err(WebsiteGenerationError::InvalidPlaceholder(vec![
+                    "endeach".to_string(),
+                ]));
+            }
+
+            if let Some(path) = captures.get(3) {
+                html.push_str(&match self.lookup(path.as_str(), item)? {
+                    DataValue::List(_) | DataValue::Table(_) => {
+                        return Err(WebsiteGenerationError::NotDataValue(
+                            path.as_str().to_string(),
+                        ));
+                    }
+                    value => escape_data(&value.to_string()),
+                });
+                continue;
+            }
+
+            let path = captures
+                .get(1)
+                .expect("Regex to match one of the groups")
+                .as_str();
+            let end = closing_endeach(&mut matches)
+                .ok_or_else(|| WebsiteGenerationError::UnclosedEach(path.to_string()))?;
+            let body = &template[rest..end.start()];
+            rest = end.end();
+
+            let DataValue::List(items) = self.lookup(path, item)? else {
+                return Err(WebsiteGenerationError::NotDataList(path.to_string()));
+            };
+            for item in items {
+                html.push_str(&self.render_with(body, Some(item))?);
+            }
+        }
+
+        html.push_str(&template[rest..]);
+
+        Ok(html)
+    }
+
+    fn lookup<'v>(
+        &'v self,
+        path: &str,
+        item: Option<&'v DataValue>,
+    ) -> OutputResult<&'v DataValue> {
+        let missing = || WebsiteGenerationError::MissingData(path.to_string());
+
+        let mut keys = path.split('.');
+        let mut value = match keys.next() {
+            Some("item") => item.ok_or_else(missing)?,
+            _ => self
+                .0
+                .get(keys.next().ok_or_else(missing)?)
+                .ok_or_else(missing)?,
+        };
+
+        for key in keys {
+            value = match value {
+                DataValue::Table(table) => table.get(key),
+                DataValue::List(list) => {
+                    key.parse::<usize>().ok().and_then(|index| list.get(index))
+                }
+                _ => None,
+            }
+            .ok_or_else(missing)?;
+        }
+
+        Ok(value)
+    }
+}
+
+// `${endeach}` of the `${each}` just matched, skipping the nested ones
+fn closing_endeach<'t>(
+    matches: &mut impl Iterator<Item = Captures<'t>>,
+) -> Option<regex::Match<'t>> {
+    let mut depth = 0;
+
+    for captures in matches {
+        if captures.get(1).is_some() {
+            depth += 1;
+        } else if captures.get(2).is_some() {
+            if depth == 0 {
+                return captures.get(0);
+            }
+            depth -= 1;
+        }
+    }
+
+    None
+}
+
+// `$` too, not to be taken as the placeholders filled after
+fn escape_data(value: &str) -> String {
+    escape_xml(value).replace('$', "&#36;")
+}
+
+impl std::fmt::Display for DataValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataValue::Null | DataValue::List(_) | DataValue::Table(_) => Ok(()),
+            DataValue::String(value) => f.write_str(value),
+            DataValue::Integer(value) => write!(f, "{value}"),
+            DataValue::Float(value) => write!(f, "{value}"),
+            DataValue::Boolean(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{DataValue, SiteData, parse_csv};
+    use crate::WebsiteGenerationError;
+
+    #[test]
+    fn csv_rows_are_named_by_the_header() {
+        let DataValue::List(rows) =
+            parse_csv("name,url\r\nPlatemaker,\"https://a.example/?x=1,2\"\n\"Say \"\"hi\"\"\",\n")
+                .expect("csv to be parsed")
+        else {
+            panic!("csv to be a list");
+        };
+
+        let cells = rows
+            .iter()
+            .map(|row| match row {
+                DataValue::Table(table) => (table["name"].to_string(), table["url"].to_string()),
+                _ => panic!("rows to be tables"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            [
+                (
+                    "Platemaker".to_string(),
+                    "https://a.example/?x=1,2".to_string()
+                ),
+                ("Say \"hi\"".to_string(), String::new()),
+            ]
+        );
+
+        assert_eq!(
+            parse_csv("name,url\nPlatemaker\n").err(),
+            Some((
+                Some(2),
+                "1 fields are in the row, but the header has 2".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_csv("name\n\"open\n").err().and_then(|(line, _)| line),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn lists_are_repeated_with_their_items() {
+        let data = SiteData(HashMap::from([(
+            "projects".to_string(),
+            serde_json::from_str::<DataValue>(
+                r#"[{"name": "Platemaker", "tags": ["rust", "<ssg>"]}, {"name": "$5", "tags": []}]"#,
+            )
+            .expect("json to be parsed"),
+        )]));
+
+        assert_eq!(
+            data.render(
+                "<ul>${each data.projects}<li>${ item.name }:${each item.tags} ${item}${endeach}</li>${endeach}</ul>${title}"
+            )
+            .expect("data to be rendered"),
+            "<ul><li>Platemaker: rust &lt;ssg&gt;</li><li>&#36;5:</li></ul>${title}"
+        );
+        assert_eq!(
+            data.render("${data.projects.0.name}")
+                .expect("data to be rendered"),
+            "Platemaker"
+        );
+
+        assert!(matches!(
+            data.render("${data.missing}"),
+            Err(WebsiteGenerationError::MissingData(path)) if path == "data.missing"
+        ));
+        assert!(matches!(
+            data.render("${each data.projects}"),
+            Err(WebsiteGenerationError::UnclosedEach(path)) if path == "data.projects"
+        ));
+        assert!(matches!(
+            data.render("${data.projects}"),
+            Err(WebsiteGenerationError::NotDataValue(_))
+        ));
+    }
+}
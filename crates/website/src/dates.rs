@@ -0,0 +1,85 @@
+use std::fmt::Write;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+// strftime-like format of the dates for `${date}`, like `%B %e, %Y`
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct DateFormat(String);
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "'{0}' is not a format of the dates. Only the specifiers of the days are allowed, like '%Y-%m-%d'."
+)]
+pub struct InvalidDateFormat(String);
+
+impl DateFormat {
+    // of the articles without the day
+    pub fn month() -> Self {
+        DateFormat("%Y-%m".to_string())
+    }
+
+    pub(crate) fn format(&self, date: NaiveDate) -> String {
+        date.format(&self.0).to_string()
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        DateFormat("%Y-%m-%d".to_string())
+    }
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = InvalidDateFormat;
+
+    // Formatting panics on the unknown specifiers and the ones of the time, so they are
+    // found here once
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut formatted = String::new();
+        match write!(formatted, "{}", NaiveDate::MIN.format(&value)) {
+            Ok(()) => Ok(DateFormat(value)),
+            Err(_) => Err(InvalidDateFormat(value)),
+        }
+    }
+}
+
+// `${date}` in the format, and `${date_iso}` as `YYYY-MM-DD` or `YYYY-MM` without the day
+pub(crate) fn date_variables(
+    html_templates: &crate::WebPageHtmlTemplates,
+    (year, month, day): (u32, u8, Option<u8>),
+) -> [(&'static str, String); 2] {
+    let iso = match day {
+        Some(day) => format!("{year:04}-{month:02}-{day:02}"),
+        None => format!("{year:04}-{month:02}"),
+    };
+
+    let format = match day {
+        Some(_) => &html_templates.date_format,
+        None => &html_templates.month_date_format,
+    };
+    // The dates which don't exist are rejected while parsing
+    let date = NaiveDate::from_ymd_opt(year as i32, month.into(), day.unwrap_or(1).into())
+        .map(|date| format.format(date))
+        .unwrap_or_else(|| iso.clone());
+
+    [("date", date), ("date_iso", iso)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateFormat;
+
+    #[test]
+    fn only_the_formats_of_the_days_are_accepted() {
+        let format = DateFormat::try_from("%B %e, %Y".to_string()).expect("format to be valid");
+        assert_eq!(
+            format.format(chrono::NaiveDate::from_ymd_opt(2025, 1, 3).expect("date to exist")),
+            "January  3, 2025"
+        );
+
+        assert!(DateFormat::try_from("%Y-%m-%d %H:%M".to_string()).is_err());
+        assert!(DateFormat::try_from("%Q".to_string()).is_err());
+    }
+}
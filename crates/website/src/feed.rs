@@ -0,0 +1,224 @@
+use chrono::{NaiveDate, Utc};
+use loss72_platemaker_core::{
+    log,
+    model::ArticleIdentifier,
+    util::{escape_xml, url_host},
+};
+
+use crate::{OutputResult, WebPageHtmlTemplates, WebsiteGenerationError, articles::ArticlePage};
+
+pub const FEED_FILE: &str = "feed.xml";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedKind {
+    #[default]
+    Rss,
+    Atom,
+}
+
+// The urls in the feeds are absolute, which the readers need and the ids of Atom should be, so
+// there is no feed without `base_url`. It is titled by `site.title`, or the host of the site.
+pub fn generate_feed(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[ArticlePage],
+    kind: FeedKind,
+) -> OutputResult<Option<String>> {
+    let Some(site_url) = html_templates.canonical_url("/") else {
+        log!(warn: "`base_url` is not configured, skipping {}", FEED_FILE);
+        return Ok(None);
+    };
+
+    log!(section: "Generating {:?} feed for {} articles", kind, articles.len());
+
+    let feed = Feed {
+        title: html_templates
+            .site
+            .get("site.title")
+            .or_else(|| url_host(&site_url))
+            .unwrap_or_else(|| site_url.clone()),
+        site_url,
+        html_templates,
+    };
+
+    match kind {
+        FeedKind::Rss => generate_rss(&feed, articles),
+        FeedKind::Atom => generate_atom(&feed, articles),
+    }
+    .map(Some)
+}
+
+struct Feed<'t> {
+    title: String,
+    site_url: String,
+    html_templates: &'t WebPageHtmlTemplates,
+}
+
+impl Feed<'_> {
+    // of the page at `url`, which is there as the feeds are only with `base_url`
+    fn absolute_url(&self, url: &str) -> String {
+        self.html_templates.canonical_url(url).unwrap_or_default()
+    }
+}
+
+fn generate_rss(feed: &Feed, articles: &[ArticlePage]) -> OutputResult<String> {
+    let items = articles
+        .iter()
+        .map(|page| {
+            let url = escape_xml(&feed.absolute_url(&page.url));
+
+            Ok(format!(
+                "<item><title>{}</title><link>{url}</link><guid>{url}</guid><description>{}</description><pubDate>{}</pubDate></item>",
+                escape_xml(&page.article.metadata.title),
+                escape_xml(&page.article.metadata.brief),
+                article_date(&page.article.id)?.format("%a, %d %b %Y 00:00:00 +0000"),
+            ))
+        })
+        .collect::<OutputResult<String>>()?;
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><rss version="2.0"><channel><title>{title}</title><link>{site_url}</link><description>{title}</description>{items}</channel></rss>"#,
+        title = escape_xml(&feed.title),
+        site_url = escape_xml(&feed.site_url),
+    ))
+}
+
+fn generate_atom(feed: &Feed, articles: &[ArticlePage]) -> OutputResult<String> {
+    let entries = articles
+        .iter()
+        .map(|page| {
+            let url = escape_xml(&feed.absolute_url(&page.url));
+
+            Ok(format!(
+                r#"<entry><title>{}</title><link href="{url}"/><id>{url}</id><updated>{}</updated><summary>{}</summary></entry>"#,
+                escape_xml(&page.article.metadata.title),
+                atom_date(article_date(&page.article.id)?),
+                escape_xml(&page.article.metadata.brief),
+            ))
+        })
+        .collect::<OutputResult<String>>()?;
+
+    let updated = articles
+        .iter()
+        .map(|page| article_date(&page.article.id))
+        .collect::<OutputResult<Vec<_>>>()?
+        .into_iter()
+        .max()
+        .unwrap_or_else(|| Utc::now().date_naive());
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><feed xmlns="http://www.w3.org/2005/Atom"><title>{title}</title><id>{site_url}</id><link href="{site_url}"/><updated>{}</updated>{entries}</feed>"#,
+        atom_date(updated),
+        title = escape_xml(&feed.title),
+        site_url = escape_xml(&feed.site_url),
+    ))
+}
+
+// Articles only carry a date, so every timestamp is midnight in UTC.
+fn atom_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%dT00:00:00Z").to_string()
+}
+
+// Articles without the day are dated to the first day of the month
+fn article_date(id: &ArticleIdentifier) -> OutputResult<NaiveDate> {
+    let (year, month, day) = id.date;
+
+    NaiveDate::from_ymd_opt(year as i32, month.into(), day.unwrap_or(1).into())
+        .ok_or_else(|| WebsiteGenerationError::InvalidDate(id.slug.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use loss72_platemaker_core::model::{Article, ArticleIdentifier, ArticleMetadata};
+
+    use super::{FeedKind, generate_feed};
+    use crate::{WebPageHtmlTemplates, articles::ArticlePage};
+
+    fn article(slug: &str, date: (u32, u8, Option<u8>), title: &str) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: slug.to_string(),
+                date,
+            },
+            metadata: ArticleMetadata {
+                title: title.to_string(),
+                brief: "a < b".to_string(),
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn feeds_are_dated_and_escaped_with_the_absolute_urls() {
+        let articles = [
+            article("03_a", (2025, 1, Some(3)), "R&D <1>"),
+            article("notes", (2025, 2, None), "Notes"),
+        ];
+        let pages = articles
+            .iter()
+            .map(|article| ArticlePage {
+                article,
+                html: String::new(),
+                path: PathBuf::new(),
+                url: format!("/articles/202501/{}.html", article.id.slug),
+            })
+            .collect::<Vec<_>>();
+        let html_templates = WebPageHtmlTemplates {
+            base_url: Some("https://example.com/blog/".to_string()),
+            site: serde_json::from_str(r#"{"title": "Tom & Jerry"}"#).expect("site to be parsed"),
+            ..Default::default()
+        };
+        let feed = |kind| {
+            generate_feed(&html_templates, &pages, kind)
+                .expect("feed to be generated")
+                .expect("feed to be with base_url")
+        };
+
+        assert_eq!(
+            feed(FeedKind::Rss),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><rss version="2.0"><channel>"#,
+                "<title>Tom &amp; Jerry</title><link>https://example.com/blog/</link>",
+                "<description>Tom &amp; Jerry</description>",
+                "<item><title>R&amp;D &lt;1&gt;</title>",
+                "<link>https://example.com/blog/articles/202501/03_a.html</link>",
+                "<guid>https://example.com/blog/articles/202501/03_a.html</guid>",
+                "<description>a &lt; b</description><pubDate>Fri, 03 Jan 2025 00:00:00 +0000</pubDate></item>",
+                "<item><title>Notes</title>",
+                "<link>https://example.com/blog/articles/202501/notes.html</link>",
+                "<guid>https://example.com/blog/articles/202501/notes.html</guid>",
+                "<description>a &lt; b</description><pubDate>Sat, 01 Feb 2025 00:00:00 +0000</pubDate></item>",
+                "</channel></rss>",
+            )
+        );
+        assert_eq!(
+            feed(FeedKind::Atom),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><feed xmlns="http://www.w3.org/2005/Atom">"#,
+                "<title>Tom &amp; Jerry</title><id>https://example.com/blog/</id>",
+                r#"<link href="https://example.com/blog/"/><updated>2025-02-01T00:00:00Z</updated>"#,
+                r#"<entry><title>R&amp;D &lt;1&gt;</title><link href="https://example.com/blog/articles/202501/03_a.html"/>"#,
+                "<id>https://example.com/blog/articles/202501/03_a.html</id>",
+                "<updated>2025-01-03T00:00:00Z</updated><summary>a &lt; b</summary></entry>",
+                r#"<entry><title>Notes</title><link href="https://example.com/blog/articles/202501/notes.html"/>"#,
+                "<id>https://example.com/blog/articles/202501/notes.html</id>",
+                "<updated>2025-02-01T00:00:00Z</updated><summary>a &lt; b</summary></entry>",
+                "</feed>",
+            )
+        );
+    }
+
+    #[test]
+    fn feeds_need_base_url() {
+        let feed = generate_feed(&WebPageHtmlTemplates::default(), &[], FeedKind::Atom);
+
+        assert!(feed.expect("feed to be skipped").is_none());
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use loss72_platemaker_construct::ConstructFile;
+use loss72_platemaker_core::{
+    log,
+    model::{ArticleIdentifier, GenerationContext},
+};
+use loss72_platemaker_template::Placeholder;
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError,
+    articles::{ArticlePage, listing_variables, render_article_list},
+};
+
+pub struct GroupPage {
+    pub group: String,
+    pub html: String,
+    pub path: PathBuf,
+}
+
+impl<'p> From<&'p GroupPage> for ConstructFile<'p> {
+    fn from(value: &'p GroupPage) -> Self {
+        ConstructFile {
+            path: &value.path,
+            content: &value.html,
+        }
+    }
+}
+
+// `index.html` in the directory of each group with `_group.html`, listing the articles of it.
+// The groups only with the assets have no articles to list, so they have no index.
+pub fn generate_group_index(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[ArticlePage],
+    ctx: &GenerationContext,
+) -> OutputResult<Vec<GroupPage>> {
+    if articles.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let Some(template) = &html_templates.group else {
+        log!(step: "_group.html is not found, skipping group index pages");
+        return Ok(vec![]);
+    };
+
+    let mut groups = BTreeMap::<&str, Vec<ArticlePage>>::new();
+    for page in articles {
+        groups
+            .entry(&page.article.id.group)
+            .or_default()
+            .push(page.clone());
+    }
+
+    log!(section: "Generating HTML for index pages of {} groups", groups.len());
+
+    let placeholder = Placeholder::from_strs("${", "}", None)
+        .expect("Regex is validated to include the capture group");
+
+    groups
+        .into_iter()
+        .rev()
+        .map(|(group, mut pages)| {
+            pages.sort_by(|left, right| left.article.id.cmp(&right.article.id).reverse());
+
+            let default_url = group_url(html_templates, &pages[0].article.id);
+            let (year, month, _) = pages[0].article.id.date;
+            let mut placeholder_contents = HashMap::from([
+                ("title", format!("{year}/{month:02}")),
+                ("group", group.to_string()),
+                ("year", year.to_string()),
+                ("month", month.to_string()),
+                ("MM", format!("{month:02}")),
+                (
+                    "articles",
+                    render_article_list(html_templates, &pages, ctx)?,
+                ),
+                ("style", html_templates.index_style.clone()),
+                (
+                    "canonical",
+                    html_templates.canonical_link(&html_templates.page_url(&default_url)),
+                ),
+            ]);
+            placeholder_contents.extend(listing_variables(ctx));
+
+            let html = placeholder
+                .partially_fill_placeholders(template, |name| {
+                    placeholder_contents
+                        .get(name)
+                        .cloned()
+                        .or_else(|| html_templates.site.get(name))
+                })
+                .map_err(WebsiteGenerationError::InvalidPlaceholder)?;
+
+            let (html, path) = html_templates.place_page(&html, &default_url);
+
+            Ok(GroupPage {
+                group: group.to_string(),
+                html,
+                path,
+            })
+        })
+        .collect()
+}
+
+// The directory the article is in without `permalink`, e.g. `/articles/202501/`
+pub(crate) fn group_url(html_templates: &WebPageHtmlTemplates, id: &ArticleIdentifier) -> String {
+    let url = html_templates.default_permalink.url(id);
+    let (directory, _) = url.rsplit_once('/').unwrap_or_default();
+    format!("{directory}/")
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{
+        Article, ArticleIdentifier, ArticleMetadata, GenerationContext,
+    };
+
+    use super::generate_group_index;
+    use crate::{WebPageHtmlTemplates, generate_article_pages};
+
+    fn article(group: &str, (year, month, day): (u32, u8, u8)) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: group.to_string(),
+                slug: format!("{day:02}_article"),
+                date: (year, month, Some(day)),
+            },
+            metadata: ArticleMetadata {
+                title: format!("Article {year}/{month}/{day}"),
+                brief: "brief".to_string(),
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn groups_list_their_articles_newest_first() {
+        let templates = WebPageHtmlTemplates {
+            index_list: "[${title}]".to_string(),
+            group: Some("${title}|${group}|${articles}".to_string()),
+            ..Default::default()
+        };
+        let ctx = GenerationContext::default();
+        let articles = [
+            article("202501", (2025, 1, 3)),
+            article("202412", (2024, 12, 24)),
+            article("202501", (2025, 1, 9)),
+        ];
+        let pages = generate_article_pages(&templates, &articles, &ctx).expect("article pages");
+
+        let groups = generate_group_index(&templates, &pages, &ctx)
+            .expect("group pages")
+            .into_iter()
+            .map(|page| (page.path.to_string_lossy().to_string(), page.html))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            groups,
+            [
+                (
+                    "articles/202501/index.html",
+                    "2025/01|202501|[Article 2025/1/9][Article 2025/1/3]"
+                ),
+                (
+                    "articles/202412/index.html",
+                    "2024/12|202412|[Article 2024/12/24]"
+                ),
+            ]
+            .map(|(path, html)| (path.to_string(), html.to_string()))
+        );
+    }
+}
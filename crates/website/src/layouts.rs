@@ -0,0 +1,236 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use loss72_platemaker_core::fs::Directory;
+use regex::Regex;
+
+use crate::{OutputResult, WebsiteGenerationError};
+
+static EXTENDS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*\$\{\s*extends\s+"([A-Za-z0-9_-]+)"\s*\}"#)
+        .expect("Statically provided regex to be valid")
+});
+
+static BLOCK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{\s*(?:block\s+([A-Za-z0-9_-]+)|(endblock))\s*\}")
+        .expect("Statically provided regex to be valid")
+});
+
+// A template starting with `${extends "base"}` is rendered as `_base.html` in the template
+// directory, with its `${block name}...${endblock}` replaced by the ones of the template.
+// Blocks not in the template keep the content in the base.
+pub(crate) struct Layouts<'dir> {
+    dir: &'dir Directory,
+    loaded: HashMap<String, String>,
+}
+
+struct Block<'t> {
+    name: &'t str,
+    // of the whole `${block}...${endblock}`
+    start: usize,
+    end: usize,
+    content: &'t str,
+}
+
+impl<'dir> Layouts<'dir> {
+    pub(crate) fn new(dir: &'dir Directory) -> Self {
+        Self {
+            dir,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn apply(&mut self, template: &str) -> OutputResult<String> {
+        self.render(template, &HashMap::new(), &mut vec![])
+    }
+
+    // `overrides` are the blocks of the descendants, the nearest one first
+    fn render(
+        &mut self,
+        template: &str,
+        overrides: &HashMap<String, String>,
+        extending: &mut Vec<String>,
+    ) -> OutputResult<String> {
+        let Some(captures) = EXTENDS_REGEX.captures(template) else {
+            return render_blocks(template, overrides);
+        };
+
+        let name = captures[1].to_string();
+        if let Some(start) = extending.iter().position(|extending| *extending == name) {
+            let mut cycle = extending[start..].to_vec();
+            cycle.push(name);
+            return Err(WebsiteGenerationError::CircularLayout(cycle));
+        }
+
+        let mut blocks = top_level_blocks(&template[captures[0].len()..])?
+            .into_iter()
+            .map(|block| (block.name.to_string(), block.content.to_string()))
+            .collect::<HashMap<_, _>>();
+        blocks.extend(overrides.clone());
+
+        let base = self.load(&name)?;
+
+        extending.push(name);
+        let rendered = self.render(&base, &blocks, extending)?;
+        extending.pop();
+
+        Ok(rendered)
+    }
+
+    fn load(&mut self, name: &str) -> OutputResult<String> {
+        if let Some(base) = self.loaded.get(name) {
+            return Ok(base.clone());
+        }
+
+        let base = self
+            .dir
+            .get_file(format!("_{name}.html"))
+            .map_err(|_| WebsiteGenerationError::MissingLayout(name.to_string()))?
+            .read_to_string()?;
+        self.loaded.insert(name.to_string(), base.clone());
+
+        Ok(base)
+    }
+}
+
+fn render_blocks(template: &str, overrides: &HashMap<String, String>) -> OutputResult<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut last = 0;
+
+    for block in top_level_blocks(template)? {
+        rendered.push_str(&template[last..block.start]);
+
+        // Blocks in the overriding one are not overridden again with itself
+        let content = match overrides.get(block.name) {
+            Some(content) => {
+                let mut rest = overrides.clone();
+                rest.remove(block.name);
+                render_blocks(content, &rest)?
+            }
+            None => render_blocks(block.content, overrides)?,
+        };
+        rendered.push_str(&content);
+
+        last = block.end;
+    }
+
+    rendered.push_str(&template[last..]);
+
+    Ok(rendered)
+}
+
+fn top_level_blocks(template: &str) -> OutputResult<Vec<Block<'_>>> {
+    let mut blocks = vec![];
+    // The outermost open block, and the depth of the nested ones in it
+    let mut open = None;
+    let mut depth = 0;
+
+    for captures in BLOCK_REGEX.captures_iter(template) {
+        let tag = captures.get(0).expect("Whole match to be present");
+
+        match (captures.get(1), &open) {
+            (Some(name), None) => open = Some((name.as_str(), tag.start(), tag.end())),
+            (Some(_), Some(_)) => depth += 1,
+            (None, Some(_)) if depth > 0 => depth -= 1,
+            (None, Some((name, start, content_start))) => {
+                blocks.push(Block {
+                    name,
+                    start: *start,
+                    end: tag.end(),
+                    content: &template[*content_start..tag.start()],
+                });
+                open = None;
+            }
+            // Stray ones are left as they are
+            (None, None) => {}
+        }
+    }
+
+    match open {
+        Some((name, _, _)) => Err(WebsiteGenerationError::UnclosedBlock(name.to_string())),
+        None => Ok(blocks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::fs::Directory;
+
+    use super::Layouts;
+    use crate::WebsiteGenerationError;
+
+    fn template_dir(name: &str, files: &[(&str, &str)]) -> Directory {
+        let path = std::env::temp_dir().join(format!("platemaker-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&path).expect("temp dir to be writable");
+        for (file, content) in files {
+            std::fs::write(path.join(file), content).expect("temp dir to be writable");
+        }
+
+        Directory::new(path).expect("temp dir to exist")
+    }
+
+    #[test]
+    fn blocks_override_the_base() {
+        let dir = template_dir(
+            "layouts",
+            &[
+                (
+                    "_base.html",
+                    "<head>${block head}<title>${title}</title>${endblock}</head><body>${block content}${endblock}</body>",
+                ),
+                (
+                    "_page.html",
+                    r#"${extends "base"}${block content}<main>${block main}default${endblock}</main>${endblock}"#,
+                ),
+            ],
+        );
+        let mut layouts = Layouts::new(&dir);
+
+        assert_eq!(
+            layouts
+                .apply("${ extends \"base\" }\n${block content}${content}${endblock}")
+                .expect("layout to be applied"),
+            "<head><title>${title}</title></head><body>${content}</body>"
+        );
+        assert_eq!(
+            layouts
+                .apply(r#"${extends "page"}${block main}${content}${endblock}"#)
+                .expect("layout to be applied"),
+            "<head><title>${title}</title></head><body><main>${content}</main></body>"
+        );
+        assert_eq!(
+            layouts
+                .apply("<p>${block note}as is${endblock}</p>")
+                .expect("template to be rendered"),
+            "<p>as is</p>"
+        );
+
+        assert!(matches!(
+            layouts.apply(r#"${extends "missing"}"#),
+            Err(WebsiteGenerationError::MissingLayout(name)) if name == "missing"
+        ));
+        assert!(matches!(
+            layouts.apply("${block content}"),
+            Err(WebsiteGenerationError::UnclosedBlock(name)) if name == "content"
+        ));
+
+        std::fs::remove_dir_all(dir.path()).ok();
+    }
+
+    #[test]
+    fn circular_layouts_are_reported() {
+        let dir = template_dir(
+            "circular-layouts",
+            &[
+                ("_a.html", r#"${extends "b"}"#),
+                ("_b.html", r#"${extends "a"}"#),
+            ],
+        );
+
+        assert!(matches!(
+            Layouts::new(&dir).apply(r#"${extends "a"}"#),
+            Err(WebsiteGenerationError::CircularLayout(cycle)) if cycle == ["a", "b", "a"]
+        ));
+
+        std::fs::remove_dir_all(dir.path()).ok();
+    }
+}
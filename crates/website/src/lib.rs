@@ -1,14 +1,69 @@
 #![deny(clippy::unwrap_used)]
 
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use articles::{ArticlePage, IndexPage};
+pub use articles::ArticlePage;
+use layouts::Layouts;
 use loss72_platemaker_construct::{ConstructFile, Construction};
-use loss72_platemaker_core::fs::Directory;
+use loss72_platemaker_core::{
+    fs::{Directory, FSNode, File},
+    model::Article,
+    util::escape_xml,
+};
+use partials::Partials;
 
+mod archive;
 mod articles;
+mod assets;
+mod data;
+mod dates;
+mod feed;
+mod groups;
+mod layouts;
+mod links;
+mod manifest;
+mod minify;
+mod not_found;
+mod partials;
+mod permalink;
+mod reading_time;
+mod redirects;
+mod related;
+mod robots;
+mod series;
+mod site;
+mod sitemap;
+mod social;
+mod tags;
+mod text;
 
-pub use articles::{generate_article_html, generate_index_html};
+pub use archive::{ArchivePage, archive_url, generate_archive_pages};
+pub use articles::{
+    LinkedArticles, generate_article_html, generate_article_pages, generate_each_article_page,
+    generate_index_html,
+};
+pub use assets::{ASSET_MANIFEST_FILE, AssetManifest, SHARED_ASSET_DIR, referenced_assets};
+pub use data::{DataValue, SiteData, load_data};
+pub use dates::{DateFormat, InvalidDateFormat};
+pub use feed::{FEED_FILE, FeedKind, generate_feed};
+pub use groups::{GroupPage, generate_group_index};
+pub use links::{BrokenLink, article_references, find_broken_links};
+pub use manifest::{
+    BUILD_MANIFEST_FILE, BuildManifest, BuildTimings, ManifestPage, generate_build_manifest,
+};
+pub use minify::{minify_css, minify_html, minify_js};
+pub use not_found::{NOT_FOUND_FILE, NotFoundPage, generate_not_found_page};
+pub use permalink::{ArticleBase, DEFAULT_PERMALINK, Permalink, PermalinkError, UrlStyle};
+pub use redirects::{RedirectPage, generate_redirect_pages};
+pub use related::{DEFAULT_RELATED_ARTICLES, related_articles};
+pub use robots::{ROBOTS_FILE, generate_robots_txt};
+pub use series::{Series, SeriesPage, collect_series, generate_series_pages};
+pub use site::SiteVariables;
+pub use sitemap::{SITEMAP_FILE, generate_sitemap};
+pub use tags::{TagPage, generate_tag_pages, tag_slug};
 
 #[derive(Debug, thiserror::Error)]
 pub enum WebsiteGenerationError {
@@ -17,6 +72,65 @@ pub enum WebsiteGenerationError {
 
     #[error("I/O Error: {0}")]
     IOError(#[from] std::io::Error),
+
+    #[error("The article '{0}' is dated to the day which does not exist")]
+    InvalidDate(String),
+
+    #[error("The partial '{0}' is included, but _{0}.html is not found in the template directory")]
+    MissingPartial(String),
+
+    #[error("The partials include each other: {}", .0.join(" -> "))]
+    CircularPartial(Vec<String>),
+
+    #[error("The template extends '{0}', but _{0}.html is not found in the template directory")]
+    MissingLayout(String),
+
+    #[error("The layouts extend each other: {}", .0.join(" -> "))]
+    CircularLayout(Vec<String>),
+
+    #[error("The block '{0}' is not closed with ${{endblock}}")]
+    UnclosedBlock(String),
+
+    #[error(
+        "The article '{0}' uses the template '{1}', but _article-{1}.html is not found in the template directory"
+    )]
+    MissingTemplate(String, String),
+
+    #[error(
+        "The data file {}{} is invalid: {message}",
+        path.display(),
+        line.map(|line| format!(":{line}")).unwrap_or_default()
+    )]
+    InvalidDataFile {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+
+    #[error("The template uses '{0}', which is not in the data files")]
+    MissingData(String),
+
+    #[error("'{0}' is a list or a table, which can only be repeated with ${{each}}")]
+    NotDataValue(String),
+
+    #[error("'{0}' is repeated with ${{each}}, but is not a list")]
+    NotDataList(String),
+
+    #[error("${{each {0}}} is not closed with ${{endeach}}")]
+    UnclosedEach(String),
+
+    #[error("The alias '{1}' of the article '{0}' is not a path in the site")]
+    InvalidAlias(String, String),
+
+    #[error("The alias '{alias}' of the article '{article}' is already taken by '{other}'")]
+    ConflictingAlias {
+        alias: String,
+        article: String,
+        other: String,
+    },
+
+    #[error("The article '{article}' links to 'article:{target}', which is none of the articles")]
+    UnknownArticleLink { article: String, target: String },
 }
 
 pub type OutputResult<T> = Result<T, WebsiteGenerationError>;
@@ -27,9 +141,124 @@ pub struct WebPageHtmlTemplates {
     pub index: String,
     pub index_style: String,
     pub index_list: String,
+    pub tag: Option<String>,
+    pub archive: Option<String>,
+    // `_group.html`, of the index page in the directory of each group
+    pub group: Option<String>,
+    // `_series.html`, of the page of each series
+    pub series: Option<String>,
+    // `_404.html`, rendered to `404.html` on the full builds
+    pub not_found: Option<String>,
+    pub syntax_style: String,
+    pub assets: AssetManifest,
+    // next to the articles, which `${asset:...}` refers to
+    pub assets_dir: String,
+    pub permalink: Permalink,
+    // Number of the related articles listed on each article page
+    pub related_count: usize,
+    // Looked up after the per-page variables
+    pub site: SiteVariables,
+    pub base_url: Option<String>,
+    // for the pages other than the articles, which are already in `permalink`
+    pub url_style: Option<UrlStyle>,
+    // Where the articles are without `permalink`, which their relative urls refer from
+    pub default_permalink: Permalink,
+    // `_article-{name}.html` by the name, for the articles with `template = "name"`
+    pub article_templates: HashMap<String, String>,
+    // The link back to the top is put after each `<h2>` section past this many
+    pub back_to_top_after: Option<usize>,
+    // `_back-to-top.html`, or the plain link without it
+    pub back_to_top: String,
+    // of `${date}`, and the other for the articles without the day
+    pub date_format: DateFormat,
+    pub month_date_format: DateFormat,
+}
+
+// Of the templates without any files, for the tests to fill only the ones they need
+#[cfg(test)]
+impl Default for WebPageHtmlTemplates {
+    fn default() -> Self {
+        WebPageHtmlTemplates {
+            article: String::new(),
+            index: String::new(),
+            index_style: String::new(),
+            index_list: String::new(),
+            tag: None,
+            archive: None,
+            group: None,
+            series: None,
+            not_found: None,
+            syntax_style: String::new(),
+            assets: Default::default(),
+            assets_dir: "assets".to_string(),
+            permalink: Default::default(),
+            related_count: 0,
+            site: Default::default(),
+            base_url: None,
+            url_style: None,
+            default_permalink: Default::default(),
+            article_templates: Default::default(),
+            back_to_top_after: None,
+            back_to_top: String::new(),
+            date_format: Default::default(),
+            month_date_format: Default::default(),
+        }
+    }
 }
 
-pub fn load_templates(template_dir: &Directory) -> OutputResult<WebPageHtmlTemplates> {
+impl WebPageHtmlTemplates {
+    // Absolute url of the page at `url`, which is only known with `base_url`
+    pub(crate) fn canonical_url(&self, url: &str) -> Option<String> {
+        self.base_url
+            .as_deref()
+            .map(|base_url| sitemap::absolute_url(base_url, url))
+    }
+
+    // in the configured style, of the page at `default_url` without it
+    pub(crate) fn page_url(&self, default_url: &str) -> String {
+        permalink::styled_url(default_url, self.url_style)
+    }
+
+    // The html with the path to write it, placed as `page_url` says. Relative urls in it
+    // are kept referring to the same files as they do at `default_url`.
+    pub(crate) fn place_page(&self, html: &str, default_url: &str) -> (String, PathBuf) {
+        let url = self.page_url(default_url);
+        let html = if url == default_url {
+            html.to_string()
+        } else {
+            permalink::absolutize_relative_urls(html, default_url)
+        };
+
+        (self.assets.rewrite(&html, &url), permalink::page_path(&url))
+    }
+
+    // `_article-{template}.html` of the article, or `_article.html` without `template`
+    pub fn article_template(&self, article: &Article) -> OutputResult<&str> {
+        match &article.metadata.template {
+            Some(name) => self
+                .article_templates
+                .get(name)
+                .map(String::as_str)
+                .ok_or_else(|| {
+                    WebsiteGenerationError::MissingTemplate(article.id.slug.clone(), name.clone())
+                }),
+            None => Ok(&self.article),
+        }
+    }
+
+    // `<link rel="canonical">` for `${canonical}`, which is left empty rather than relative
+    pub(crate) fn canonical_link(&self, url: &str) -> String {
+        self.canonical_url(url)
+            .map(|url| format!(r#"<link rel="canonical" href="{}">"#, escape_xml(&url)))
+            .unwrap_or_default()
+    }
+}
+
+// `data` is filled into all the templates, which are the same for every page
+pub fn load_templates(
+    template_dir: &Directory,
+    data: &SiteData,
+) -> OutputResult<WebPageHtmlTemplates> {
     let [article, index, index_list] =
         template_dir.get_files(&[&"_article.html", &"_index.html", &"_index-list.html"])?;
     let [index_style] = template_dir
@@ -37,29 +266,97 @@ pub fn load_templates(template_dir: &Directory) -> OutputResult<WebPageHtmlTempl
         .ok_or(std::io::Error::from(std::io::ErrorKind::NotFound))??
         .get_files(&[&"index.css"])?;
 
+    // Layouts and partials are read once for all the templates
+    let mut layouts = Layouts::new(template_dir);
+    let mut partials = Partials::new(template_dir);
+    let mut read_template =
+        |file: File| data.render(&partials.expand(&layouts.apply(&file.read_to_string()?)?)?);
+
+    let mut article_templates = HashMap::new();
+    for file in template_dir
+        .try_iter_content()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+    {
+        let name = file
+            .path()
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("_article-")?.strip_suffix(".html"))
+            .map(str::to_string);
+
+        if let Some(name) = name {
+            article_templates.insert(name, read_template(file)?);
+        }
+    }
+
     Ok(WebPageHtmlTemplates {
-        article: article.read_to_string()?,
-        index: index.read_to_string()?,
+        article: read_template(article)?,
+        index: read_template(index)?,
         index_style: index_style.read_to_string()?,
-        index_list: index_list.read_to_string()?,
+        index_list: read_template(index_list)?,
+        tag: template_dir
+            .get_file("_tag.html")
+            .ok()
+            .map(&mut read_template)
+            .transpose()?,
+        archive: template_dir
+            .get_file("_archive.html")
+            .ok()
+            .map(&mut read_template)
+            .transpose()?,
+        group: template_dir
+            .get_file("_group.html")
+            .ok()
+            .map(&mut read_template)
+            .transpose()?,
+        series: template_dir
+            .get_file("_series.html")
+            .ok()
+            .map(&mut read_template)
+            .transpose()?,
+        not_found: template_dir
+            .get_file("_404.html")
+            .ok()
+            .map(&mut read_template)
+            .transpose()?,
+        syntax_style: String::new(),
+        assets: AssetManifest::default(),
+        assets_dir: "assets".to_string(),
+        permalink: Permalink::default(),
+        related_count: DEFAULT_RELATED_ARTICLES,
+        site: SiteVariables::default(),
+        base_url: None,
+        url_style: None,
+        default_permalink: Permalink::default(),
+        article_templates,
+        back_to_top_after: None,
+        back_to_top: template_dir
+            .get_file("_back-to-top.html")
+            .ok()
+            .map(&mut read_template)
+            .transpose()?
+            .unwrap_or_else(|| articles::DEFAULT_BACK_TO_TOP.to_string()),
+        date_format: DateFormat::default(),
+        month_date_format: DateFormat::month(),
     })
 }
 
 pub fn get_webpage_construction<'a>(
-    index: Option<&'a IndexPage>,
+    root_files: Vec<ConstructFile<'a>>,
     articles: &'a [ArticlePage],
+    redirects: &'a [RedirectPage],
 ) -> Construction<'a> {
+    // Article pages have the paths relative to the destination given by the permalink,
+    // as the redirects to them do by the aliases
     Construction {
         dir: Path::new(""),
-        content: if let Some(index) = index {
-            vec![index.into()]
-        } else {
-            vec![]
-        },
-        sub_dir: vec![Construction {
-            dir: Path::new("articles"),
-            content: articles.iter().map(ConstructFile::from).collect(),
-            sub_dir: vec![],
-        }],
+        content: root_files
+            .into_iter()
+            .chain(articles.iter().map(ConstructFile::from))
+            .chain(redirects.iter().map(ConstructFile::from))
+            .collect(),
+        sub_dir: vec![],
     }
 }
@@ -0,0 +1,382 @@
+use std::path::{Path, PathBuf};
+
+use loss72_platemaker_core::{
+    fs::{Directory, FSNode},
+    log,
+    model::Article,
+    util::{escape_xml, percent_decode},
+};
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError,
+    assets::{next_attribute, resolve},
+};
+
+// of the links to the other articles by their ids, which the markdown leaves as
+// `article:<group>/<slug>` for the pages to link to wherever the articles are
+const ARTICLE_LINK_PREFIX: &str = r#"href="article:"#;
+
+// and of `[[slug]]`, by the slug only or with the group like `[[202501/slug]]`
+const WIKILINK_PREFIX: &str = r#"<a href="wikilink:"#;
+const WIKILINK_END: &str = "</a>";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BrokenLink {
+    // relative to the destination
+    pub page: PathBuf,
+    pub href: String,
+}
+
+// `href`s in the pages of the destination which don't point to any file in it.
+// External ones, `mailto:` and the like are not checked.
+pub fn find_broken_links(destination: &Directory) -> std::io::Result<Vec<BrokenLink>> {
+    let mut broken = vec![];
+
+    for file in destination
+        .try_iter_tree()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(FSNode::into_file)
+        .filter(|file| file.path().extension().is_some_and(|ext| ext == "html"))
+    {
+        let page = file
+            .path()
+            .strip_prefix(destination.path())
+            .unwrap_or(file.path())
+            .to_path_buf();
+        let page_url = format!(
+            "/{}",
+            page.iter()
+                .map(|component| component.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        );
+        let html = file.read_to_string()?;
+
+        let mut rest = html.as_str();
+        while let Some((_, start, end)) = next_attribute(rest, &["href="]) {
+            let href = &rest[start..end];
+            rest = &rest[end..];
+
+            if !resolves(destination.path(), href, &page_url) {
+                broken.push(BrokenLink {
+                    page: page.clone(),
+                    href: href.to_string(),
+                });
+            }
+        }
+    }
+
+    broken.sort_by(|left, right| left.page.cmp(&right.page).then(left.href.cmp(&right.href)));
+
+    Ok(broken)
+}
+
+// `article:<group>/<slug>`, or `article:<year>/<month>/<slug>`, in the html of `page` to the urls
+// of the articles in `articles`, with the fragment after it kept. The ones not linking to any of them are errors.
+pub(crate) fn resolve_article_links(
+    html_templates: &WebPageHtmlTemplates,
+    html: &str,
+    page: &Article,
+    articles: &[&Article],
+) -> OutputResult<String> {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(ARTICLE_LINK_PREFIX) {
+        let (before, reference) = rest.split_at(start + ARTICLE_LINK_PREFIX.len());
+        let Some(end) = reference.find('"') else {
+            break;
+        };
+        let (reference, after) = reference.split_at(end);
+
+        let (id, fragment) = reference.split_at(reference.find('#').unwrap_or(reference.len()));
+        let target = article_by_id(id, articles).ok_or_else(|| {
+            WebsiteGenerationError::UnknownArticleLink {
+                article: page.id.slug.clone(),
+                target: reference.to_string(),
+            }
+        })?;
+
+        output.push_str(&before[..start]);
+        output.push_str(r#"href=""#);
+        output.push_str(&escape_xml(&html_templates.permalink.url(&target.id)));
+        output.push_str(fragment);
+        rest = after;
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+// `[[slug]]` in the html of `page` to the urls of the articles of the slugs in `articles`. The
+// ones linking to none or more than one of them are warned, and left as the labels marked broken.
+pub(crate) fn resolve_wikilinks(
+    html_templates: &WebPageHtmlTemplates,
+    html: &str,
+    page: &Article,
+    articles: &[&Article],
+) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(WIKILINK_PREFIX) {
+        let (before, link) = rest.split_at(start);
+        let Some(end) = link.find(WIKILINK_END) else {
+            break;
+        };
+        let (link, after) = link.split_at(end + WIKILINK_END.len());
+        let Some((reference, label)) = link[WIKILINK_PREFIX.len()..link.len() - WIKILINK_END.len()]
+            .split_once('"')
+            .and_then(|(reference, rest)| Some((reference, rest.split_once('>')?.1)))
+        else {
+            break;
+        };
+
+        let (slug, fragment) = reference.split_at(reference.find('#').unwrap_or(reference.len()));
+        let candidates = articles_by_slug(slug, articles);
+
+        output.push_str(before);
+        match candidates.as_slice() {
+            [target] => output.push_str(&format!(
+                r#"<a href="{}{fragment}" class="wikilink">{label}</a>"#,
+                escape_xml(&html_templates.permalink.url(&target.id))
+            )),
+            candidates => {
+                if candidates.is_empty() {
+                    log!(warn: "[[{}]] in the article '{}' is none of the articles", slug, page.id.slug);
+                } else {
+                    let ids = candidates
+                        .iter()
+                        .map(|article| format!("{}/{}", article.id.group, article.id.slug))
+                        .collect::<Vec<_>>();
+                    log!(
+                        warn: "[[{}]] in the article '{}' may be any of {}, which are told apart with the groups",
+                        slug,
+                        page.id.slug,
+                        ids.join(" and ")
+                    );
+                }
+                output.push_str(&format!(
+                    r#"<span class="wikilink wikilink-broken" title="{slug}">{label}</span>"#
+                ));
+            }
+        }
+        rest = after;
+    }
+    output.push_str(rest);
+
+    output
+}
+
+// The other articles which `html` links to with `article:` and `[[slug]]`, once for each. The
+// ones linking to none or more than one of the articles are not of any.
+pub(crate) fn linked_articles_in<'article>(
+    html: &str,
+    page: &Article,
+    articles: &[&'article Article],
+) -> Vec<&'article Article> {
+    let mut linked = Vec::<&Article>::new();
+
+    for (reference, by_id) in references(html) {
+        let target = if by_id {
+            article_by_id(reference, articles)
+        } else {
+            match articles_by_slug(reference, articles).as_slice() {
+                [target] => Some(*target),
+                _ => None,
+            }
+        };
+
+        if let Some(target) = target
+            && target.id != page.id
+            && linked.iter().all(|article| article.id != target.id)
+        {
+            linked.push(target);
+        }
+    }
+
+    linked
+}
+
+// `article:<group>/<slug>` and `wikilink:<slug>` which `html` links to, once for each and
+// without the fragments, for telling whether the links of an article changed without the others
+pub fn article_references(html: &str) -> Vec<String> {
+    let mut references = references(html)
+        .map(|(reference, by_id)| {
+            let scheme = if by_id { "article" } else { "wikilink" };
+            format!("{scheme}:{reference}")
+        })
+        .collect::<Vec<_>>();
+    references.sort();
+    references.dedup();
+    references
+}
+
+// of `article:`, which are by the ids, and then of `[[slug]]`
+fn references(html: &str) -> impl Iterator<Item = (&str, bool)> {
+    [(ARTICLE_LINK_PREFIX, true), (r#"href="wikilink:"#, false)]
+        .into_iter()
+        .flat_map(move |(prefix, by_id)| {
+            html.split(prefix).skip(1).map(move |rest| {
+                let reference = rest.split(['"', '#']).next().unwrap_or_default();
+                (reference, by_id)
+            })
+        })
+}
+
+// of `<group>/<slug>`, or `<year>/<month>/<slug>` as the directories of the group are
+fn article_by_id<'article>(id: &str, articles: &[&'article Article]) -> Option<&'article Article> {
+    let (group, slug) = id.split_once('/')?;
+    let dated = slug.split_once('/').and_then(|(month, slug)| {
+        let (year, month) = (group.parse::<u32>().ok()?, month.parse::<u8>().ok()?);
+        Some((format!("{year:0>4}{month:0>2}"), slug))
+    });
+
+    articles.iter().copied().find(|article| {
+        (article.id.group == group && article.id.slug == slug)
+            || dated
+                .as_ref()
+                .is_some_and(|(group, slug)| article.id.group == *group && article.id.slug == *slug)
+    })
+}
+
+// of the slug, or `<group>/<slug>` to tell apart the ones of the same slug
+fn articles_by_slug<'article>(
+    slug: &str,
+    articles: &[&'article Article],
+) -> Vec<&'article Article> {
+    articles
+        .iter()
+        .copied()
+        .filter(|article| {
+            article.id.slug == slug || format!("{}/{}", article.id.group, article.id.slug) == slug
+        })
+        .collect()
+}
+
+fn resolves(destination: &Path, href: &str, page_url: &str) -> bool {
+    let href = href.replace("&amp;", "&");
+    let path = &href[..href.find(['?', '#']).unwrap_or(href.len())];
+
+    if path.is_empty() || path.starts_with("//") || path.contains(':') {
+        return true;
+    }
+
+    // which are not served either
+    let Some(resolved) = percent_decode(&resolve(path, page_url)) else {
+        return false;
+    };
+    let target = destination.join(resolved.trim_start_matches('/'));
+
+    // Directories are served with their `index.html`
+    if path.ends_with('/') || target.is_dir() {
+        target.join("index.html").is_file()
+    } else {
+        target.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use loss72_platemaker_core::{
+        fs::Directory,
+        model::{Article, ArticleIdentifier},
+    };
+
+    use super::{BrokenLink, article_by_id, article_references, find_broken_links};
+
+    #[test]
+    fn article_ids_are_the_groups_or_the_dates() {
+        let article = |group: &str, slug: &str| Article {
+            id: ArticleIdentifier {
+                group: group.to_string(),
+                slug: slug.to_string(),
+                date: (2024, 3, None),
+            },
+            metadata: Default::default(),
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        };
+        let articles = [
+            article("202403", "my-slug"),
+            article("202403", "series/part"),
+        ];
+        let articles = articles.iter().collect::<Vec<_>>();
+        let found = |id: &str| article_by_id(id, &articles).map(|article| article.id.slug.as_str());
+
+        assert_eq!(found("2024/03/my-slug"), Some("my-slug"));
+        assert_eq!(found("2024/3/my-slug"), Some("my-slug"));
+        assert_eq!(found("202403/my-slug"), Some("my-slug"));
+        assert_eq!(found("2024/03/series/part"), Some("series/part"));
+        assert_eq!(found("202403/series/part"), Some("series/part"));
+        assert_eq!(found("2024/04/my-slug"), None);
+        assert_eq!(found("my-slug"), None);
+    }
+
+    #[test]
+    fn reports_links_to_missing_files() {
+        let root = std::env::temp_dir().join(format!("platemaker-links-{}", std::process::id()));
+        for (path, content) in [
+            (
+                "index.html",
+                r#"<a href="/articles/202501/03_hello.html">Hello</a><a href="/tags/日記/">Tag</a><a href="https://example.com/missing">Ext</a>"#,
+            ),
+            (
+                "articles/202501/03_hello.html",
+                r##"<a href="../../">Top</a><a href="./04_renamed.html#top">Old</a><a href="#note">Note</a><a href="mailto:me@example.com">Mail</a><link href="/styles/index.css?v=1">"##,
+            ),
+            (
+                "tags/日記/index.html",
+                "<a href='/archive/'>Archive</a><a href='/tags/%E6%97%A5%E8%A8%98/'>Tag</a><a href='/styles/a%zz.css'>Bad</a>",
+            ),
+            ("styles/index.css", ""),
+            // which is not served at the url with the broken escape
+            ("styles/a%zz.css", ""),
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().expect("path to have parent"))
+                .expect("temp dir to be writable");
+            std::fs::write(path, content).expect("temp dir to be writable");
+        }
+
+        let destination = Directory::new(&root).expect("temp dir to exist");
+
+        assert_eq!(
+            find_broken_links(&destination).expect("links to be checked"),
+            [
+                BrokenLink {
+                    page: PathBuf::from("articles/202501/03_hello.html"),
+                    href: "./04_renamed.html#top".to_string(),
+                },
+                BrokenLink {
+                    page: PathBuf::from("tags/日記/index.html"),
+                    href: "/archive/".to_string(),
+                },
+                BrokenLink {
+                    page: PathBuf::from("tags/日記/index.html"),
+                    href: "/styles/a%zz.css".to_string(),
+                },
+            ]
+        );
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn article_references_are_without_the_fragments() {
+        let html = concat!(
+            r#"<a href="article:202501/02_b#top">B</a><a href="wikilink:03_c" class="wikilink">C</a>"#,
+            r#"<a href="article:202501/02_b">B</a><a href="./pic.png">Pic</a>"#,
+        );
+
+        assert_eq!(
+            article_references(html),
+            ["article:202501/02_b", "wikilink:03_c"]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use chrono::{SecondsFormat, Utc};
+use loss72_platemaker_construct::ConstructionPlan;
+use loss72_platemaker_core::util::content_hash;
+use serde::{Serialize, Serializer};
+
+use crate::articles::ArticlePage;
+
+pub const BUILD_MANIFEST_FILE: &str = "manifest.json";
+
+// Bumped on the changes which the consumers have to follow, not on the added fields
+const BUILD_MANIFEST_VERSION: u32 = 1;
+
+// Every page written by a full build, for deploy diffing or link checking
+#[derive(Debug, Serialize)]
+pub struct BuildManifest {
+    pub version: u32,
+    // RFC 3339 in UTC
+    pub generated_at: String,
+    pub count: usize,
+    pub pages: Vec<ManifestPage>,
+    pub timings: BuildTimings,
+}
+
+// Time taken by each step of the build, in milliseconds in the manifest
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct BuildTimings {
+    // Finding the markdown files and the assets
+    #[serde(serialize_with = "milliseconds")]
+    pub scan: Duration,
+    #[serde(serialize_with = "milliseconds")]
+    pub parse: Duration,
+    // The pages, the listings and the other generated files
+    #[serde(serialize_with = "milliseconds")]
+    pub render: Duration,
+    #[serde(serialize_with = "milliseconds")]
+    pub write: Duration,
+    // Copying the templates and the assets, with the image variants and the remote images
+    #[serde(serialize_with = "milliseconds")]
+    pub assets: Duration,
+    #[serde(serialize_with = "milliseconds")]
+    pub total: Duration,
+}
+
+fn milliseconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestPage {
+    // relative to the destination, with `/` as the separator
+    pub path: String,
+    // `group/slug` of the source article, and so the title and the date.
+    // The pages which are not of an article have `null` for them.
+    pub article: Option<String>,
+    pub title: Option<String>,
+    // `YYYY-MM-DD`, or `YYYY-MM` for the articles without the day
+    pub date: Option<String>,
+    // SHA-256 of the content in hex
+    pub hash: String,
+}
+
+pub fn generate_build_manifest(
+    plan: &ConstructionPlan,
+    root: &Path,
+    articles: &[ArticlePage],
+) -> BuildManifest {
+    let articles = articles
+        .iter()
+        .map(|page| (root.join(&page.path), page.article))
+        .collect::<HashMap<_, _>>();
+
+    let mut pages = plan
+        .files
+        .iter()
+        .map(|(path, content)| {
+            let article = articles.get(path);
+            let relative = path.strip_prefix(root).unwrap_or(path);
+
+            ManifestPage {
+                path: relative
+                    .iter()
+                    .map(|component| component.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                article: article.map(|article| format!("{}/{}", article.id.group, article.id.slug)),
+                title: article.map(|article| article.metadata.title.clone()),
+                date: article.map(|article| match article.id.date {
+                    (year, month, Some(day)) => format!("{year:04}-{month:02}-{day:02}"),
+                    (year, month, None) => format!("{year:04}-{month:02}"),
+                }),
+                hash: content_hash(content),
+            }
+        })
+        .collect::<Vec<_>>();
+    pages.sort_by(|left, right| left.path.cmp(&right.path));
+
+    BuildManifest {
+        version: BUILD_MANIFEST_VERSION,
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        count: pages.len(),
+        pages,
+        timings: BuildTimings::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use loss72_platemaker_construct::ConstructionPlan;
+    use loss72_platemaker_core::model::{Article, ArticleIdentifier, ArticleMetadata};
+
+    use super::generate_build_manifest;
+    use crate::articles::ArticlePage;
+
+    #[test]
+    fn pages_are_listed_with_their_articles() {
+        let article = Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: "03_hello".to_string(),
+                date: (2025, 1, Some(3)),
+            },
+            metadata: ArticleMetadata {
+                title: "Hello".to_string(),
+                brief: String::new(),
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        };
+        let pages = [ArticlePage {
+            article: &article,
+            html: "<p>hello</p>".to_string(),
+            path: PathBuf::from("articles/202501/03_hello.html"),
+            url: "/articles/202501/03_hello.html".to_string(),
+        }];
+        let root = Path::new("/out");
+        let plan = ConstructionPlan {
+            dirs: vec![],
+            files: vec![
+                (root.join("index.html"), "<ul></ul>"),
+                (root.join("articles/202501/03_hello.html"), "<p>hello</p>"),
+            ],
+        };
+
+        let mut manifest = generate_build_manifest(&plan, root, &pages);
+
+        assert_eq!(manifest.version, 1);
+        assert_eq!(manifest.count, 2);
+        assert_eq!(
+            manifest
+                .pages
+                .iter()
+                .map(|page| (
+                    page.path.as_str(),
+                    page.article.as_deref(),
+                    page.title.as_deref(),
+                    page.date.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "articles/202501/03_hello.html",
+                    Some("202501/03_hello"),
+                    Some("Hello"),
+                    Some("2025-01-03")
+                ),
+                ("index.html", None, None, None),
+            ]
+        );
+        assert_eq!(manifest.pages[1].hash.len(), 64);
+
+        manifest.timings.parse = Duration::from_micros(12_900);
+        let json = serde_json::to_value(&manifest).expect("manifest to be serialized");
+        assert_eq!(json["timings"]["parse"], 12);
+        assert_eq!(json["timings"]["total"], 0);
+    }
+}
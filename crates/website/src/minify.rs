@@ -0,0 +1,260 @@
+use std::{iter::Peekable, str::Chars};
+
+// Whitespace is significant in these, so they are written as they are
+const PRESERVED_TAGS: [&str; 4] = ["pre", "code", "textarea", "script"];
+
+pub fn minify_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut pending_space = false;
+
+    while let Some(char) = rest.chars().next() {
+        // Conditional comments like `<!--[if IE]>` are kept
+        if rest.starts_with("<!--") && !rest.starts_with("<!--[") {
+            let end = rest.find("-->").map_or(rest.len(), |index| index + 3);
+            rest = &rest[end..];
+            continue;
+        }
+
+        if char.is_whitespace() {
+            pending_space = true;
+            rest = &rest[char.len_utf8()..];
+            continue;
+        }
+
+        if pending_space && !output.is_empty() {
+            output.push(' ');
+        }
+        pending_space = false;
+
+        if char != '<' {
+            output.push(char);
+            rest = &rest[char.len_utf8()..];
+            continue;
+        }
+
+        let tag = &rest[..tag_end(rest)];
+        output.push_str(tag);
+        rest = &rest[tag.len()..];
+
+        if let Some(name) = preserved_tag_name(tag) {
+            let end = rest
+                .to_ascii_lowercase()
+                .find(&format!("</{name}"))
+                .unwrap_or(rest.len());
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+
+    output
+}
+
+fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+
+    for (index, char) in tag.char_indices() {
+        match (quote, char) {
+            (None, '"' | '\'') => quote = Some(char),
+            (Some(open), _) if open == char => quote = None,
+            (None, '>') => return index + 1,
+            _ => {}
+        }
+    }
+
+    tag.len()
+}
+
+fn preserved_tag_name(tag: &str) -> Option<&'static str> {
+    let name = tag
+        .strip_prefix('<')?
+        .split(|char: char| char.is_whitespace() || char == '>' || char == '/')
+        .next()?;
+
+    PRESERVED_TAGS
+        .into_iter()
+        .find(|preserved| preserved.eq_ignore_ascii_case(name))
+}
+
+// Comments and the whitespace without meaning, keeping the strings. The spaces in the selectors
+// and around `(` of the media queries are kept, and only the ones next to `{};,>` and after `:`
+// are dropped.
+pub fn minify_css(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    let mut pending_space = false;
+
+    while let Some(char) = chars.next() {
+        match char {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                while let Some(char) = chars.next() {
+                    if char == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+                pending_space = true;
+            }
+            char if char.is_whitespace() => pending_space = true,
+            char => {
+                let dropped = |char: char| matches!(char, '{' | '}' | ';' | ',' | '>');
+                if pending_space
+                    && !output.is_empty()
+                    && !dropped(char)
+                    && !output.ends_with(|last| dropped(last) || last == ':')
+                {
+                    output.push(' ');
+                }
+                pending_space = false;
+
+                output.push(char);
+                if matches!(char, '"' | '\'') {
+                    push_quoted(&mut output, &mut chars, char);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+// Comments and the indents, keeping the line breaks for the statements without `;`.
+// `/` is taken as a regular expression after the operators and the brackets, which are
+// kept as they are with the strings.
+pub fn minify_js(js: &str) -> String {
+    let mut output = String::with_capacity(js.len());
+    let mut chars = js.chars().peekable();
+    let mut pending = None;
+
+    while let Some(char) = chars.next() {
+        match char {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|char| *char != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut newline = false;
+                while let Some(char) = chars.next() {
+                    newline |= char == '\n';
+                    if char == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+                pending = Some(if newline || pending == Some('\n') {
+                    '\n'
+                } else {
+                    ' '
+                });
+            }
+            '\n' => pending = Some('\n'),
+            char if char.is_whitespace() => {
+                pending = pending.or(Some(' '));
+            }
+            char => {
+                let last = output.trim_end().chars().last();
+                if let Some(space) = pending.take().filter(|_| last.is_some()) {
+                    output.push(space);
+                }
+
+                output.push(char);
+                match char {
+                    '"' | '\'' | '`' => push_quoted(&mut output, &mut chars, char),
+                    '/' if last.is_none_or(|last| "(,=:[!&|?{};+-*%<>~^".contains(last)) => {
+                        push_regex(&mut output, &mut chars)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    output
+}
+
+// The rest of a string after the opening quote, with the escaped characters
+fn push_quoted(output: &mut String, chars: &mut Peekable<Chars>, quote: char) {
+    while let Some(char) = chars.next() {
+        output.push(char);
+        if char == '\\' {
+            output.extend(chars.next());
+        } else if char == quote {
+            break;
+        }
+    }
+}
+
+// The rest of a regular expression after the opening `/`, in which `/` of the classes
+// like `[/]` doesn't close it
+fn push_regex(output: &mut String, chars: &mut Peekable<Chars>) {
+    let mut class = false;
+
+    while let Some(char) = chars.next() {
+        output.push(char);
+        match char {
+            '\\' => output.extend(chars.next()),
+            '[' => class = true,
+            ']' => class = false,
+            '/' if !class => break,
+            '\n' => break,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{minify_css, minify_html, minify_js};
+
+    #[test]
+    fn minifies_outside_of_preserved_blocks() {
+        let html = concat!(
+            "<!DOCTYPE html>\n<html>\n  <body>\n    <!-- comment -->\n",
+            "    <p title=\"a  b\">Hello,\n      <b>world</b>  !</p>\n",
+            "    <pre><code class=\"block\">fn main() {\n    <!-- kept -->\n}</code></pre>\n",
+            "    <textarea>  a\n  b</textarea>\n",
+            "    <SCRIPT>if (a  <  b) {}\n</SCRIPT>\n  </body>\n</html>\n",
+        );
+
+        assert_eq!(
+            minify_html(html),
+            concat!(
+                "<!DOCTYPE html> <html> <body> ",
+                "<p title=\"a  b\">Hello, <b>world</b> !</p> ",
+                "<pre><code class=\"block\">fn main() {\n    <!-- kept -->\n}</code></pre> ",
+                "<textarea>  a\n  b</textarea> ",
+                "<SCRIPT>if (a  <  b) {}\n</SCRIPT> </body> </html>",
+            )
+        );
+    }
+
+    #[test]
+    fn minifies_styles_keeping_the_meaning() {
+        let css = concat!(
+            "/* reset */\n@media screen and (max-width: 600px) {\n",
+            "  a :hover,\n  nav > p {\n    content: \"a  /* b */\";\n    margin: 0 auto;\n  }\n}\n",
+        );
+
+        assert_eq!(
+            minify_css(css),
+            "@media screen and (max-width:600px){a :hover,nav>p{content:\"a  /* b */\";margin:0 auto;}}"
+        );
+    }
+
+    #[test]
+    fn minifies_scripts_keeping_the_lines() {
+        let js = concat!(
+            "// header\nconst url = \"https://example.com\" // trailing\n",
+            "const re = /\\/\\/[/]x/g;  /* block */ const half = a / 2\n\n",
+            "    if (x) {\n      go(`a  ${b}`)\n    }\n",
+        );
+
+        assert_eq!(
+            minify_js(js),
+            concat!(
+                "const url = \"https://example.com\"\n",
+                "const re = /\\/\\/[/]x/g; const half = a / 2\n",
+                "if (x) {\ngo(`a  ${b}`)\n}",
+            )
+        );
+    }
+}
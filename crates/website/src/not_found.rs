@@ -0,0 +1,104 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use loss72_platemaker_construct::ConstructFile;
+use loss72_platemaker_core::{log, model::GenerationContext};
+use loss72_platemaker_template::Placeholder;
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError, articles::listing_variables,
+    permalink::absolutize_relative_urls,
+};
+
+// Where the hosts look for the page of the missing urls
+pub const NOT_FOUND_FILE: &str = "404.html";
+
+pub struct NotFoundPage {
+    pub html: String,
+    pub path: PathBuf,
+}
+
+impl<'p> From<&'p NotFoundPage> for ConstructFile<'p> {
+    fn from(value: &'p NotFoundPage) -> Self {
+        ConstructFile {
+            path: &value.path,
+            content: &value.html,
+        }
+    }
+}
+
+// `_404.html` with the variables of the index but the articles
+pub fn generate_not_found_page(
+    html_templates: &WebPageHtmlTemplates,
+    ctx: &GenerationContext,
+) -> OutputResult<Option<NotFoundPage>> {
+    let Some(template) = &html_templates.not_found else {
+        return Ok(None);
+    };
+
+    log!(section: "Generating HTML for {}", NOT_FOUND_FILE);
+
+    let placeholder = Placeholder::from_strs("${", "}", None)
+        .expect("Regex is validated to include the capture group");
+
+    let mut placeholder_contents = HashMap::from([("style", html_templates.index_style.clone())]);
+    placeholder_contents.extend(listing_variables(ctx));
+
+    let html = placeholder
+        .partially_fill_placeholders(template, |name| {
+            placeholder_contents
+                .get(name)
+                .cloned()
+                .or_else(|| html_templates.site.get(name))
+        })
+        .map_err(WebsiteGenerationError::InvalidPlaceholder)?;
+
+    // It is served at any missing url, where the relative ones would point elsewhere
+    let url = format!("/{NOT_FOUND_FILE}");
+    let html = absolutize_relative_urls(&html, &url);
+
+    Ok(Some(NotFoundPage {
+        html: html_templates.assets.rewrite(&html, &url),
+        path: PathBuf::from(NOT_FOUND_FILE),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::GenerationContext;
+
+    use super::generate_not_found_page;
+    use crate::{SiteVariables, WebPageHtmlTemplates};
+
+    #[test]
+    fn rendered_with_the_site_variables() {
+        let mut html_templates = WebPageHtmlTemplates {
+            index_style: "body{}".to_string(),
+            site: serde_json::from_str::<SiteVariables>(r#"{"title": "Loss72"}"#)
+                .expect("site variables to be parsed"),
+            ..Default::default()
+        };
+        let ctx = GenerationContext {
+            build_id: Some("0123abcd".to_string()),
+            ..Default::default()
+        };
+
+        assert!(
+            generate_not_found_page(&html_templates, &ctx)
+                .expect("nothing to fail")
+                .is_none()
+        );
+
+        html_templates.not_found = Some(
+            r#"<style>${style}</style><h1>${site.title}</h1><a href="./?v=${build_id}">Top</a>"#
+                .to_string(),
+        );
+        let page = generate_not_found_page(&html_templates, &ctx)
+            .expect("page to be rendered")
+            .expect("template to be given");
+        assert_eq!(page.path.to_str(), Some("404.html"));
+        assert_eq!(
+            page.html,
+            r#"<style>body{}</style><h1>Loss72</h1><a href="/?v=0123abcd">Top</a>"#
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use loss72_platemaker_core::fs::Directory;
+use loss72_platemaker_template::Placeholder;
+use regex::Regex;
+
+use crate::{OutputResult, WebsiteGenerationError};
+
+// `${> header}` includes `_header.html` in the template directory
+pub(crate) struct Partials<'dir> {
+    dir: &'dir Directory,
+    placeholder: Placeholder,
+    // name -> content with its own includes expanded
+    loaded: HashMap<String, String>,
+}
+
+impl<'dir> Partials<'dir> {
+    pub(crate) fn new(dir: &'dir Directory) -> Self {
+        Self {
+            dir,
+            placeholder: Placeholder::from_strs(
+                "${>",
+                "}",
+                Regex::new("[A-Za-z0-9_-]+").expect("Statically provided regex to be valid"),
+            )
+            .expect("Regex is validated to include the capture group"),
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn expand(&mut self, template: &str) -> OutputResult<String> {
+        self.expand_in(template, &mut vec![])
+    }
+
+    // `including` is the chain of the partials being expanded, to find the circular ones
+    fn expand_in(&mut self, template: &str, including: &mut Vec<String>) -> OutputResult<String> {
+        let names = self
+            .placeholder
+            .parse_used_placeholders(template)
+            .collect::<Vec<_>>();
+
+        for name in &names {
+            self.load(name, including)?;
+        }
+
+        Ok(self.placeholder.fill_placeholders(template, |name| {
+            self.loaded.get(name).cloned().unwrap_or_default()
+        }))
+    }
+
+    fn load(&mut self, name: &str, including: &mut Vec<String>) -> OutputResult<()> {
+        if let Some(start) = including.iter().position(|including| including == name) {
+            let mut cycle = including[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(WebsiteGenerationError::CircularPartial(cycle));
+        }
+
+        if self.loaded.contains_key(name) {
+            return Ok(());
+        }
+
+        let content = self
+            .dir
+            .get_file(format!("_{name}.html"))
+            .map_err(|_| WebsiteGenerationError::MissingPartial(name.to_string()))?
+            .read_to_string()?;
+
+        including.push(name.to_string());
+        let expanded = self.expand_in(&content, including)?;
+        including.pop();
+
+        self.loaded.insert(name.to_string(), expanded);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::fs::Directory;
+
+    use super::Partials;
+    use crate::WebsiteGenerationError;
+
+    fn template_dir(name: &str, files: &[(&str, &str)]) -> Directory {
+        let path = std::env::temp_dir().join(format!("platemaker-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&path).expect("temp dir to be writable");
+        for (file, content) in files {
+            std::fs::write(path.join(file), content).expect("temp dir to be writable");
+        }
+
+        Directory::new(path).expect("temp dir to exist")
+    }
+
+    #[test]
+    fn expands_nested_partials() {
+        let dir = template_dir(
+            "partials",
+            &[
+                ("_header.html", "<header>${> nav }</header>"),
+                ("_nav.html", "<nav>${title}</nav>"),
+            ],
+        );
+
+        let mut partials = Partials::new(&dir);
+        assert_eq!(
+            partials
+                .expand("${> header}<main>${content}</main>${>nav}")
+                .expect("partials to be expanded"),
+            "<header><nav>${title}</nav></header><main>${content}</main><nav>${title}</nav>"
+        );
+
+        assert!(matches!(
+            partials.expand("${> footer}"),
+            Err(WebsiteGenerationError::MissingPartial(name)) if name == "footer"
+        ));
+
+        std::fs::remove_dir_all(dir.path()).ok();
+    }
+
+    #[test]
+    fn circular_partials_are_reported() {
+        let dir = template_dir(
+            "circular-partials",
+            &[
+                ("_a.html", "${> b}"),
+                ("_b.html", "${> c}"),
+                ("_c.html", "${> a}"),
+            ],
+        );
+
+        assert!(matches!(
+            Partials::new(&dir).expand("<body>${> a}</body>"),
+            Err(WebsiteGenerationError::CircularPartial(cycle)) if cycle == ["a", "b", "c", "a"]
+        ));
+
+        std::fs::remove_dir_all(dir.path()).ok();
+    }
+}
@@ -0,0 +1,357 @@
+use std::path::{Component, Path, PathBuf};
+
+use loss72_platemaker_core::model::ArticleIdentifier;
+use serde::Deserialize;
+
+use crate::assets::{map_url_attributes, resolve};
+
+pub const DEFAULT_PERMALINK: &str = "/articles/:group/:slug.html";
+
+const DEFAULT_ARTICLE_BASE: &str = "articles";
+
+const TOKENS: [&str; 5] = ["year", "month", "day", "group", "slug"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum PermalinkError {
+    #[error("The permalink `{0}` should start with `/`.")]
+    NotAbsolute(String),
+
+    #[error("The permalink `{0}` contains the unknown token `:{1}`. Available tokens are {tokens}.", tokens = TOKENS.map(|token| format!("`:{token}`")).join(", "))]
+    UnknownToken(String, String),
+
+    #[error("The permalink `{0}` should contain `:slug` for each article to have its own page.")]
+    NoSlug(String),
+
+    #[error("The article base path `{0}` should not go out of the destination with `..`.")]
+    EscapingBase(String),
+}
+
+// Url of the article pages, e.g. `/:year/:month/:slug/`.
+// The ones ending with `/` are written to `index.html` in the directory.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Permalink {
+    template: String,
+    style: Option<UrlStyle>,
+}
+
+impl Default for Permalink {
+    fn default() -> Self {
+        Permalink {
+            template: DEFAULT_PERMALINK.to_string(),
+            style: None,
+        }
+    }
+}
+
+// How the urls of the pages end, for the hosts which only serve one of them.
+// Without it, the permalink decides for the articles and the others are `PrettyUrls`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum UrlStyle {
+    // `/foo/`, written to `foo/index.html`
+    #[serde(rename = "pretty")]
+    PrettyUrls,
+    // `/foo.html`
+    #[serde(rename = "file")]
+    FileUrls,
+}
+
+// `url` of a page in either style, e.g. `/tags/rust/`
+pub(crate) fn styled_url(url: &str, style: Option<UrlStyle>) -> String {
+    match style {
+        Some(UrlStyle::PrettyUrls) if !url.ends_with('/') => {
+            format!("{}/", url.strip_suffix(".html").unwrap_or(url))
+        }
+        Some(UrlStyle::FileUrls) if url.ends_with('/') && url != "/" => {
+            format!("{}.html", url.trim_end_matches('/'))
+        }
+        _ => url.to_string(),
+    }
+}
+
+// Directory in the destination of the articles and their assets, e.g. `blog`.
+// The empty one places them at the site root.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct ArticleBase(String);
+
+impl Default for ArticleBase {
+    fn default() -> Self {
+        ArticleBase(DEFAULT_ARTICLE_BASE.to_string())
+    }
+}
+
+impl TryFrom<String> for ArticleBase {
+    type Error = PermalinkError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let base = value.trim_matches('/');
+
+        if Path::new(base)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(PermalinkError::EscapingBase(value));
+        }
+
+        Ok(ArticleBase(base.to_string()))
+    }
+}
+
+impl ArticleBase {
+    // Relative to the destination
+    pub fn path(&self) -> &Path {
+        Path::new(&self.0)
+    }
+
+    // `DEFAULT_PERMALINK` under this directory, for the articles without `permalink`
+    pub fn permalink(&self) -> Permalink {
+        let template = if self.0.is_empty() {
+            "/:group/:slug.html".to_string()
+        } else {
+            format!("/{}/:group/:slug.html", self.0)
+        };
+
+        Permalink {
+            template,
+            style: None,
+        }
+    }
+}
+
+// Relative to the destination
+pub(crate) fn page_path(url: &str) -> PathBuf {
+    let path = Path::new(url.trim_start_matches('/'));
+
+    if url.ends_with('/') {
+        path.join("index.html")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+impl TryFrom<String> for Permalink {
+    type Error = PermalinkError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if !value.starts_with('/') {
+            return Err(PermalinkError::NotAbsolute(value));
+        }
+
+        if let Some(unknown) = tokens(&value).find(|token| !TOKENS.contains(token)) {
+            return Err(PermalinkError::UnknownToken(
+                value.clone(),
+                unknown.to_string(),
+            ));
+        }
+
+        if !tokens(&value).any(|token| token == "slug") {
+            return Err(PermalinkError::NoSlug(value));
+        }
+
+        Ok(Permalink {
+            template: value,
+            style: None,
+        })
+    }
+}
+
+impl Permalink {
+    pub fn with_style(self, style: Option<UrlStyle>) -> Self {
+        Permalink { style, ..self }
+    }
+
+    pub fn url(&self, id: &ArticleIdentifier) -> String {
+        let (year, month, day) = id.date;
+
+        let mut url = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(index) = rest.find(':') {
+            url.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+
+            let token = token_at(rest);
+            match token {
+                "year" => url.push_str(&year.to_string()),
+                "month" => url.push_str(&format!("{month:02}")),
+                "day" => match day {
+                    Some(day) => url.push_str(&format!("{day:02}")),
+                    // Dropped with the separator after it, e.g. `/2025/01/slug/` for
+                    // `/:year/:month/:day/:slug/`
+                    None => {
+                        let after = &rest[token.len()..];
+                        let separator = after
+                            .chars()
+                            .next()
+                            .filter(|char| "/-_".contains(*char) && url.ends_with(*char));
+
+                        if let Some(separator) = separator {
+                            rest = &after[separator.len_utf8()..];
+                            continue;
+                        }
+                    }
+                },
+                "group" => url.push_str(&id.group),
+                "slug" => url.push_str(&id.slug),
+                _ => url.push(':'),
+            }
+            rest = &rest[token.len()..];
+        }
+
+        url.push_str(rest);
+        styled_url(&url, self.style)
+    }
+
+    // Relative to the destination
+    pub fn path(&self, id: &ArticleIdentifier) -> PathBuf {
+        page_path(&self.url(id))
+    }
+}
+
+fn tokens(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split(':')
+        .skip(1)
+        .map(token_at)
+        .filter(|token| !token.is_empty())
+}
+
+fn token_at(str: &str) -> &str {
+    let length = str
+        .find(|char: char| !(char.is_ascii_alphanumeric() || char == '_'))
+        .unwrap_or(str.len());
+
+    &str[..length]
+}
+
+// Articles refer to their assets relative to the default location, next to the assets of
+// the group. Those references are made absolute for the pages placed anywhere else.
+pub(crate) fn absolutize_relative_urls(html: &str, page_url: &str) -> String {
+    map_url_attributes(html, |url| absolute_url(url, page_url))
+}
+
+fn absolute_url(url: &str, page_url: &str) -> String {
+    if url.is_empty() || url.starts_with('/') || url.starts_with('#') || url.contains(':') {
+        return url.to_string();
+    }
+
+    let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    format!("{}{suffix}", resolve(path, page_url))
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::ArticleIdentifier;
+
+    use super::{
+        ArticleBase, Permalink, PermalinkError, UrlStyle, absolutize_relative_urls, styled_url,
+    };
+
+    #[test]
+    fn article_base_stays_in_the_destination() {
+        let id = ArticleIdentifier {
+            group: "202501".to_string(),
+            slug: "03_hello".to_string(),
+            date: (2025, 1, Some(3)),
+        };
+        let base = |value: &str| ArticleBase::try_from(value.to_string());
+
+        assert_eq!(ArticleBase::default().permalink(), Permalink::default());
+        assert_eq!(
+            base("/blog/").map(|base| base.permalink().url(&id)).ok(),
+            Some("/blog/202501/03_hello.html".to_string())
+        );
+        assert_eq!(
+            base("").map(|base| base.permalink().url(&id)).ok(),
+            Some("/202501/03_hello.html".to_string())
+        );
+        assert!(matches!(
+            base("blog/../.."),
+            Err(PermalinkError::EscapingBase(_))
+        ));
+    }
+
+    #[test]
+    fn fills_tokens_of_the_permalink() {
+        let id = ArticleIdentifier {
+            group: "202501".to_string(),
+            slug: "03_hello".to_string(),
+            date: (2025, 1, Some(3)),
+        };
+
+        let default = Permalink::default();
+        assert_eq!(default.url(&id), "/articles/202501/03_hello.html");
+        assert_eq!(
+            default.path(&id).to_str(),
+            Some("articles/202501/03_hello.html")
+        );
+
+        let permalink = Permalink::try_from("/:year/:month/:day/:slug/".to_string())
+            .expect("permalink to be valid");
+        assert_eq!(permalink.url(&id), "/2025/01/03/03_hello/");
+        assert_eq!(
+            permalink.path(&id).to_str(),
+            Some("2025/01/03/03_hello/index.html")
+        );
+
+        let day_less = ArticleIdentifier {
+            slug: "hello".to_string(),
+            date: (2025, 1, None),
+            ..id.clone()
+        };
+        assert_eq!(permalink.url(&day_less), "/2025/01/hello/");
+        assert_eq!(default.url(&day_less), "/articles/202501/hello.html");
+
+        let pretty = Permalink::default().with_style(Some(UrlStyle::PrettyUrls));
+        assert_eq!(pretty.url(&id), "/articles/202501/03_hello/");
+        assert_eq!(
+            pretty.path(&id).to_str(),
+            Some("articles/202501/03_hello/index.html")
+        );
+        let file = permalink.with_style(Some(UrlStyle::FileUrls));
+        assert_eq!(file.url(&id), "/2025/01/03/03_hello.html");
+        assert_eq!(file.path(&id).to_str(), Some("2025/01/03/03_hello.html"));
+
+        assert_eq!(
+            styled_url("/tags/rust/", Some(UrlStyle::FileUrls)),
+            "/tags/rust.html"
+        );
+        assert_eq!(styled_url("/", Some(UrlStyle::FileUrls)), "/");
+        assert_eq!(styled_url("/tags/rust/", None), "/tags/rust/");
+
+        assert!(matches!(
+            Permalink::try_from("/:year/:title/".to_string()),
+            Err(PermalinkError::UnknownToken(_, token)) if token == "title"
+        ));
+        assert!(matches!(
+            Permalink::try_from("/:year/:month/".to_string()),
+            Err(PermalinkError::NoSlug(_))
+        ));
+        assert!(matches!(
+            Permalink::try_from(":slug.html".to_string()),
+            Err(PermalinkError::NotAbsolute(_))
+        ));
+    }
+
+    #[test]
+    fn relative_urls_are_made_absolute() {
+        assert_eq!(
+            absolutize_relative_urls(
+                concat!(
+                    r#"<img src="./assets/pic.png" srcset="./assets/pic-200w.png 200w, ./assets/pic.png 800w">"#,
+                    r#"<a href="../202412/24_xmas.html#top">"#,
+                    r##"<a href="#note">"##,
+                    r#"<a href="https://example.com/">"#,
+                ),
+                "/articles/202501/03_hello.html",
+            ),
+            concat!(
+                r#"<img src="/articles/202501/assets/pic.png" srcset="/articles/202501/assets/pic-200w.png 200w, /articles/202501/assets/pic.png 800w">"#,
+                r#"<a href="/articles/202412/24_xmas.html#top">"#,
+                r##"<a href="#note">"##,
+                r#"<a href="https://example.com/">"#,
+            )
+        );
+    }
+}
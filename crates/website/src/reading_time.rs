@@ -0,0 +1,103 @@
+use loss72_platemaker_core::model::{Article, GenerationContext};
+
+use crate::text::strip_tags;
+
+const CJK_LANGS: [&str; 3] = ["ja", "zh", "ko"];
+
+pub(crate) fn reading_minutes(article: &Article, ctx: &GenerationContext) -> u32 {
+    let text = strip_tags(&article.content);
+
+    // `lang` in the frontmatter overrides the detection, e.g. for English articles quoting
+    // Japanese or Japanese ones full of code
+    let cjk = article
+        .metadata
+        .lang
+        .as_deref()
+        .map(|lang| {
+            lang.split(['-', '_'])
+                .next()
+                .unwrap_or(lang)
+                .to_ascii_lowercase()
+        })
+        .map(|lang| CJK_LANGS.contains(&lang.as_str()));
+
+    let (words, cjk_chars) = match cjk {
+        Some(true) => (0, text.chars().filter(|char| !char.is_whitespace()).count()),
+        Some(false) => (text.split_whitespace().count(), 0),
+        None => count_mixed(&text),
+    };
+
+    let minutes = words as f64 / ctx.words_per_minute() as f64
+        + cjk_chars as f64 / ctx.cjk_characters_per_minute() as f64;
+
+    (minutes.ceil() as u32).max(1)
+}
+
+fn count_mixed(text: &str) -> (usize, usize) {
+    let cjk_chars = text.chars().filter(|char| is_cjk(*char)).count();
+    let words = text
+        .split(|char: char| char.is_whitespace() || is_cjk(char))
+        .filter(|word| !word.is_empty())
+        .count();
+
+    (words, cjk_chars)
+}
+
+fn is_cjk(char: char) -> bool {
+    matches!(char,
+        '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{
+        Article, ArticleIdentifier, ArticleMetadata, GenerationContext,
+    };
+
+    use super::reading_minutes;
+
+    fn article(content: String, lang: Option<&str>) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: "01_article".to_string(),
+                date: (2025, 1, Some(1)),
+            },
+            metadata: ArticleMetadata {
+                title: "Article".to_string(),
+                brief: "brief".to_string(),
+                lang: lang.map(str::to_string),
+                ..Default::default()
+            },
+            content,
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn counts_words_and_cjk_characters() {
+        let ctx = GenerationContext {
+            words_per_minute: Some(100),
+            cjk_characters_per_minute: Some(100),
+            ..Default::default()
+        };
+        let words = "<p>word</p>".repeat(150);
+        let kanji = "<p>漢字</p>".repeat(150);
+
+        assert_eq!(reading_minutes(&article(words.clone(), None), &ctx), 2);
+        assert_eq!(reading_minutes(&article(kanji.clone(), None), &ctx), 3);
+        assert_eq!(reading_minutes(&article(words + &kanji, None), &ctx), 5);
+        assert_eq!(
+            reading_minutes(&article("本日は晴天なり".repeat(100), Some("en")), &ctx),
+            1
+        );
+        assert_eq!(reading_minutes(&article(String::new(), None), &ctx), 1);
+    }
+}
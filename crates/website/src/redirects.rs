@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use loss72_platemaker_construct::ConstructFile;
+use loss72_platemaker_core::util::escape_xml;
+
+use crate::{OutputResult, WebPageHtmlTemplates, WebsiteGenerationError, articles::ArticlePage};
+
+// A page at an alias of an article, which sends the visitors to the article
+#[derive(Debug)]
+pub struct RedirectPage {
+    // relative to the destination
+    pub path: PathBuf,
+    pub html: String,
+}
+
+impl<'p> From<&'p RedirectPage> for ConstructFile<'p> {
+    fn from(value: &'p RedirectPage) -> Self {
+        ConstructFile {
+            path: &value.path,
+            content: &value.html,
+        }
+    }
+}
+
+// For `aliases` in the frontmatter, which are the urls from the site root like
+// `/2024/old-post/`. The ones without the extension are taken as directories. `reserved` are
+// the other pages and files of the site, like the index and the feed, which no alias may take.
+pub fn generate_redirect_pages(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[ArticlePage],
+    reserved: &[&Path],
+) -> OutputResult<Vec<RedirectPage>> {
+    // path -> `group/slug` of the article which is there or has it as an alias, or the path of
+    // the other one
+    let mut taken = reserved
+        .iter()
+        .map(|path| (path.to_path_buf(), path.display().to_string()))
+        .chain(
+            articles
+                .iter()
+                .map(|page| (page.path.clone(), article_name(page))),
+        )
+        .collect::<HashMap<_, _>>();
+
+    let mut redirects = vec![];
+    for page in articles {
+        for alias in &page.article.metadata.aliases {
+            let (url, path) = alias_page(html_templates, alias).ok_or_else(|| {
+                WebsiteGenerationError::InvalidAlias(article_name(page), alias.clone())
+            })?;
+
+            if let Some(other) = taken.get(&path) {
+                return Err(WebsiteGenerationError::ConflictingAlias {
+                    alias: url,
+                    article: article_name(page),
+                    other: other.clone(),
+                });
+            }
+            taken.insert(path.clone(), article_name(page));
+
+            let target = html_templates
+                .canonical_url(&page.url)
+                .unwrap_or_else(|| page.url.clone());
+            redirects.push(RedirectPage {
+                path,
+                html: redirect_html(&target),
+            });
+        }
+    }
+
+    Ok(redirects)
+}
+
+fn article_name(page: &ArticlePage) -> String {
+    format!("{}/{}", page.article.id.group, page.article.id.slug)
+}
+
+// The url in the configured style and the path to write the page, if it stays in the site
+fn alias_page(html_templates: &WebPageHtmlTemplates, alias: &str) -> Option<(String, PathBuf)> {
+    let alias = format!("/{}", alias.trim().trim_start_matches('/'));
+    let last = alias.rsplit('/').next().unwrap_or_default();
+    let alias = if last.is_empty() || last.contains('.') {
+        alias
+    } else {
+        format!("{alias}/")
+    };
+
+    let url = html_templates.page_url(&alias);
+    let path = crate::permalink::page_path(&url);
+
+    let in_site = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    (in_site && path != Path::new("index.html")).then_some((url, path))
+}
+
+fn redirect_html(target: &str) -> String {
+    let target = escape_xml(target);
+
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Redirecting</title><link rel="canonical" href="{target}"><meta http-equiv="refresh" content="0; url={target}"><meta name="robots" content="noindex"></head><body><a href="{target}">{target}</a></body></html>"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use loss72_platemaker_core::model::{Article, ArticleIdentifier, ArticleMetadata};
+
+    use super::generate_redirect_pages;
+    use crate::{UrlStyle, WebPageHtmlTemplates, WebsiteGenerationError, articles::ArticlePage};
+
+    fn article(slug: &str, aliases: &[&str]) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: slug.to_string(),
+                date: (2025, 1, Some(3)),
+            },
+            metadata: ArticleMetadata {
+                title: slug.to_string(),
+                brief: String::new(),
+                aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    fn templates(url_style: Option<UrlStyle>) -> WebPageHtmlTemplates {
+        WebPageHtmlTemplates {
+            url_style,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn aliases_redirect_in_the_url_style() {
+        let hello = article("03_hello", &["/2025/hello", "old.html"]);
+        let pages = [ArticlePage::listing(&templates(None), &hello)];
+
+        let redirects =
+            generate_redirect_pages(&templates(None), &pages, &[]).expect("aliases to be valid");
+        assert_eq!(
+            redirects
+                .iter()
+                .map(|page| page.path.clone())
+                .collect::<Vec<_>>(),
+            [
+                PathBuf::from("2025/hello/index.html"),
+                PathBuf::from("old.html")
+            ]
+        );
+        assert!(redirects[0].html.contains(
+            r#"<meta http-equiv="refresh" content="0; url=/articles/202501/03_hello.html">"#
+        ));
+        assert!(
+            redirects[0]
+                .html
+                .contains(r#"<link rel="canonical" href="/articles/202501/03_hello.html">"#)
+        );
+
+        let redirects = generate_redirect_pages(&templates(Some(UrlStyle::FileUrls)), &pages, &[])
+            .expect("aliases to be valid");
+        assert_eq!(redirects[0].path, PathBuf::from("2025/hello.html"));
+
+        assert!(matches!(
+            generate_redirect_pages(
+                &templates(None),
+                &[ArticlePage::listing(&templates(None), &article("04_up", &["../up/"]))],
+                &[]
+            ),
+            Err(WebsiteGenerationError::InvalidAlias(_, alias)) if alias == "../up/"
+        ));
+    }
+
+    #[test]
+    fn conflicting_aliases_name_both_articles() {
+        let first = article("03_hello", &["/old/"]);
+        let second = article("04_again", &["old"]);
+        let pages = [
+            ArticlePage::listing(&templates(None), &first),
+            ArticlePage::listing(&templates(None), &second),
+        ];
+
+        assert!(matches!(
+            generate_redirect_pages(&templates(None), &pages, &[]),
+            Err(WebsiteGenerationError::ConflictingAlias { alias, article, other })
+                if alias == "/old/" && article == "202501/04_again" && other == "202501/03_hello"
+        ));
+    }
+
+    #[test]
+    fn aliases_cannot_take_the_other_pages() {
+        let hello = article("03_hello", &["/tags/rust/", "sitemap.xml"]);
+        let pages = [ArticlePage::listing(&templates(None), &hello)];
+
+        assert!(matches!(
+            generate_redirect_pages(&templates(None), &pages, &[Path::new("tags/rust/index.html")]),
+            Err(WebsiteGenerationError::ConflictingAlias { alias, other, .. })
+                if alias == "/tags/rust/" && other == "tags/rust/index.html"
+        ));
+        assert!(matches!(
+            generate_redirect_pages(&templates(None), &pages[..], &[Path::new("sitemap.xml")]),
+            Err(WebsiteGenerationError::ConflictingAlias { alias, other, .. })
+                if alias == "/sitemap.xml" && other == "sitemap.xml"
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use chrono::{Datelike, NaiveDate};
+use loss72_platemaker_core::model::{Article, ArticleIdentifier};
+
+use crate::tags::tag_slug;
+
+pub const DEFAULT_RELATED_ARTICLES: usize = 5;
+
+// Up to `count` articles for each of `articles` in the same order, sharing the most tags with
+// it and then the closest in date. Ones sharing no tag are never related. `others` may be
+// related as well, which are none of `articles`.
+pub fn related_articles<'article>(
+    articles: &'article [Article],
+    others: &[&'article Article],
+    count: usize,
+) -> Vec<Vec<&'article Article>> {
+    let candidates = articles
+        .iter()
+        .chain(others.iter().copied())
+        .collect::<Vec<_>>();
+    let tags = candidates
+        .iter()
+        .map(|article| {
+            article
+                .metadata
+                .tags
+                .iter()
+                .map(|tag| tag_slug(tag))
+                .filter(|slug| !slug.is_empty())
+                .collect::<HashSet<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // `articles` come first in `candidates`
+    articles
+        .iter()
+        .zip(&tags)
+        .map(|(article, article_tags)| {
+            let mut candidates = candidates
+                .iter()
+                .copied()
+                .zip(&tags)
+                .filter(|(other, _)| other.id != article.id)
+                .map(|(other, other_tags)| {
+                    let shared = article_tags.intersection(other_tags).count();
+                    let distance = days(&article.id).abs_diff(days(&other.id));
+                    (shared, distance, other)
+                })
+                .filter(|(shared, _, _)| *shared > 0)
+                .collect::<Vec<_>>();
+
+            // The newer one comes first for the same distance, to be deterministic
+            candidates.sort_by(|left, right| {
+                right
+                    .0
+                    .cmp(&left.0)
+                    .then(left.1.cmp(&right.1))
+                    .then(right.2.id.cmp(&left.2.id))
+            });
+
+            candidates
+                .into_iter()
+                .take(count)
+                .map(|(_, _, other)| other)
+                .collect()
+        })
+        .collect()
+}
+
+// Articles without the day are counted from the first day of the month
+fn days(id: &ArticleIdentifier) -> i64 {
+    let (year, month, day) = id.date;
+
+    NaiveDate::from_ymd_opt(year as i32, month.into(), day.unwrap_or(1).into())
+        .map(|date| date.num_days_from_ce().into())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{Article, ArticleIdentifier, ArticleMetadata};
+
+    use super::related_articles;
+
+    fn article(day: u8, tags: &[&str]) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: format!("{day:02}_article"),
+                date: (2025, 1, Some(day)),
+            },
+            metadata: ArticleMetadata {
+                title: format!("Article {day}"),
+                brief: String::new(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn ranks_by_shared_tags_then_date() {
+        let articles = [
+            article(10, &["rust", "web"]),
+            article(1, &["Rust", "web"]),
+            article(12, &["rust"]),
+            article(8, &["rust"]),
+            article(20, &["cooking"]),
+            article(11, &[]),
+        ];
+
+        let slugs = related_articles(&articles, &[], 3)
+            .into_iter()
+            .map(|related| {
+                related
+                    .iter()
+                    .map(|article| article.id.slug.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(slugs[0], ["01_article", "12_article", "08_article"]);
+        assert_eq!(slugs[2], ["10_article", "08_article", "01_article"]);
+        assert!(slugs[4].is_empty());
+        assert!(slugs[5].is_empty());
+    }
+
+    #[test]
+    fn others_are_related_too() {
+        let articles = [article(10, &["rust"])];
+        let others = [article(12, &["rust"]), article(20, &["cooking"])];
+
+        let related = related_articles(&articles, &[&others[1], &others[0]], 3);
+        assert_eq!(related.len(), 1);
+        assert_eq!(
+            related[0]
+                .iter()
+                .map(|article| article.id.slug.as_str())
+                .collect::<Vec<_>>(),
+            ["12_article"]
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use crate::sitemap::{SITEMAP_FILE, absolute_url};
+
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+// for all the crawlers, pointing to the sitemap which is only written with `base_url`
+pub fn generate_robots_txt(base_url: Option<&str>, disallow: &[String]) -> String {
+    let mut lines = vec!["User-agent: *".to_string()];
+
+    if disallow.is_empty() {
+        // An empty rule allows everything
+        lines.push("Disallow:".to_string());
+    }
+    lines.extend(disallow.iter().map(|path| {
+        let path = path.trim();
+        if path.starts_with(['/', '*']) {
+            format!("Disallow: {path}")
+        } else {
+            format!("Disallow: /{path}")
+        }
+    }));
+
+    if let Some(base_url) = base_url {
+        lines.push(String::new());
+        lines.push(format!("Sitemap: {}", absolute_url(base_url, SITEMAP_FILE)));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_robots_txt;
+
+    #[test]
+    fn sitemap_is_referred_with_base_url() {
+        assert_eq!(
+            generate_robots_txt(
+                Some("https://example.com/blog/"),
+                &["/drafts/".to_string(), "private.html".to_string()]
+            ),
+            "User-agent: *\nDisallow: /drafts/\nDisallow: /private.html\n\nSitemap: https://example.com/blog/sitemap.xml\n"
+        );
+        assert_eq!(generate_robots_txt(None, &[]), "User-agent: *\nDisallow:\n");
+    }
+}
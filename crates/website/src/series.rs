@@ -0,0 +1,313 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use loss72_platemaker_construct::ConstructFile;
+use loss72_platemaker_core::{
+    log,
+    model::{Article, GenerationContext},
+    util::escape_xml,
+};
+use loss72_platemaker_template::Placeholder;
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError,
+    articles::{ArticlePage, listing_variables, render_article_list},
+    tags::tag_slug,
+};
+
+// Articles with the same `series`, of which the name is the one seen first
+#[derive(Clone, Debug)]
+pub struct Series<'article> {
+    pub name: &'article str,
+    pub slug: String,
+    // by `series_order`, and then the dates for the ones without it
+    pub parts: Vec<&'article Article>,
+}
+
+impl Series<'_> {
+    // 0-based
+    pub fn part_of(&self, article: &Article) -> Option<usize> {
+        self.parts.iter().position(|part| part.id == article.id)
+    }
+
+    pub fn url(&self) -> String {
+        format!("/series/{}/", self.slug)
+    }
+}
+
+// by the slug, which is the name like the tags are
+pub fn collect_series<'article>(
+    articles: impl IntoIterator<Item = &'article Article>,
+) -> Vec<Series<'article>> {
+    let mut series = BTreeMap::<String, Series>::new();
+
+    for article in articles {
+        let Some(name) = article.metadata.series.as_deref() else {
+            continue;
+        };
+        let slug = tag_slug(name);
+        if slug.is_empty() {
+            continue;
+        }
+
+        series
+            .entry(slug.clone())
+            .or_insert_with(|| Series {
+                name,
+                slug,
+                parts: vec![],
+            })
+            .parts
+            .push(article);
+    }
+
+    series
+        .into_values()
+        .map(|mut series| {
+            series.parts.sort_by(|left, right| {
+                let order = |article: &Article| {
+                    let order = article.metadata.series_order;
+                    (order.is_none(), order)
+                };
+                order(left).cmp(&order(right)).then(left.id.cmp(&right.id))
+            });
+            series
+        })
+        .collect()
+}
+
+// "Part 2 of 3" with the links to the other parts for `${series}`, and the name links to the
+// page of the series with `_series.html`
+pub(crate) fn render_series_navigation(
+    html_templates: &WebPageHtmlTemplates,
+    series: &Series,
+    article: &Article,
+) -> String {
+    let Some(part) = series.part_of(article) else {
+        return String::new();
+    };
+
+    let name = escape_xml(series.name);
+    let name = if html_templates.series.is_some() {
+        format!(
+            r#"<a href="{}">{name}</a>"#,
+            escape_xml(&html_templates.page_url(&series.url()))
+        )
+    } else {
+        name
+    };
+
+    let parts = series
+        .parts
+        .iter()
+        .enumerate()
+        .map(|(index, other)| {
+            let title = escape_xml(&other.metadata.title);
+            if index == part {
+                format!(r#"<li class="current">{title}</li>"#)
+            } else {
+                format!(
+                    r#"<li><a href="{}">{title}</a></li>"#,
+                    escape_xml(&html_templates.permalink.url(&other.id))
+                )
+            }
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<nav class="series"><p class="series-title">Part {} of {} in {name}</p><ol class="series-parts">{parts}</ol></nav>"#,
+        part + 1,
+        series.parts.len()
+    )
+}
+
+pub struct SeriesPage {
+    pub name: String,
+    pub html: String,
+    pub path: PathBuf,
+}
+
+impl<'p> From<&'p SeriesPage> for ConstructFile<'p> {
+    fn from(value: &'p SeriesPage) -> Self {
+        ConstructFile {
+            path: &value.path,
+            content: &value.html,
+        }
+    }
+}
+
+// `/series/{slug}/` with `_series.html`, listing the parts in the order
+pub fn generate_series_pages(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[ArticlePage],
+    ctx: &GenerationContext,
+) -> OutputResult<Vec<SeriesPage>> {
+    let series = collect_series(articles.iter().map(|page| page.article));
+    if series.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let Some(template) = &html_templates.series else {
+        log!(step: "_series.html is not found, skipping series pages");
+        return Ok(vec![]);
+    };
+
+    log!(section: "Generating HTML for {} series pages", series.len());
+
+    let placeholder = Placeholder::from_strs("${", "}", None)
+        .expect("Regex is validated to include the capture group");
+
+    series
+        .iter()
+        .map(|series| {
+            let pages = series
+                .parts
+                .iter()
+                .filter_map(|part| articles.iter().find(|page| page.article.id == part.id))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let default_url = series.url();
+            let mut placeholder_contents = HashMap::from([
+                ("series", series.name.to_string()),
+                ("count", series.parts.len().to_string()),
+                (
+                    "articles",
+                    render_article_list(html_templates, &pages, ctx)?,
+                ),
+                ("style", html_templates.index_style.clone()),
+                (
+                    "canonical",
+                    html_templates.canonical_link(&html_templates.page_url(&default_url)),
+                ),
+            ]);
+            placeholder_contents.extend(listing_variables(ctx));
+
+            let html = placeholder
+                .partially_fill_placeholders(template, |name| {
+                    placeholder_contents
+                        .get(name)
+                        .cloned()
+                        .or_else(|| html_templates.site.get(name))
+                })
+                .map_err(WebsiteGenerationError::InvalidPlaceholder)?;
+
+            let (html, path) = html_templates.place_page(&html, &default_url);
+
+            Ok(SeriesPage {
+                name: series.name.to_string(),
+                html,
+                path,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use loss72_platemaker_core::model::{
+        Article, ArticleIdentifier, ArticleMetadata, GenerationContext,
+    };
+
+    use super::{collect_series, render_series_navigation};
+    use crate::{WebPageHtmlTemplates, generate_article_pages};
+
+    fn article(day: u8, series: Option<&str>, series_order: Option<u32>) -> Article {
+        Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: format!("{day:02}_article"),
+                date: (2025, 1, Some(day)),
+            },
+            metadata: ArticleMetadata {
+                title: format!("Article {day}"),
+                brief: "brief".to_string(),
+                series: series.map(str::to_string),
+                series_order,
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn parts_are_ordered_and_then_dated() {
+        let articles = [
+            article(1, Some("Rust Tips"), None),
+            article(2, Some("rust tips"), Some(1)),
+            article(3, None, Some(1)),
+            article(4, Some("Rust Tips"), None),
+            article(5, Some("Other"), None),
+        ];
+
+        let series = collect_series(&articles)
+            .into_iter()
+            .map(|series| {
+                let slugs = series.parts.iter().map(|part| part.id.slug.as_str());
+                (series.name, series.slug, slugs.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            series,
+            [
+                ("Other", "other".to_string(), vec!["05_article"]),
+                (
+                    "Rust Tips",
+                    "rust-tips".to_string(),
+                    vec!["02_article", "01_article", "04_article"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn article_pages_are_navigated_in_the_series() {
+        let templates = WebPageHtmlTemplates {
+            article: "${series_part}/${series_count}|${series}".to_string(),
+            ..Default::default()
+        };
+        let articles = [
+            article(1, Some("Rust"), None),
+            article(2, None, None),
+            article(3, Some("Rust"), None),
+        ];
+        let pages = generate_article_pages(&templates, &articles, &GenerationContext::default())
+            .expect("article pages");
+
+        assert_eq!(
+            pages[2].html,
+            concat!(
+                r#"2/2|<nav class="series"><p class="series-title">Part 2 of 2 in Rust</p>"#,
+                r#"<ol class="series-parts"><li><a href="/articles/202501/01_article.html">Article 1</a></li>"#,
+                r#"<li class="current">Article 3</li></ol></nav>"#,
+            )
+        );
+        assert_eq!(pages[1].html, "/|");
+    }
+
+    #[test]
+    fn hostile_names_are_escaped_in_the_series_directory() {
+        let templates = WebPageHtmlTemplates {
+            series: Some("${series}".to_string()),
+            ..Default::default()
+        };
+        let articles = [article(1, Some("../../R&D <1>"), None)];
+        let series = collect_series(&articles);
+
+        assert_eq!(series[0].url(), "/series/r-d-1/");
+        assert_eq!(
+            render_series_navigation(&templates, &series[0], &articles[0]),
+            concat!(
+                r#"<nav class="series"><p class="series-title">Part 1 of 1 in "#,
+                r#"<a href="/series/r-d-1/">../../R&amp;D &lt;1&gt;</a></p>"#,
+                r#"<ol class="series-parts"><li class="current">Article 1</li></ol></nav>"#,
+            )
+        );
+    }
+}
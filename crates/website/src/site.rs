@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+const NAMESPACE: &str = "site.";
+
+// `[site]` in the configuration, given to the templates as `${site.name}`.
+// Nested tables are flattened with dots, like `${site.author.name}`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(from = "HashMap<String, SiteValue>")]
+pub struct SiteVariables(HashMap<String, String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SiteValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Table(HashMap<String, SiteValue>),
+}
+
+impl From<HashMap<String, SiteValue>> for SiteVariables {
+    fn from(value: HashMap<String, SiteValue>) -> Self {
+        let mut variables = HashMap::new();
+        flatten(value, "", &mut variables);
+
+        SiteVariables(variables)
+    }
+}
+
+fn flatten(
+    table: HashMap<String, SiteValue>,
+    prefix: &str,
+    variables: &mut HashMap<String, String>,
+) {
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+
+        let value = match value {
+            SiteValue::String(value) => value,
+            SiteValue::Integer(value) => value.to_string(),
+            SiteValue::Float(value) => value.to_string(),
+            SiteValue::Boolean(value) => value.to_string(),
+            SiteValue::Table(table) => {
+                flatten(table, &format!("{key}."), variables);
+                continue;
+            }
+        };
+
+        variables.insert(key, value);
+    }
+}
+
+impl SiteVariables {
+    // By the placeholder name including the namespace. The undefined ones are left to fail
+    // as invalid placeholders.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.0.get(name.strip_prefix(NAMESPACE)?).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{SiteValue, SiteVariables};
+
+    #[test]
+    fn nested_tables_are_flattened() {
+        let site = SiteVariables::from(HashMap::from([
+            ("title".to_string(), SiteValue::String("Loss72".to_string())),
+            ("since".to_string(), SiteValue::Integer(2024)),
+            (
+                "author".to_string(),
+                SiteValue::Table(HashMap::from([(
+                    "name".to_string(),
+                    SiteValue::String("raiga".to_string()),
+                )])),
+            ),
+        ]));
+
+        assert_eq!(site.get("site.title").as_deref(), Some("Loss72"));
+        assert_eq!(site.get("site.since").as_deref(), Some("2024"));
+        assert_eq!(site.get("site.author.name").as_deref(), Some("raiga"));
+        assert_eq!(site.get("site.author"), None);
+        assert_eq!(site.get("title"), None);
+        assert_eq!(site.get("site.missing"), None);
+    }
+}
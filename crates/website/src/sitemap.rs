@@ -0,0 +1,155 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use loss72_platemaker_construct::ConstructionPlan;
+use loss72_platemaker_core::{log, util::escape_xml};
+
+use crate::{NOT_FOUND_FILE, articles::ArticlePage, redirects::RedirectPage};
+
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+
+pub fn generate_sitemap(
+    plan: &ConstructionPlan,
+    root: &Path,
+    pages: &[PathBuf],
+    articles: &[ArticlePage],
+    redirects: &[RedirectPage],
+    base_url: &str,
+) -> String {
+    log!(section: "Generating sitemap");
+
+    let article_dates = articles
+        .iter()
+        .map(|page| {
+            (
+                format!("/{}", page.path.to_string_lossy()),
+                page.article.id.date,
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let newest_date = articles.iter().map(|page| page.article.id.date).max();
+    // which only point to the articles listed already
+    let redirects = redirects
+        .iter()
+        .map(|page| page.path.as_path())
+        .collect::<HashSet<_>>();
+
+    let mut paths = plan
+        .files
+        .iter()
+        .filter_map(|(path, _)| path.strip_prefix(root).ok())
+        .filter(|path| !redirects.contains(path) && *path != Path::new(NOT_FOUND_FILE))
+        .chain(pages.iter().map(PathBuf::as_path))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .map(|path| format!("/{}", path.to_string_lossy()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let urls = paths
+        .iter()
+        .map(|path| {
+            let lastmod = if path == "/index.html" {
+                newest_date
+            } else {
+                article_dates.get(path).copied()
+            };
+
+            let lastmod = lastmod
+                .map(|(year, month, day)| match day {
+                    Some(day) => format!("<lastmod>{year:04}-{month:02}-{day:02}</lastmod>"),
+                    None => format!("<lastmod>{year:04}-{month:02}</lastmod>"),
+                })
+                .unwrap_or_default();
+
+            format!(
+                "<url><loc>{}</loc>{lastmod}</url>",
+                escape_xml(&absolute_url(base_url, path.trim_end_matches("index.html")))
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">{urls}</urlset>"#
+    )
+}
+
+pub(crate) fn absolute_url(base_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use loss72_platemaker_construct::ConstructionPlan;
+    use loss72_platemaker_core::model::{Article, ArticleIdentifier};
+
+    use super::generate_sitemap;
+    use crate::{articles::ArticlePage, redirects::RedirectPage};
+
+    #[test]
+    fn sitemap_lists_the_pages_with_the_dates() {
+        let article = Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: "03_a&b".to_string(),
+                date: (2025, 1, Some(3)),
+            },
+            metadata: Default::default(),
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        };
+        let articles = [ArticlePage {
+            article: &article,
+            html: String::new(),
+            path: PathBuf::from("articles/202501/03_a&b.html"),
+            url: "/articles/202501/03_a&b.html".to_string(),
+        }];
+        let redirects = [RedirectPage {
+            path: PathBuf::from("old.html"),
+            html: String::new(),
+        }];
+        let plan = ConstructionPlan {
+            dirs: vec![],
+            files: [
+                "index.html",
+                "articles/202501/03_a&b.html",
+                "old.html",
+                "404.html",
+                "feed.xml",
+                "tags/rust/index.html",
+            ]
+            .into_iter()
+            .map(|path| (Path::new("out").join(path), ""))
+            .collect(),
+        };
+
+        assert_eq!(
+            generate_sitemap(
+                &plan,
+                Path::new("out"),
+                &[PathBuf::from("about.html")],
+                &articles,
+                &redirects,
+                "https://example.com/blog/",
+            ),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+                "<url><loc>https://example.com/blog/about.html</loc></url>",
+                "<url><loc>https://example.com/blog/articles/202501/03_a&amp;b.html</loc><lastmod>2025-01-03</lastmod></url>",
+                "<url><loc>https://example.com/blog/</loc><lastmod>2025-01-03</lastmod></url>",
+                "<url><loc>https://example.com/blog/tags/rust/</loc></url>",
+                "</urlset>",
+            )
+        );
+    }
+}
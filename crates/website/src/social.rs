@@ -0,0 +1,169 @@
+use loss72_platemaker_core::{model::Article, util::escape_xml};
+
+use crate::{
+    WebPageHtmlTemplates,
+    assets::resolve,
+    sitemap::absolute_url,
+    text::{strip_tags, truncate_text},
+};
+
+const DESCRIPTION_LENGTH: usize = 200;
+
+// Open Graph and Twitter card `<meta>` tags of an article page for `${og}`.
+// Crawlers need absolute urls, so the url and the image are left out without `base_url`.
+pub(crate) fn render_social_meta(
+    html_templates: &WebPageHtmlTemplates,
+    article: &Article,
+    url: &str,
+) -> String {
+    let title = &article.metadata.title;
+    let description = description(article);
+    let canonical = html_templates.canonical_url(url);
+    let image = image_url(html_templates, article);
+
+    let mut tags = vec![
+        ("og:type", "article".to_string()),
+        ("og:title", title.clone()),
+        ("og:description", description.clone()),
+    ];
+    tags.extend(canonical.map(|canonical| ("og:url", canonical)));
+    tags.extend(image.clone().map(|image| ("og:image", image)));
+
+    let card = if image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    tags.extend([
+        ("twitter:card", card.to_string()),
+        ("twitter:title", title.clone()),
+        ("twitter:description", description),
+    ]);
+    tags.extend(image.map(|image| ("twitter:image", image)));
+
+    tags.into_iter()
+        .map(|(property, content)| {
+            // Open Graph uses `property`, and Twitter cards use `name`
+            let attribute = if property.starts_with("og:") {
+                "property"
+            } else {
+                "name"
+            };
+            format!(
+                r#"<meta {attribute}="{property}" content="{}">"#,
+                escape_xml(&content)
+            )
+        })
+        .collect()
+}
+
+// The brief, or the lead of the article in plain text without it
+fn description(article: &Article) -> String {
+    if !article.metadata.brief.trim().is_empty() {
+        return article.metadata.brief.clone();
+    }
+
+    let lead = article.excerpt.as_deref().unwrap_or(&article.content);
+    truncate_text(&strip_tags(lead), DESCRIPTION_LENGTH)
+}
+
+// `cover` in the frontmatter, or the first image in the article. Both are relative to the
+// default location of the article like the images in it.
+fn image_url(html_templates: &WebPageHtmlTemplates, article: &Article) -> Option<String> {
+    let src = article
+        .metadata
+        .cover
+        .as_deref()
+        .or_else(|| article.images.first().map(|image| image.src.as_str()))
+        .filter(|src| !src.is_empty())?;
+
+    if src.contains("://") {
+        return Some(src.to_string());
+    }
+
+    let base_url = html_templates.base_url.as_deref()?;
+    let (path, suffix) = src.split_at(src.find(['?', '#']).unwrap_or(src.len()));
+    let path = resolve(path, &html_templates.default_permalink.url(&article.id));
+    let path = html_templates
+        .assets
+        .rewrite_url(&path, "/")
+        .unwrap_or(path);
+
+    Some(absolute_url(base_url, &format!("{path}{suffix}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use loss72_platemaker_core::model::{
+        Article, ArticleIdentifier, ArticleImage, ArticleMetadata,
+    };
+
+    use super::render_social_meta;
+    use crate::WebPageHtmlTemplates;
+
+    fn templates(base_url: Option<&str>) -> WebPageHtmlTemplates {
+        WebPageHtmlTemplates {
+            base_url: base_url.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn meta_tags_use_the_first_image_or_cover() {
+        let mut article = Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: "03_hello".to_string(),
+                date: (2025, 1, Some(3)),
+            },
+            metadata: ArticleMetadata {
+                title: "Hello & world".to_string(),
+                brief: String::new(),
+                ..Default::default()
+            },
+            content: "<p>Lead <em>text</em></p>".to_string(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![ArticleImage {
+                src: "./assets/pic.png".to_string(),
+                source: PathBuf::new(),
+                width: 800,
+                height: 600,
+            }],
+        };
+        let with_base_url = templates(Some("https://example.com/"));
+        let url = "/articles/202501/03_hello.html";
+
+        assert_eq!(
+            render_social_meta(&with_base_url, &article, url),
+            concat!(
+                r#"<meta property="og:type" content="article">"#,
+                r#"<meta property="og:title" content="Hello &amp; world">"#,
+                r#"<meta property="og:description" content="Lead text">"#,
+                r#"<meta property="og:url" content="https://example.com/articles/202501/03_hello.html">"#,
+                r#"<meta property="og:image" content="https://example.com/articles/202501/assets/pic.png">"#,
+                r#"<meta name="twitter:card" content="summary_large_image">"#,
+                r#"<meta name="twitter:title" content="Hello &amp; world">"#,
+                r#"<meta name="twitter:description" content="Lead text">"#,
+                r#"<meta name="twitter:image" content="https://example.com/articles/202501/assets/pic.png">"#,
+            )
+        );
+
+        article.metadata.cover = Some("https://cdn.example.com/cover.png".to_string());
+        assert!(
+            render_social_meta(&with_base_url, &article, url).contains(
+                r#"<meta property="og:image" content="https://cdn.example.com/cover.png">"#
+            )
+        );
+
+        article.metadata.cover = None;
+        article.images.clear();
+        let html = render_social_meta(&with_base_url, &article, url);
+        assert!(!html.contains("og:image"));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+
+        assert!(!render_social_meta(&templates(None), &article, url).contains("og:url"));
+    }
+}
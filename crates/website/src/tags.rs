@@ -0,0 +1,181 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use loss72_platemaker_construct::ConstructFile;
+use loss72_platemaker_core::{log, model::GenerationContext};
+use loss72_platemaker_template::Placeholder;
+
+use crate::{
+    OutputResult, WebPageHtmlTemplates, WebsiteGenerationError,
+    articles::{ArticlePage, listing_variables, render_article_list},
+};
+
+pub struct TagPage {
+    pub name: String,
+    pub html: String,
+    pub path: PathBuf,
+}
+
+impl<'p> From<&'p TagPage> for ConstructFile<'p> {
+    fn from(value: &'p TagPage) -> Self {
+        ConstructFile {
+            path: &value.path,
+            content: &value.html,
+        }
+    }
+}
+
+// One path segment of the url, keeping the letters of any script like `日記`. The characters
+// which would make more of the segments or end the path, like `/`, `?` and `#`, separate the
+// words as the spaces do, and the words only of dots like `..` are left out.
+pub fn tag_slug(tag: &str) -> String {
+    tag.split(|char: char| !(char.is_alphanumeric() || ['-', '_', '+', '.'].contains(&char)))
+        .filter(|word| !word.chars().all(|char| char == '.'))
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+pub fn generate_tag_pages(
+    html_templates: &WebPageHtmlTemplates,
+    articles: &[ArticlePage],
+    ctx: &GenerationContext,
+) -> OutputResult<Vec<TagPage>> {
+    // slug -> (name seen first, articles)
+    let mut tags = BTreeMap::<String, (&str, Vec<ArticlePage>)>::new();
+
+    for page in articles {
+        let mut slugs = vec![];
+
+        for tag in &page.article.metadata.tags {
+            let slug = tag_slug(tag);
+            if slug.is_empty() || slugs.contains(&slug) {
+                continue;
+            }
+
+            tags.entry(slug.clone())
+                .or_insert_with(|| (tag, vec![]))
+                .1
+                .push(page.clone());
+            slugs.push(slug);
+        }
+    }
+
+    if tags.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let Some(template) = &html_templates.tag else {
+        log!(warn: "Articles have tags but _tag.html is not found, skipping tag pages");
+        return Ok(vec![]);
+    };
+
+    log!(section: "Generating HTML for {} tag pages", tags.len());
+
+    let placeholder = Placeholder::from_strs("${", "}", None)
+        .expect("Regex is validated to include the capture group");
+
+    tags.into_iter()
+        .map(|(slug, (name, pages))| {
+            let default_url = format!("/tags/{slug}/");
+            let mut placeholder_contents = HashMap::from([
+                ("tag", name.to_string()),
+                (
+                    "articles",
+                    render_article_list(html_templates, &pages, ctx)?,
+                ),
+                ("style", html_templates.index_style.clone()),
+                (
+                    "canonical",
+                    html_templates.canonical_link(&html_templates.page_url(&default_url)),
+                ),
+            ]);
+            placeholder_contents.extend(listing_variables(ctx));
+
+            let html = placeholder
+                .partially_fill_placeholders(template, |name| {
+                    placeholder_contents
+                        .get(name)
+                        .cloned()
+                        .or_else(|| html_templates.site.get(name))
+                })
+                .map_err(WebsiteGenerationError::InvalidPlaceholder)?;
+
+            let (html, path) = html_templates.place_page(&html, &default_url);
+
+            Ok(TagPage {
+                name: name.to_string(),
+                html,
+                path,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use loss72_platemaker_core::model::{Article, ArticleIdentifier, ArticleMetadata};
+
+    use super::{generate_tag_pages, tag_slug};
+    use crate::{WebPageHtmlTemplates, articles::ArticlePage};
+
+    #[test]
+    fn hostile_tags_stay_in_the_tag_directory() {
+        let article = Article {
+            id: ArticleIdentifier {
+                group: "202501".to_string(),
+                slug: "03_hello".to_string(),
+                date: (2025, 1, Some(3)),
+            },
+            metadata: ArticleMetadata {
+                title: "Hello".to_string(),
+                tags: vec!["../../../etc/x".to_string(), "C/C++".to_string()],
+                ..Default::default()
+            },
+            content: String::new(),
+            excerpt: None,
+            toc: vec![],
+            images: vec![],
+        };
+        let pages = [ArticlePage {
+            article: &article,
+            html: String::new(),
+            path: PathBuf::from("articles/202501/03_hello.html"),
+            url: "/articles/202501/03_hello.html".to_string(),
+        }];
+        let html_templates = WebPageHtmlTemplates {
+            tag: Some("${tag}".to_string()),
+            ..Default::default()
+        };
+
+        let tag_pages = generate_tag_pages(&html_templates, &pages, &Default::default())
+            .expect("tag pages to be generated");
+
+        assert_eq!(
+            tag_pages
+                .iter()
+                .map(|page| (page.name.as_str(), page.path.as_path()))
+                .collect::<Vec<_>>(),
+            [
+                ("C/C++", Path::new("tags/c-c++/index.html")),
+                ("../../../etc/x", Path::new("tags/etc-x/index.html")),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizes_tag_slug() {
+        assert_eq!(tag_slug("rust"), "rust");
+        assert_eq!(tag_slug("Web Development"), "web-development");
+        assert_eq!(tag_slug("  Rust  Lang "), "rust-lang");
+        assert_eq!(tag_slug("日記"), "日記");
+        assert_eq!(tag_slug("C/C++"), "c-c++");
+        assert_eq!(tag_slug("../../x"), "x");
+        assert_eq!(tag_slug(r"..\.."), "");
+        assert_eq!(tag_slug("a?b#c%20"), "a-b-c-20");
+    }
+}
@@ -0,0 +1,62 @@
+pub(crate) fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for char in html.chars() {
+        match char {
+            '<' => in_tag = true,
+            // Tags separate words, e.g. `<li>a</li><li>b</li>`
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(char),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+// Cuts the text (which may contain character references, but no tags) down to `length`
+// characters, without splitting a reference like `&amp;` in the middle.
+pub(crate) fn truncate_text(text: &str, length: usize) -> String {
+    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut truncated = String::new();
+    let mut count = 0;
+    let mut chars = words.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        if count == length {
+            truncated.push('…');
+            return truncated;
+        }
+
+        truncated.push(char);
+        if char == '&' {
+            while let Some(char) = chars.next_if(|char| *char != ';' && !char.is_whitespace()) {
+                truncated.push(char);
+            }
+            truncated.extend(chars.next_if_eq(&';'));
+        }
+
+        count += 1;
+    }
+
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_tags, truncate_text};
+
+    #[test]
+    fn truncates_without_breaking_markup() {
+        let text = strip_tags("<p>Tom &amp; <em>Jerry</em></p>\n<p>again</p>");
+
+        assert_eq!(truncate_text(&text, 100), "Tom &amp; Jerry again");
+        assert_eq!(truncate_text(&text, 5), "Tom &amp;…");
+        assert_eq!(truncate_text(&text, 4), "Tom …");
+    }
+}
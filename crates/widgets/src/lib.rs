@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 pub mod ai;
 pub mod article_type;
+pub mod shortcode;
 pub mod sources;
 
 pub trait Widget {
@@ -54,7 +55,7 @@ impl<T: GroundingWidget> Widget for T {
     }
 }
 
-#[derive(Clone, Deserialize, Debug, Default)]
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
 pub struct Widgets {
     #[serde(default)]
     pub ai: ai::AiUsage,
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ShortcodeArgs {
+    pub positional: Vec<String>,
+    pub named: HashMap<String, String>,
+}
+
+type ShortcodeHandler = fn(&ShortcodeArgs) -> Result<String, String>;
+
+const SHORTCODES: [(&str, ShortcodeHandler); 1] = [("youtube", youtube)];
+
+// `None` when no shortcode is registered with the name
+pub fn expand_shortcode(name: &str, args: &ShortcodeArgs) -> Option<Result<String, String>> {
+    SHORTCODES
+        .iter()
+        .find(|(registered, _)| *registered == name)
+        .map(|(_, handler)| handler(args))
+}
+
+fn youtube(args: &ShortcodeArgs) -> Result<String, String> {
+    let id = args
+        .named
+        .get("id")
+        .or(args.positional.first())
+        .ok_or("the video id is required, e.g. `{{< youtube abc123 >}}`")?;
+
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+    {
+        return Err(format!("`{id}` is not a valid video id"));
+    }
+
+    Ok(format!(
+        r#"<div class="youtube"><iframe src="https://www.youtube-nocookie.com/embed/{id}" title="YouTube video" loading="lazy" allow="accelerometer; clipboard-write; encrypted-media; gyroscope; picture-in-picture" allowfullscreen></iframe></div>"#
+    ))
+}